@@ -1,5 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::PathBuf;
+
 use bevy::{
     app::TaskPoolThreadAssignmentPolicy,
     camera_controller::pan_camera::{PanCamera, PanCameraPlugin},
@@ -12,25 +14,21 @@ use wdn_physics::{
     PhysicsPlugin as WdnPhysicsPlugin,
     kinematics::Position,
     layer::{Layer, LayerStack},
-    tile::{
-        index::TileIndex,
-        material::{TileKind, TileMaterial},
-        position::TilePosition,
-        storage::TileStorageMut,
-    },
+    tile::{material::TileMaterial, position::TilePosition, storage::TileStorageMut},
 };
 use wdn_render::{
     RenderPlugin as WdnRenderPlugin, RenderSystems,
     dev::{DevPlugin as WdnDevRenderPlugin, DevRenderSettings},
     layer::LayerView,
 };
-use wdn_save::SavePlugin as WdnSavePlugin;
-use wdn_tasks::TasksPlugin as WdnTasksPlugin;
-use wdn_ui::UiPlugin as WdnUiPlugin;
+use wdn_save::{SavePlugin as WdnSavePlugin, SaveReplay, StartRecording, handle_save_messages};
+use wdn_tasks::{TasksPlugin as WdnTasksPlugin, task::NoTasks};
+use wdn_ui::{UiPlugin as WdnUiPlugin, build::handle_build_input};
 use wdn_world::{
     WorldPlugin as WdnWorldPlugin,
+    command::PlayerCommand,
     door::Door,
-    pawn::{Pawn, action::PawnAction, path::PawnPath},
+    pawn::{Pawn, path::PawnPath},
 };
 
 pub fn main() {
@@ -50,11 +48,13 @@ pub fn main() {
         .add_systems(
             Update,
             (
-                handle_pawn_input.before(RenderSystems::RenderDamage),
-                handle_tile_toggle
-                    .before(RenderSystems::RenderDoors)
-                    .before(RenderSystems::RenderTiles)
-                    .before(RenderSystems::RenderDev),
+                handle_pawn_input
+                    .before(RenderSystems::RenderDamage)
+                    .before(handle_build_input),
+                handle_replay_input.before(handle_save_messages),
+                handle_dev_input
+                    .before(RenderSystems::RenderDev)
+                    .before(handle_pawn_input),
             ),
         )
         .configure_schedules(ScheduleBuildSettings {
@@ -135,10 +135,16 @@ fn spawn_pawn(mut commands: Commands, mut storage: TileStorageMut) {
         .id();
     commands.spawn((
         Player,
+        NoTasks,
         Pawn::default(),
         ChildOf(layer),
         Position::new(Vec2::new(0.5, 0.5), Rot2::IDENTITY),
     ));
+    commands.spawn((
+        Pawn::default(),
+        ChildOf(layer),
+        Position::new(Vec2::new(0.5, -1.5), Rot2::IDENTITY),
+    ));
 
     commands.insert_resource(LayerView::new(layer_stack, 0));
 
@@ -171,20 +177,21 @@ fn handle_pawn_input(
     camera_query: Single<(&Camera, &GlobalTransform)>,
     window: Single<&Window>,
     layer: Single<Entity, With<Layer>>,
-    pawn_query: Single<(Entity, &mut PawnAction, &mut PawnPath), (With<Pawn>, With<Player>)>,
+    pawn_query: Single<(Entity, &PawnPath), (With<Pawn>, With<Player>)>,
+    mut player_commands: MessageWriter<PlayerCommand>,
     mut dev_render: ResMut<DevRenderSettings>,
 ) {
-    let (entity, mut action, mut path) = pawn_query.into_inner();
+    let (entity, path) = pawn_query.into_inner();
 
     let (camera, camera_transform) = camera_query.into_inner();
 
     // Check for attack inputs first (they take priority)
     if keys.just_pressed(KeyCode::KeyQ) {
-        *action = PawnAction::AttackLeft;
+        player_commands.write(PlayerCommand::AttackLeft { pawn: entity });
         return;
     }
     if keys.just_pressed(KeyCode::KeyE) {
-        *action = PawnAction::AttackRight;
+        player_commands.write(PlayerCommand::AttackRight { pawn: entity });
         return;
     }
 
@@ -194,60 +201,41 @@ fn handle_pawn_input(
         && let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos)
     {
         let tile_pos = TilePosition::floor(*layer, world_pos);
-        path.set_target(tile_pos);
+        if path.target() != Some(tile_pos) {
+            player_commands.write(PlayerCommand::SetTarget {
+                pawn: entity,
+                target: tile_pos,
+            });
+        }
 
         dev_render.draw_pawn_paths = Some(entity);
     }
+}
 
-    // Default to standing
-    *action = PawnAction::Stand;
+fn handle_dev_input(keys: Res<ButtonInput<KeyCode>>, mut dev_render: ResMut<DevRenderSettings>) {
+    if keys.just_pressed(KeyCode::F1) {
+        dev_render.draw_contacts = !dev_render.draw_contacts;
+    }
+    if keys.just_pressed(KeyCode::F2) {
+        dev_render.draw_tile_colliders = !dev_render.draw_tile_colliders;
+    }
+    if keys.just_pressed(KeyCode::F3) {
+        dev_render.draw_tile_index = !dev_render.draw_tile_index;
+    }
 }
 
-fn handle_tile_toggle(
-    mut commands: Commands,
-    mouse: Res<ButtonInput<MouseButton>>,
-    camera_query: Single<(&Camera, &GlobalTransform)>,
-    window: Single<&Window>,
-    layer: Single<Entity, With<Layer>>,
-    index: Res<TileIndex>,
-    mut tile_storage: TileStorageMut,
-    // mut dev_render: ResMut<DevRenderSettings>,
+fn handle_replay_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut start_recording: MessageWriter<StartRecording>,
+    mut save_replay: MessageWriter<SaveReplay>,
 ) {
-    if (mouse.just_pressed(MouseButton::Right) || mouse.just_pressed(MouseButton::Left))
-        && let Some(cursor_pos) = window.cursor_position()
-    {
-        let (camera, camera_transform) = camera_query.into_inner();
-        if let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) {
-            // Convert world position to tile position
-            let tile_pos = TilePosition::floor(*layer, world_pos);
-
-            // Toggle tile material between Empty and Wall
-            let current_material = tile_storage.get_kind(tile_pos);
-            match current_material {
-                TileKind::Empty | TileKind::Stairs => {
-                    if mouse.just_pressed(MouseButton::Left) {
-                        tile_storage.set_material(tile_pos, TileMaterial::WALL);
-                    }
-                }
-                TileKind::Wall => {
-                    if mouse.just_pressed(MouseButton::Left) {
-                        tile_storage.set_material(tile_pos, TileMaterial::DOOR);
-                        commands.spawn((Door::default(), tile_pos));
-                    }
-                }
-                TileKind::Door => {
-                    let door_id = index.get_tile(tile_pos);
-
-                    if mouse.just_pressed(MouseButton::Left) {
-                        if let Some(door_id) = door_id {
-                            commands.entity(door_id).despawn();
-                        }
-                        tile_storage.set_material(tile_pos, TileMaterial::EMPTY);
-                    } /*else if mouse.just_pressed(MouseButton::Right) {
-                    dev_render.draw_door_flow_fields = door_id;
-                    }*/
-                }
-            };
-        }
+    if keys.just_pressed(KeyCode::F5) {
+        // Recording replans the path of every pawn with a target.
+        start_recording.write(StartRecording);
+    }
+    if keys.just_pressed(KeyCode::F6) {
+        save_replay.write(SaveReplay {
+            path: PathBuf::from("replay.wdnr"),
+        });
     }
 }
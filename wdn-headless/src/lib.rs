@@ -0,0 +1,100 @@
+pub mod scenario;
+#[cfg(test)]
+mod tests;
+
+use std::time::Duration;
+
+use bevy_app::{TaskPoolOptions, prelude::*};
+use bevy_ecs::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
+use wdn_physics::{Deterministic, PhysicsPlugin};
+use wdn_save::{SavePlugin, data::SaveData};
+use wdn_tasks::TasksPlugin;
+use wdn_world::WorldPlugin;
+
+pub const DEFAULT_TIMESTEP: Duration = Duration::from_micros(15625);
+
+pub fn headless_app(timestep: Duration, threads: Option<usize>) -> App {
+    let task_pool = match threads {
+        Some(threads) => TaskPoolPlugin {
+            task_pool_options: TaskPoolOptions::with_num_threads(threads),
+        },
+        None => TaskPoolPlugin::default(),
+    };
+
+    let mut app = App::new();
+    app.add_plugins((
+        task_pool,
+        TimePlugin,
+        PhysicsPlugin,
+        WorldPlugin,
+        TasksPlugin,
+        SavePlugin,
+    ));
+
+    app.insert_resource(Deterministic);
+    app.insert_resource(Time::<Fixed>::from_duration(timestep));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    app
+}
+
+pub fn run(app: &mut App, ticks: u64, mut on_tick: impl FnMut(u64, u64)) -> Result {
+    for tick in 0..ticks {
+        app.update();
+        on_tick(tick, state_hash(app.world_mut())?);
+    }
+
+    Ok(())
+}
+
+pub fn state_hash(world: &mut World) -> Result<u64> {
+    let mut data = SaveData::capture(world)?;
+
+    let mut pawns: Vec<_> = data.pawns.into_iter().enumerate().collect();
+    pawns.sort_unstable_by_key(|(_, pawn)| {
+        (
+            pawn.layer,
+            pawn.position.to_array().map(f32::to_bits),
+            pawn.linear_velocity.to_array().map(f32::to_bits),
+        )
+    });
+
+    // Pawn indices follow query order, so remap everything that refers to them.
+    let mut indices = vec![0; pawns.len()];
+    for (index, &(previous, _)) in pawns.iter().enumerate() {
+        indices[previous] = index as u32;
+    }
+    data.pawns = pawns.into_iter().map(|(_, pawn)| pawn).collect();
+    for door in &mut data.doors {
+        if let Some(access) = &mut door.access {
+            for owner in &mut access.owners {
+                *owner = indices[*owner as usize];
+            }
+            access.owners.sort_unstable();
+        }
+    }
+    for projectile in &mut data.projectiles {
+        projectile.pawn = indices[projectile.pawn as usize];
+    }
+    data.projectiles.sort_unstable_by_key(|projectile| {
+        (
+            projectile.pawn,
+            projectile.position.to_array().map(f32::to_bits),
+            projectile.linear_velocity.to_array().map(f32::to_bits),
+        )
+    });
+
+    Ok(fnv1a(&data.encode()))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,71 @@
+use std::{
+    env,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+    time::Duration,
+};
+
+use wdn_headless::{DEFAULT_TIMESTEP, headless_app, run, scenario::spawn_scenario};
+use wdn_save::{load_world, replay::load_replay};
+
+const USAGE: &str =
+    "usage: wdn-headless [--ticks N] [--timestep-us N] [--threads N] [--load PATH] [--replay PATH]";
+
+fn main() -> ExitCode {
+    let mut ticks = 1000;
+    let mut timestep = DEFAULT_TIMESTEP;
+    let mut threads = None;
+    let mut load = None;
+    let mut replay = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match (arg.as_str(), args.next()) {
+            ("--ticks", Some(value)) => value.parse().map(|value| ticks = value).is_ok(),
+            ("--timestep-us", Some(value)) => value
+                .parse()
+                .map(|value| timestep = Duration::from_micros(value))
+                .is_ok(),
+            ("--threads", Some(value)) => value.parse().map(|value| threads = Some(value)).is_ok(),
+            ("--load", Some(value)) => {
+                load = Some(value);
+                true
+            }
+            ("--replay", Some(value)) => {
+                replay = Some(value);
+                true
+            }
+            _ => false,
+        };
+
+        if !parsed {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut app = headless_app(timestep, threads);
+    let setup = match (&load, &replay) {
+        (None, Some(path)) => load_replay(app.world_mut(), path),
+        (Some(path), None) => load_world(app.world_mut(), path),
+        (None, None) => spawn_scenario(app.world_mut()).map(drop),
+        (Some(_), Some(_)) => Err("--load and --replay are mutually exclusive".into()),
+    };
+    if let Err(error) = setup {
+        eprintln!("failed to set up world: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut output = BufWriter::new(io::stdout().lock());
+    let result = run(&mut app, ticks, |tick, hash| {
+        let _ = writeln!(output, "{tick} {hash:016x}");
+    });
+    let _ = output.flush();
+
+    if let Err(error) = result {
+        eprintln!("simulation failed: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
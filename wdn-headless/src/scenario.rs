@@ -0,0 +1,104 @@
+use std::f32::consts::PI;
+
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use wdn_physics::{
+    kinematics::{Mass, Position},
+    layer::Layer,
+    tile::{material::TileMaterial, position::TilePosition, storage::TileStorageMut},
+};
+use wdn_world::{
+    door::Door,
+    pawn::{Pawn, Staff, action::PawnAction, path::PawnPath},
+};
+
+pub fn spawn_scenario(world: &mut World) -> Result<Entity> {
+    Ok(world
+        .run_system_once(spawn_scenario_system)
+        .map_err(|err| err.to_string())?)
+}
+
+fn spawn_scenario_system(mut commands: Commands, mut storage: TileStorageMut) -> Entity {
+    let layer = commands.spawn(Layer::default()).id();
+
+    for x in -2..2 {
+        for y in -2..2 {
+            storage.set_material(
+                TilePosition::new(layer, x * 32, y * 32),
+                TileMaterial::EMPTY,
+            );
+        }
+    }
+
+    for x in 0..=8 {
+        storage.set_material(TilePosition::new(layer, x, 0), TileMaterial::WALL);
+        storage.set_material(TilePosition::new(layer, x, 8), TileMaterial::WALL);
+    }
+    for y in 1..8 {
+        storage.set_material(TilePosition::new(layer, 0, y), TileMaterial::WALL);
+        storage.set_material(TilePosition::new(layer, 8, y), TileMaterial::WALL);
+    }
+    for y in 1..8 {
+        storage.set_material(TilePosition::new(layer, 4, y), TileMaterial::SLOW);
+    }
+
+    for position in [
+        TilePosition::new(layer, 0, 4),
+        TilePosition::new(layer, 8, 4),
+    ] {
+        storage.set_material(position, TileMaterial::DOOR);
+        commands.spawn((Door::default(), position, ChildOf(layer)));
+    }
+
+    for x in 1..4 {
+        for y in 1..8 {
+            spawn_pawn(
+                &mut commands,
+                Vec2::new(x as f32 + 0.5, y as f32 + 0.5),
+                TilePosition::new(layer, x * 4 - 12 + y, 12),
+            );
+        }
+    }
+
+    for x in -12..9i32 {
+        spawn_pawn(
+            &mut commands,
+            Vec2::new(x as f32 + 0.5, -4.5),
+            TilePosition::new(layer, 5 + x.rem_euclid(3), 1 + x.rem_euclid(7)),
+        );
+    }
+
+    let arena = commands.spawn(Layer::default()).id();
+    storage.set_material(TilePosition::new(arena, -32, -32), TileMaterial::EMPTY);
+
+    for x in -6..-2 {
+        let x = x as f32 * 2.0 + 0.5;
+        commands.spawn((
+            Pawn::default(),
+            ChildOf(arena),
+            Position::new(Vec2::new(x, -10.5), Rot2::IDENTITY),
+            PawnAction::AttackLeft,
+        ));
+        // Projectiles pass through allies, so the prisoners face staff.
+        commands.spawn((
+            Staff,
+            ChildOf(arena),
+            Position::new(Vec2::new(x, -10.0), Rot2::radians(PI)),
+            PawnAction::AttackLeft,
+        ));
+    }
+
+    layer
+}
+
+fn spawn_pawn(commands: &mut Commands, start: Vec2, target: TilePosition) {
+    let mut path = PawnPath::default();
+    path.set_target(target);
+    commands.spawn((
+        Pawn::default(),
+        Mass::new(Pawn::MASS),
+        ChildOf(target.layer()),
+        Position::new(start, Rot2::IDENTITY),
+        path,
+    ));
+}
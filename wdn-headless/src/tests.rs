@@ -0,0 +1,297 @@
+use bevy_app::prelude::*;
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use wdn_physics::kinematics::Position;
+use wdn_physics::{
+    kinematics::Velocity,
+    tile::{
+        material::{TileKind, TileMaterial},
+        position::TilePosition,
+        storage::TileStorage,
+    },
+};
+use wdn_save::replay::{ReplayData, finish_recording, play_replay, start_recording};
+use wdn_tasks::{blueprint::Blueprint, task::Task};
+use wdn_world::{combat::Projectile, command::PlayerCommand, pawn::Pawn};
+
+use crate::{DEFAULT_TIMESTEP, headless_app, run, scenario::spawn_scenario, state_hash};
+
+fn run_scenario(ticks: u64) -> Vec<u64> {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    spawn_scenario(app.world_mut()).unwrap();
+
+    let mut hashes = Vec::new();
+    run(&mut app, ticks, |_, hash| hashes.push(hash)).unwrap();
+    hashes
+}
+
+#[test]
+fn headless_run_is_repeatable() {
+    let first = run_scenario(200);
+    let second = run_scenario(200);
+
+    assert_eq!(first.len(), 200);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn headless_run_advances_state() {
+    let hashes = run_scenario(100);
+
+    assert_ne!(hashes.first(), hashes.last());
+}
+
+#[test]
+fn headless_run_hashes_projectiles() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    spawn_scenario(app.world_mut()).unwrap();
+    run(&mut app, 10, |_, _| {}).unwrap();
+
+    let mut projectiles = app
+        .world_mut()
+        .query_filtered::<&mut Velocity, With<Projectile>>();
+    assert!(projectiles.iter(app.world()).count() > 0);
+
+    let hash = state_hash(app.world_mut()).unwrap();
+    assert_eq!(state_hash(app.world_mut()).unwrap(), hash);
+    *projectiles.iter_mut(app.world_mut()).next().unwrap() = Velocity::new(Vec2::ZERO);
+    assert_ne!(state_hash(app.world_mut()).unwrap(), hash);
+}
+
+#[test]
+fn replay_reproduces_recorded_run() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    let layer = spawn_scenario(app.world_mut()).unwrap();
+    run(&mut app, 40, |_, _| {}).unwrap();
+
+    let pawn = app
+        .world_mut()
+        .query_filtered::<Entity, With<Pawn>>()
+        .iter(app.world())
+        .next()
+        .unwrap();
+    start_recording(app.world_mut()).unwrap();
+
+    let mut recorded = Vec::new();
+    for tick in 0..100 {
+        let command = match tick {
+            5 => Some(PlayerCommand::SetTarget {
+                pawn,
+                target: TilePosition::new(layer, -6, -6),
+            }),
+            10 => Some(PlayerCommand::SetMaterial {
+                position: TilePosition::new(layer, 6, 0),
+                material: TileMaterial::EMPTY,
+            }),
+            30 => Some(PlayerCommand::AttackRight { pawn }),
+            50 => Some(PlayerCommand::DespawnDoor {
+                position: TilePosition::new(layer, 0, 4),
+            }),
+            _ => None,
+        };
+        if let Some(command) = command {
+            app.world_mut().write_message(command);
+        }
+        run(&mut app, 1, |_, hash| recorded.push(hash)).unwrap();
+    }
+
+    let data = finish_recording(app.world_mut()).unwrap();
+    assert_eq!(data.commands.len(), 4);
+    let data = ReplayData::decode(&data.encode()).unwrap();
+
+    let mut replay = headless_app(DEFAULT_TIMESTEP, None);
+    play_replay(replay.world_mut(), data).unwrap();
+    let mut replayed = Vec::new();
+    run(&mut replay, 100, |_, hash| replayed.push(hash)).unwrap();
+
+    assert_eq!(recorded, replayed);
+}
+
+#[test]
+fn replay_reproduces_blueprint_construction() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    let layer = spawn_scenario(app.world_mut()).unwrap();
+    run(&mut app, 40, |_, _| {}).unwrap();
+    start_recording(app.world_mut()).unwrap();
+
+    let built = TilePosition::new(layer, -6, -8);
+    let removed = TilePosition::new(layer, -8, -8);
+    let mut recorded = Vec::new();
+    for tick in 0..400 {
+        let commands = match tick {
+            5 => vec![
+                PlayerCommand::PlaceBlueprint {
+                    position: built,
+                    material: TileMaterial::WALL,
+                },
+                PlayerCommand::PlaceBlueprint {
+                    position: removed,
+                    material: TileMaterial::WALL,
+                },
+            ],
+            10 => vec![PlayerCommand::RemoveBlueprint { position: removed }],
+            _ => vec![],
+        };
+        for command in commands {
+            app.world_mut().write_message(command);
+        }
+        run(&mut app, 1, |_, hash| recorded.push(hash)).unwrap();
+    }
+
+    assert_eq!(get_kind(&mut app, built), TileKind::Wall);
+    assert_eq!(get_kind(&mut app, removed), TileKind::Empty);
+
+    let data = finish_recording(app.world_mut()).unwrap();
+    assert_eq!(data.commands.len(), 3);
+    let data = ReplayData::decode(&data.encode()).unwrap();
+
+    let mut replay = headless_app(DEFAULT_TIMESTEP, None);
+    play_replay(replay.world_mut(), data).unwrap();
+    let mut replayed = Vec::new();
+    run(&mut replay, 400, |_, hash| replayed.push(hash)).unwrap();
+
+    assert_eq!(recorded, replayed);
+}
+
+#[test]
+fn replay_reproduces_recording_started_mid_task() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    let layer = spawn_scenario(app.world_mut()).unwrap();
+    let pawn = app
+        .world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(Vec2::new(-20.5, 20.5), Rot2::IDENTITY),
+        ))
+        .id();
+    run(&mut app, 40, |_, _| {}).unwrap();
+
+    let site = TilePosition::new(layer, -6, -8);
+    app.world_mut()
+        .write_message(PlayerCommand::PlaceBlueprint {
+            position: site,
+            material: TileMaterial::WALL,
+        });
+    run_until(&mut app, |app| {
+        app.world_mut()
+            .query::<&Task>()
+            .iter(app.world())
+            .any(|task| !task.worked().is_zero())
+    });
+
+    app.world_mut()
+        .write_message(PlayerCommand::AttackLeft { pawn });
+    run(&mut app, 1, |_, _| {}).unwrap();
+    start_recording(app.world_mut()).unwrap();
+
+    let mut recorded = Vec::new();
+    run(&mut app, 300, |_, hash| recorded.push(hash)).unwrap();
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+
+    let data = finish_recording(app.world_mut()).unwrap();
+    assert!(
+        data.initial
+            .tasks
+            .iter()
+            .any(|task| task.blueprint.is_some())
+    );
+    assert!(!data.initial.projectiles.is_empty());
+    let data = ReplayData::decode(&data.encode()).unwrap();
+
+    let mut replay = headless_app(DEFAULT_TIMESTEP, None);
+    play_replay(replay.world_mut(), data).unwrap();
+    let mut replayed = Vec::new();
+    run(&mut replay, 300, |_, hash| replayed.push(hash)).unwrap();
+
+    assert_eq!(recorded, replayed);
+}
+
+#[test]
+fn replay_rejects_commands_for_unrecorded_pawns() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    let layer = spawn_scenario(app.world_mut()).unwrap();
+    run(&mut app, 1, |_, _| {}).unwrap();
+    start_recording(app.world_mut()).unwrap();
+
+    let pawn = app
+        .world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(Vec2::new(-10.5, -10.5), Rot2::IDENTITY),
+        ))
+        .id();
+    app.world_mut()
+        .write_message(PlayerCommand::AttackLeft { pawn });
+    run(&mut app, 1, |_, _| {}).unwrap();
+
+    assert!(finish_recording(app.world_mut()).is_err());
+}
+
+#[test]
+fn failed_blueprint_can_be_placed_again() {
+    let mut app = headless_app(DEFAULT_TIMESTEP, None);
+    let layer = spawn_scenario(app.world_mut()).unwrap();
+    for x in -12..=-8 {
+        for y in -12..=-8 {
+            if x == -12 || x == -8 || y == -12 || y == -8 {
+                app.world_mut().write_message(PlayerCommand::SetMaterial {
+                    position: TilePosition::new(layer, x, y),
+                    material: TileMaterial::WALL,
+                });
+            }
+        }
+    }
+    run(&mut app, 1, |_, _| {}).unwrap();
+
+    let site = TilePosition::new(layer, -10, -10);
+    app.world_mut()
+        .write_message(PlayerCommand::PlaceBlueprint {
+            position: site,
+            material: TileMaterial::WALL,
+        });
+    run(&mut app, 1, |_, _| {}).unwrap();
+    assert_eq!(blueprint_count(&mut app), 1);
+
+    run_until(&mut app, |app| blueprint_count(app) == 0);
+
+    app.world_mut().write_message(PlayerCommand::SetMaterial {
+        position: TilePosition::new(layer, -8, -10),
+        material: TileMaterial::EMPTY,
+    });
+    app.world_mut()
+        .write_message(PlayerCommand::PlaceBlueprint {
+            position: site,
+            material: TileMaterial::WALL,
+        });
+    run(&mut app, 1, |_, _| {}).unwrap();
+    assert_eq!(blueprint_count(&mut app), 1);
+
+    run_until(&mut app, |app| get_kind(app, site) == TileKind::Wall);
+    assert_eq!(blueprint_count(&mut app), 0);
+}
+
+fn get_kind(app: &mut App, position: TilePosition) -> TileKind {
+    app.world_mut()
+        .run_system_once(move |storage: TileStorage| storage.get_kind(position))
+        .unwrap()
+}
+
+fn blueprint_count(app: &mut App) -> usize {
+    app.world_mut()
+        .query_filtered::<(), With<Blueprint>>()
+        .iter(app.world())
+        .count()
+}
+
+fn run_until(app: &mut App, mut condition: impl FnMut(&mut App) -> bool) {
+    for _ in 0..2000 {
+        run(app, 1, |_, _| {}).unwrap();
+        if condition(app) {
+            return;
+        }
+    }
+
+    panic!("condition not reached");
+}
@@ -0,0 +1,20 @@
+use std::process::Command;
+
+fn run_headless(threads: usize) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_wdn-headless"))
+        .args(["--ticks", "300", "--threads", &threads.to_string()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn deterministic_across_thread_counts() {
+    let single = run_headless(1);
+    let many = run_headless(8);
+
+    assert_eq!(single.lines().count(), 300);
+    assert_eq!(single, many);
+}
@@ -0,0 +1,112 @@
+use bevy_app::{App, FixedUpdate, TaskPoolPlugin};
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use bevy_time::TimePlugin;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use wdn_physics::{
+    PhysicsPlugin,
+    collision::{Collider, sleep::Sleep},
+    kinematics::{Position, Velocity},
+    layer::Layer,
+};
+
+const WIDTH: i32 = 64;
+const HEIGHT: i32 = 64;
+const LARGE_SPACING: i32 = 8;
+const SLEEPING_SIZES: [i32; 4] = [16, 32, 64, 128];
+
+fn make_app(width: i32, height: i32) -> App {
+    let mut app = App::new();
+    app.add_plugins((TaskPoolPlugin::default(), TimePlugin, PhysicsPlugin));
+
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    for x in 0..width {
+        for y in 0..height {
+            app.world_mut().spawn((
+                Collider::new(0.2, true),
+                Position::new(Vec2::new(x as f32 + 0.5, y as f32 + 0.5), Rot2::IDENTITY),
+                Velocity::default(),
+                ChildOf(layer),
+            ));
+        }
+    }
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.world_mut().run_schedule(FixedUpdate);
+    }
+
+    app
+}
+
+fn spawn_large_colliders(app: &mut App) {
+    let layer = app
+        .world_mut()
+        .query_filtered::<Entity, With<Layer>>()
+        .single(app.world())
+        .expect("missing benchmark layer");
+    for x in (0..WIDTH).step_by(LARGE_SPACING as usize) {
+        for y in (0..HEIGHT).step_by(LARGE_SPACING as usize) {
+            app.world_mut().spawn((
+                Collider::new(1.5, false),
+                Position::new(Vec2::new(x as f32 + 0.5, y as f32 + 0.5), Rot2::IDENTITY),
+                Velocity::new(Vec2::new(20.0, 0.0)),
+                ChildOf(layer),
+            ));
+        }
+    }
+}
+
+fn wake_all(app: &mut App) {
+    app.world_mut()
+        .run_system_once(|mut sleepers: Query<&mut Sleep>| {
+            sleepers.iter_mut().for_each(|mut sleep| sleep.wake());
+        })
+        .expect("failed to wake benchmark colliders");
+}
+
+fn bench_resolve_collisions_awake(c: &mut Criterion) {
+    let mut app = make_app(WIDTH, HEIGHT);
+
+    c.bench_function("resolve_collisions_awake", |b| {
+        b.iter(|| {
+            wake_all(&mut app);
+            app.world_mut().run_schedule(FixedUpdate);
+        });
+    });
+}
+
+// Worlds of growing size where every collider sleeps, to compare the cost per sleeper.
+fn bench_resolve_collisions_sleeping(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve_collisions_sleeping");
+    for size in SLEEPING_SIZES {
+        let mut app = make_app(size, size);
+
+        group.throughput(Throughput::Elements((size * size) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size * size), &size, |b, _| {
+            b.iter(|| {
+                app.world_mut().run_schedule(FixedUpdate);
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_resolve_collisions_large(c: &mut Criterion) {
+    let mut app = make_app(WIDTH, HEIGHT);
+    spawn_large_colliders(&mut app);
+
+    c.bench_function("resolve_collisions_large", |b| {
+        b.iter(|| {
+            wake_all(&mut app);
+            app.world_mut().run_schedule(FixedUpdate);
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_resolve_collisions_awake,
+    bench_resolve_collisions_sleeping,
+    bench_resolve_collisions_large
+);
+criterion_main!(benches);
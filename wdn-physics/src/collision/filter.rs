@@ -0,0 +1,41 @@
+use bevy_ecs::prelude::*;
+use bitflags::bitflags;
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CollisionGroups : u32 {
+        const NONE = 0;
+        const DEFAULT = 1 << 0;
+        const TILE = 1 << 1;
+        const PRISONER = 1 << 2;
+        const STAFF = 1 << 3;
+        const PROJECTILE = 1 << 4;
+        const SENSOR = 1 << 5;
+        const GHOST = 1 << 6;
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionFilter {
+    pub memberships: CollisionGroups,
+    pub mask: CollisionGroups,
+}
+
+impl CollisionFilter {
+    pub const DEFAULT: Self = Self::new(CollisionGroups::DEFAULT, CollisionGroups::all());
+    pub const TILE: Self = Self::new(CollisionGroups::TILE, CollisionGroups::all());
+
+    pub const fn new(memberships: CollisionGroups, mask: CollisionGroups) -> Self {
+        Self { memberships, mask }
+    }
+
+    pub fn interacts(&self, other: &Self) -> bool {
+        self.mask.intersects(other.memberships) && other.mask.intersects(self.memberships)
+    }
+}
+
+impl Default for CollisionFilter {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
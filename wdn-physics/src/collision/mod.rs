@@ -1,27 +1,37 @@
+pub mod filter;
+pub mod shape;
+pub mod sleep;
 #[cfg(test)]
 mod tests;
 
-use std::mem;
+use std::{f32::consts::PI, mem};
 
 use bevy_app::prelude::*;
-use bevy_ecs::{batching::BatchingStrategy, prelude::*, query::QueryData};
+use bevy_ecs::{batching::BatchingStrategy, entity::EntityHashMap, prelude::*, query::QueryData};
 use bevy_math::prelude::*;
+use bevy_platform::collections::{HashMap, hash_map};
 use bevy_time::prelude::*;
 
 use crate::{
-    PhysicsSystems,
+    Deterministic, PhysicsSystems,
+    collision::{
+        filter::CollisionFilter,
+        shape::{ColliderShape, Hull, shape_collision},
+        sleep::{Sleep, update_sleep, wake_colliders},
+    },
     kinematics::{GlobalPosition, GlobalVelocity},
     tile::{
-        Tile, adjacency::Adjacency, index::TileIndex, position::TilePosition, storage::TileStorage,
+        Tile, adjacency::Adjacency, index::TileIndex, material::TileKind, position::TilePosition,
+        storage::TileStorage,
     },
 };
 
 pub struct CollisionPlugin;
 
 #[derive(Component, Clone, Copy, Debug)]
-#[require(Collisions, TilePosition, GlobalPosition)]
+#[require(Collisions, CollisionFilter, Sleep, TilePosition, GlobalPosition)]
 pub struct Collider {
-    radius: f32,
+    shape: ColliderShape,
     solid: bool,
 }
 
@@ -35,8 +45,11 @@ pub struct TileCollider {
 pub struct ColliderQuery {
     id: Entity,
     collider: &'static Collider,
+    filter: &'static CollisionFilter,
+    tile: &'static TilePosition,
     position: &'static GlobalPosition,
     velocity: Option<&'static GlobalVelocity>,
+    parent: Option<&'static ChildOf>,
 }
 
 #[derive(QueryData, Debug)]
@@ -61,6 +74,22 @@ pub struct Collision {
     pub solid: bool,
 }
 
+#[derive(Message, Clone, Copy, Debug)]
+pub struct CollisionStarted {
+    pub entity: Entity,
+    pub target: CollisionTarget,
+    pub position: Vec2,
+    pub normal: Dir2,
+}
+
+#[derive(Message, Clone, Copy, Debug)]
+pub struct CollisionEnded {
+    pub entity: Entity,
+    pub target: CollisionTarget,
+    pub position: Vec2,
+    pub normal: Dir2,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum CollisionTarget {
     Collider {
@@ -73,6 +102,46 @@ pub enum CollisionTarget {
     },
 }
 
+#[derive(Clone, Copy, Debug)]
+struct Orbit {
+    pivot: Vec2,
+    pivot_velocity: Vec2,
+    offset: Vec2,
+    offset_velocity: Vec2,
+    angular: f32,
+}
+
+const SMALL_REACH: f32 = 0.5;
+const TILE_REACH: f32 = 1.5;
+const MAX_ORBIT_STEP: f32 = PI / 16.0;
+
+static FACES: [(IVec2, Dir2, Vec2, Vec2); 4] = [
+    (
+        IVec2::new(0, 1),
+        Dir2::Y,
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+    ),
+    (
+        IVec2::new(1, 0),
+        Dir2::X,
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+    ),
+    (
+        IVec2::new(0, -1),
+        Dir2::NEG_Y,
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+    ),
+    (
+        IVec2::new(-1, 0),
+        Dir2::NEG_X,
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 1.0),
+    ),
+];
+
 static NEIGHBORS: [(IVec2, Adjacency); 9] = [
     (IVec2::new(-1, 1), Adjacency::NORTH_WEST),
     (IVec2::new(0, 1), Adjacency::NORTH),
@@ -90,24 +159,172 @@ struct TileColliderAdjacency<'w, 's> {
     tiles: [Option<TileColliderQueryItem<'w, 's>>; 9],
 }
 
+// Large and fast colliders are indexed in every tile their swept bounds cover this tick. Swept
+// bounds only change when a collider moves, so the index is kept between ticks.
+#[derive(Resource, Default, Debug)]
+pub struct LargeColliderIndex {
+    tiles: HashMap<TilePosition, Vec<Entity>>,
+    colliders: EntityHashMap<Vec<TilePosition>>,
+    delta_secs: f32,
+}
+
+type ChangedColliderFilter = Or<(
+    Changed<Collider>,
+    Changed<TilePosition>,
+    Changed<GlobalPosition>,
+    Changed<GlobalVelocity>,
+)>;
+
+pub fn index_large_colliders(
+    mut large_colliders: ResMut<LargeColliderIndex>,
+    colliders: Query<ColliderQuery>,
+    changed: Query<ColliderQuery, ChangedColliderFilter>,
+    mut removed: RemovedComponents<Collider>,
+    time: Res<Time>,
+) {
+    let delta_secs = time.delta_secs();
+
+    // Swept bounds depend on the timestep, so the whole index is rebuilt when it changes.
+    if large_colliders.delta_secs != delta_secs {
+        *large_colliders = LargeColliderIndex {
+            delta_secs,
+            ..Default::default()
+        };
+        for collider in &colliders {
+            large_colliders.insert(&collider, delta_secs);
+        }
+        removed.clear();
+        return;
+    }
+
+    for id in removed.read() {
+        large_colliders.remove(id);
+    }
+    for collider in &changed {
+        large_colliders.remove(collider.id);
+        large_colliders.insert(&collider, delta_secs);
+    }
+}
+
+#[expect(clippy::too_many_arguments)]
 pub fn resolve_collisions(
     index: Res<TileIndex>,
+    large_colliders: Res<LargeColliderIndex>,
     storage: TileStorage,
-    mut colliders: Query<(ColliderQuery, &TilePosition, &mut Collisions)>,
+    mut colliders: Query<(ColliderQuery, &TilePosition, &Sleep, &mut Collisions)>,
     candidate_colliders: Query<ColliderQuery>,
     candidate_tiles: Query<TileColliderQuery>,
+    parents: Query<(&GlobalPosition, &GlobalVelocity)>,
+    deterministic: Option<Res<Deterministic>>,
     time: Res<Time>,
 ) {
     let delta_secs = time.delta_secs();
+    let deterministic = deterministic.is_some();
 
     colliders
         .par_iter_mut()
         .batching_strategy(BatchingStrategy::new().min_batch_size(16))
-        .for_each(|(collider, &tile_position, mut collisions)| {
+        .for_each(|(collider, &tile_position, sleep, mut collisions)| {
+            // Resting bodies keep their contacts, unless a neighbour they touch has despawned.
+            if sleep.is_resting(&collider)
+                && collisions.iter().all(|collision| match collision.target {
+                    CollisionTarget::Collider { id, .. } => candidate_colliders.contains(id),
+                    CollisionTarget::Tile { .. } => true,
+                })
+            {
+                return;
+            }
+
             collisions.clear();
 
+            let tile_candidates = |tile| {
+                index
+                    .get_tile(tile)
+                    .and_then(|candidate| candidate_tiles.get(candidate).ok())
+            };
+
+            let orbit = collider.orbit(&parents);
+            let chord_velocity = orbit.map(|orbit| {
+                GlobalVelocity::new(
+                    orbit.chord_velocity(0.0, delta_secs),
+                    collider.angular_velocity(),
+                )
+            });
+            let collider = collider.with_motion(
+                collider.position,
+                collider.tile,
+                chord_velocity.as_ref().or(collider.velocity),
+            );
+
+            let collides_tiles = collider.filter.interacts(&CollisionFilter::TILE);
+            let reach = collider.reach(delta_secs);
+
+            let large = reach > SMALL_REACH;
+            if large {
+                let range = (reach + SMALL_REACH).floor() as i32 + 1;
+
+                let mut sorted_candidates: Vec<Entity> = (-range..=range)
+                    .flat_map(|y| (-range..=range).map(move |x| tile_position.with_offset(x, y)))
+                    .flat_map(|tile| index.get_objects(tile).iter().copied())
+                    .collect();
+                sorted_candidates.sort_unstable();
+                for candidate in sorted_candidates {
+                    if candidate != collider.id
+                        && let Ok(candidate_collider) = candidate_colliders.get(candidate)
+                        && candidate_collider.reach(delta_secs) <= SMALL_REACH
+                        && collider.filter.interacts(candidate_collider.filter)
+                    {
+                        collisions.check_collider(&collider, &candidate_collider, delta_secs);
+                    }
+                }
+
+                if reach > TILE_REACH {
+                    collisions.check_large_colliders(
+                        &collider,
+                        &large_colliders,
+                        &candidate_colliders,
+                        delta_secs,
+                    );
+
+                    if collides_tiles {
+                        match orbit {
+                            Some(orbit) => collisions.check_orbit_tiles(
+                                &collider,
+                                &orbit,
+                                &storage,
+                                tile_candidates,
+                                delta_secs,
+                            ),
+                            None => collisions.check_tile_faces(
+                                &collider,
+                                &storage,
+                                tile_candidates,
+                                0,
+                                0.0,
+                                delta_secs,
+                            ),
+                        }
+                    }
+                    return;
+                }
+
+                // Tiles next to the collider keep the neighbour checks below, but it can still
+                // reach walls further out.
+                if collides_tiles && orbit.is_none() {
+                    collisions.check_tile_faces(
+                        &collider,
+                        &storage,
+                        tile_candidates,
+                        2,
+                        0.0,
+                        delta_secs,
+                    );
+                }
+            }
+
             let mut tile_colliders = TileColliderAdjacency::default();
             let mut wall_adjacency = storage.get_wall_adjacency(tile_position);
+            let mut sorted_candidates = Vec::new();
 
             for (neighbor_index, &(neighbor_offset, adjacency)) in NEIGHBORS.iter().enumerate() {
                 let neighbor = tile_position.with_offset(neighbor_offset.x, neighbor_offset.y);
@@ -117,18 +334,29 @@ pub fn resolve_collisions(
                 };
 
                 for &candidate in index_entry.objects() {
-                    if candidate == collider.id {
+                    if large || candidate == collider.id {
+                        continue;
+                    }
+
+                    if deterministic {
+                        sorted_candidates.push(candidate);
                         continue;
                     }
 
                     let Ok(candidate_collider) = candidate_colliders.get(candidate) else {
                         continue;
                     };
+                    if candidate_collider.reach(delta_secs) > SMALL_REACH
+                        || !collider.filter.interacts(candidate_collider.filter)
+                    {
+                        continue;
+                    }
 
                     collisions.check_collider(&collider, &candidate_collider, delta_secs);
                 }
 
-                if let Some(candidate) = index_entry.tile()
+                if collides_tiles
+                    && let Some(candidate) = index_entry.tile()
                     && let Ok(candidate_tile) = candidate_tiles.get(candidate)
                 {
                     wall_adjacency |= adjacency;
@@ -136,6 +364,38 @@ pub fn resolve_collisions(
                 }
             }
 
+            sorted_candidates.sort_unstable();
+            for candidate in sorted_candidates {
+                if let Ok(candidate_collider) = candidate_colliders.get(candidate)
+                    && candidate_collider.reach(delta_secs) <= SMALL_REACH
+                    && collider.filter.interacts(candidate_collider.filter)
+                {
+                    collisions.check_collider(&collider, &candidate_collider, delta_secs);
+                }
+            }
+
+            collisions.check_large_colliders(
+                &collider,
+                &large_colliders,
+                &candidate_colliders,
+                delta_secs,
+            );
+
+            if !collides_tiles {
+                return;
+            }
+
+            if let Some(orbit) = orbit {
+                collisions.check_orbit_tiles(
+                    &collider,
+                    &orbit,
+                    &storage,
+                    tile_candidates,
+                    delta_secs,
+                );
+                return;
+            }
+
             if wall_adjacency != Adjacency::NONE {
                 collisions.check_tile_neighbors(
                     &collider,
@@ -150,8 +410,34 @@ pub fn resolve_collisions(
         });
 }
 
+pub fn write_collision_messages(
+    colliders: Query<(Entity, &Collisions)>,
+    mut started_writer: MessageWriter<CollisionStarted>,
+    mut ended_writer: MessageWriter<CollisionEnded>,
+) {
+    for (entity, collisions) in &colliders {
+        started_writer.write_batch(collisions.started().map(|collision| CollisionStarted {
+            entity,
+            target: collision.target,
+            position: collision.position,
+            normal: collision.normal,
+        }));
+        ended_writer.write_batch(collisions.ended().map(|collision| CollisionEnded {
+            entity,
+            target: collision.target,
+            position: collision.position,
+            normal: collision.normal,
+        }));
+    }
+}
+
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
+        app.add_message::<CollisionStarted>()
+            .add_message::<CollisionEnded>();
+
+        app.init_resource::<LargeColliderIndex>();
+
         app.configure_sets(
             FixedUpdate,
             PhysicsSystems::Collisions.before(PhysicsSystems::Kinematics),
@@ -159,14 +445,34 @@ impl Plugin for CollisionPlugin {
 
         app.add_systems(
             FixedUpdate,
-            resolve_collisions.in_set(PhysicsSystems::Collisions),
+            (
+                wake_colliders,
+                index_large_colliders,
+                resolve_collisions,
+                write_collision_messages,
+                update_sleep,
+            )
+                .chain()
+                .in_set(PhysicsSystems::Collisions),
         );
     }
 }
 
 impl Collider {
     pub fn new(radius: f32, solid: bool) -> Self {
-        Self { radius, solid }
+        Self::from_shape(ColliderShape::Circle { radius }, solid)
+    }
+
+    pub fn rect(half_size: Vec2, solid: bool) -> Self {
+        Self::from_shape(ColliderShape::Rect { half_size }, solid)
+    }
+
+    pub fn from_shape(shape: ColliderShape, solid: bool) -> Self {
+        Self { shape, solid }
+    }
+
+    pub fn shape(&self) -> &ColliderShape {
+        &self.shape
     }
 
     pub fn solid(&self) -> bool {
@@ -178,9 +484,19 @@ impl Collider {
     }
 }
 
-impl ColliderQueryItem<'_, '_> {
-    pub fn radius(&self) -> f32 {
-        self.collider.radius
+impl<'w, 's> ColliderQueryItem<'w, 's> {
+    pub fn id(&self) -> Entity {
+        self.id
+    }
+
+    pub fn shape(&self) -> &ColliderShape {
+        &self.collider.shape
+    }
+
+    pub fn extent(&self, direction: Vec2) -> f32 {
+        self.collider
+            .shape
+            .extent(self.position.rotation(), direction)
     }
 
     pub fn position(&self) -> Vec2 {
@@ -198,9 +514,93 @@ impl ColliderQueryItem<'_, '_> {
         self.velocity.map_or(Vec2::ZERO, |v| v.linear())
     }
 
+    pub fn angular_velocity(&self) -> f32 {
+        self.velocity.map_or(0.0, |v| v.angular())
+    }
+
+    pub fn reach(&self, delta_secs: f32) -> f32 {
+        self.collider.shape.bounding_radius() + self.velocity().length() * delta_secs
+    }
+
     pub fn solid(&self) -> bool {
         self.collider.solid
     }
+
+    pub fn filter(&self) -> &CollisionFilter {
+        self.filter
+    }
+
+    fn swept_tiles(&self, delta_secs: f32) -> impl Iterator<Item = TilePosition> + use<> {
+        let bounds = Rect::from_corners(self.position(), self.position_at(delta_secs))
+            .inflate(self.collider.shape.bounding_radius());
+        let min = bounds.min.floor().as_ivec2();
+        let max = bounds.max.floor().as_ivec2();
+        let layer = self.tile.layer();
+
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| TilePosition::new(layer, x, y)))
+    }
+
+    pub(crate) fn hull(&self) -> Hull {
+        self.collider
+            .shape
+            .hull(self.position.position(), self.position.rotation())
+    }
+
+    fn orbit(&self, parents: &Query<(&GlobalPosition, &GlobalVelocity)>) -> Option<Orbit> {
+        let velocity = self.velocity?;
+        let (parent_position, parent_velocity) = parents.get(self.parent?.parent()).ok()?;
+        if parent_velocity.angular() == 0.0 {
+            return None;
+        }
+
+        let offset = self.position() - parent_position.position();
+        Some(Orbit {
+            pivot: parent_position.position(),
+            pivot_velocity: parent_velocity.linear(),
+            offset,
+            offset_velocity: velocity.linear()
+                - parent_velocity.linear()
+                - offset.perp() * parent_velocity.angular(),
+            angular: parent_velocity.angular(),
+        })
+    }
+
+    fn with_motion<'a>(
+        &self,
+        position: &'a GlobalPosition,
+        tile: &'a TilePosition,
+        velocity: Option<&'a GlobalVelocity>,
+    ) -> ColliderQueryItem<'a, 's>
+    where
+        'w: 'a,
+    {
+        ColliderQueryItem {
+            id: self.id,
+            collider: self.collider,
+            filter: self.filter,
+            tile,
+            position,
+            velocity,
+            parent: self.parent,
+        }
+    }
+}
+
+impl Orbit {
+    fn position_at(&self, t: f32) -> Vec2 {
+        self.pivot
+            + self.pivot_velocity * t
+            + Rot2::radians(self.angular * t) * (self.offset + self.offset_velocity * t)
+    }
+
+    fn chord_velocity(&self, start_secs: f32, delta_secs: f32) -> Vec2 {
+        if delta_secs <= 0.0 {
+            return self.pivot_velocity + self.offset_velocity + self.offset.perp() * self.angular;
+        }
+
+        (self.position_at(start_secs + delta_secs) - self.position_at(start_secs)) / delta_secs
+    }
 }
 
 impl TileCollider {
@@ -291,17 +691,33 @@ impl Collisions {
         candidate: &ColliderQueryItem,
         threshold: f32,
     ) {
-        if let Some(t) = collider_collision(
-            collider.position() - candidate.position(),
-            collider.velocity() - candidate.velocity(),
-            collider.radius() + candidate.radius(),
-        ) && t < threshold
+        let delta_velocity = collider.velocity() - candidate.velocity();
+        let hit = match (collider.shape(), candidate.shape()) {
+            (
+                &ColliderShape::Circle { radius },
+                &ColliderShape::Circle {
+                    radius: candidate_radius,
+                },
+            ) => collider_collision(
+                collider.position() - candidate.position(),
+                delta_velocity,
+                radius + candidate_radius,
+            )
+            .map(|t| {
+                let normal = Dir2::new(collider.position_at(t) - candidate.position_at(t));
+                (t, normal.unwrap_or(Dir2::X))
+            }),
+            _ => shape_collision(&collider.hull(), &candidate.hull(), delta_velocity),
+        };
+
+        if let Some((t, normal)) = hit
+            && t < threshold
         {
             let position = collider.position_at(t);
             let target_position = candidate.position_at(t);
             let collision = Collision {
                 position,
-                normal: Dir2::new(position - target_position).unwrap_or(Dir2::X),
+                normal,
                 target: CollisionTarget::Collider {
                     id: candidate.id,
                     position: target_position,
@@ -312,6 +728,136 @@ impl Collisions {
         }
     }
 
+    fn check_large_colliders(
+        &mut self,
+        collider: &ColliderQueryItem,
+        large_colliders: &LargeColliderIndex,
+        candidate_colliders: &Query<ColliderQuery>,
+        delta_secs: f32,
+    ) {
+        let reach = collider.reach(delta_secs);
+        for candidate in large_colliders.get(collider, delta_secs) {
+            if candidate != collider.id
+                && let Ok(candidate_collider) = candidate_colliders.get(candidate)
+                && candidate_collider.tile.layer() == collider.tile.layer()
+                && collider.position().distance(candidate_collider.position())
+                    <= reach + candidate_collider.reach(delta_secs)
+                && collider.filter.interacts(candidate_collider.filter)
+            {
+                self.check_collider(collider, &candidate_collider, delta_secs);
+            }
+        }
+    }
+
+    fn check_tile_faces<'w, 's>(
+        &mut self,
+        collider: &ColliderQueryItem,
+        storage: &TileStorage,
+        candidates: impl Fn(TilePosition) -> Option<TileColliderQueryItem<'w, 's>>,
+        min_range: i32,
+        start_secs: f32,
+        delta_secs: f32,
+    ) {
+        let hull = collider.hull();
+        let range = (collider.reach(delta_secs) + SMALL_REACH).floor() as i32 + 1;
+        for y in -range..=range {
+            for x in -range..=range {
+                if x.abs().max(y.abs()) < min_range {
+                    continue;
+                }
+
+                let tile = collider.tile.with_offset(x, y);
+                let candidate = candidates(tile);
+                let (id, solid) = match candidate {
+                    Some(candidate) => (Some(candidate.id), collider.solid() && candidate.solid()),
+                    None if storage.get_kind(tile) == TileKind::Wall => (None, collider.solid()),
+                    None => continue,
+                };
+
+                let corner = tile.position().as_vec2();
+                for &(offset, normal, start, end) in &FACES {
+                    if storage.get_kind(tile.with_offset(offset.x, offset.y)) == TileKind::Wall {
+                        continue;
+                    }
+
+                    if let Some((t, hit_normal)) = shape_collision(
+                        &hull,
+                        &Hull::segment(corner + start, corner + end),
+                        collider.velocity(),
+                    ) && t < delta_secs
+                    {
+                        let tangent = (end - start).as_ivec2();
+                        let collision = Collision {
+                            position: collider.position_at(t),
+                            normal: face_normal(storage, tile, normal, tangent, hit_normal),
+                            target: CollisionTarget::Tile { id, position: tile },
+                            solid,
+                        };
+                        self.insert(collision, start_secs + t);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_orbit_tiles<'w, 's>(
+        &mut self,
+        collider: &ColliderQueryItem,
+        orbit: &Orbit,
+        storage: &TileStorage,
+        candidates: impl Fn(TilePosition) -> Option<TileColliderQueryItem<'w, 's>>,
+        delta_secs: f32,
+    ) {
+        let first_active = self.active.len();
+        let steps = (orbit.angular.abs() * delta_secs / MAX_ORBIT_STEP)
+            .ceil()
+            .max(1.0);
+        let step_secs = delta_secs / steps;
+
+        for step in 0..steps as u32 {
+            let start_secs = step as f32 * step_secs;
+            let rotation = Rot2::radians(collider.angular_velocity() * start_secs);
+            let position = GlobalPosition::new(
+                orbit.position_at(start_secs),
+                rotation * collider.position.rotation(),
+            );
+            let tile = TilePosition::floor(collider.tile.layer(), position.position());
+            let velocity = GlobalVelocity::new(
+                orbit.chord_velocity(start_secs, step_secs),
+                collider.angular_velocity(),
+            );
+
+            self.check_tile_faces(
+                &collider.with_motion(&position, &tile, Some(&velocity)),
+                storage,
+                &candidates,
+                0,
+                start_secs,
+                step_secs,
+            );
+
+            if self
+                .next_time()
+                .is_some_and(|t| t <= start_secs + step_secs)
+            {
+                break;
+            }
+        }
+
+        let mut index = first_active;
+        while index < self.active.len() {
+            let target = self.active[index].target;
+            if self.active[first_active..index]
+                .iter()
+                .any(|collision| collision.target.contains(&target))
+            {
+                self.active.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
     fn check_tile_neighbors(
         &mut self,
         collider: &ColliderQueryItem,
@@ -329,7 +875,7 @@ impl Collisions {
                 Dir2::NEG_X,
                 candidate_position.x() as f32 - collider.position().x,
                 collider.velocity().x,
-                collider.radius(),
+                collider.extent(Vec2::X),
                 delta_secs,
             );
         }
@@ -343,7 +889,7 @@ impl Collisions {
                 Dir2::NEG_Y,
                 candidate_position.y() as f32 - collider.position().y,
                 collider.velocity().y,
-                collider.radius(),
+                collider.extent(Vec2::Y),
                 delta_secs,
             );
         }
@@ -357,7 +903,7 @@ impl Collisions {
                 Dir2::X,
                 collider.position().x - tile_position.x() as f32,
                 -collider.velocity().x,
-                collider.radius(),
+                collider.extent(Vec2::NEG_X),
                 delta_secs,
             );
         }
@@ -371,7 +917,7 @@ impl Collisions {
                 Dir2::Y,
                 collider.position().y - tile_position.y() as f32,
                 -collider.velocity().y,
-                collider.radius(),
+                collider.extent(Vec2::NEG_Y),
                 delta_secs,
             );
         }
@@ -453,13 +999,13 @@ impl Collisions {
         normal: Dir2,
         delta_position_component: f32,
         collider_velocity_component: f32,
-        collider_radius: f32,
+        collider_extent: f32,
         delta_secs: f32,
     ) {
         if let Some(t) = wall_collision(
             delta_position_component,
             collider_velocity_component,
-            collider_radius,
+            collider_extent,
         ) && t < delta_secs
         {
             let position = collider.position_at(t);
@@ -489,14 +1035,28 @@ impl Collisions {
         corner_position: IVec2,
         delta_secs: f32,
     ) {
-        if let Some(t) = collider_collision(
-            collider.position() - corner_position.as_vec2(),
-            collider.velocity(),
-            collider.radius(),
-        ) && t < delta_secs
+        let target_position = corner_position.as_vec2();
+        let hit = match *collider.shape() {
+            ColliderShape::Circle { radius } => collider_collision(
+                collider.position() - target_position,
+                collider.velocity(),
+                radius,
+            )
+            .map(|t| {
+                let normal = Dir2::new(collider.position_at(t) - target_position);
+                (t, normal.unwrap_or(Dir2::X))
+            }),
+            _ => shape_collision(
+                &collider.hull(),
+                &Hull::point(target_position, 0.0),
+                collider.velocity(),
+            ),
+        };
+
+        if let Some((t, normal)) = hit
+            && t < delta_secs
         {
             let position = collider.position_at(t);
-            let target_position = corner_position.as_vec2();
             let (id, solid) = match candidate {
                 Some(candidate) => (Some(candidate.id), collider.solid() && candidate.solid()),
                 None => (None, collider.solid()),
@@ -504,7 +1064,7 @@ impl Collisions {
 
             let collision = Collision {
                 position,
-                normal: Dir2::new(position - target_position).unwrap_or(Dir2::X),
+                normal,
                 target: CollisionTarget::Tile {
                     id,
                     position: tile_position,
@@ -571,6 +1131,47 @@ impl CollisionTarget {
     }
 }
 
+impl LargeColliderIndex {
+    fn insert(&mut self, collider: &ColliderQueryItem, delta_secs: f32) {
+        if collider.reach(delta_secs) <= SMALL_REACH {
+            return;
+        }
+
+        let tiles: Vec<TilePosition> = collider.swept_tiles(delta_secs).collect();
+        for &tile in &tiles {
+            self.tiles.entry(tile).or_default().push(collider.id);
+        }
+        self.colliders.insert(collider.id, tiles);
+    }
+
+    fn remove(&mut self, id: Entity) {
+        let Some(tiles) = self.colliders.remove(&id) else {
+            return;
+        };
+
+        for tile in tiles {
+            if let hash_map::Entry::Occupied(mut entry) = self.tiles.entry(tile) {
+                entry.get_mut().retain(|&candidate| candidate != id);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    fn get(&self, collider: &ColliderQueryItem, delta_secs: f32) -> Vec<Entity> {
+        let mut candidates: Vec<Entity> = collider
+            .swept_tiles(delta_secs)
+            .filter_map(|tile| self.tiles.get(&tile))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
 impl<'w, 's> TileColliderAdjacency<'w, 's> {
     fn north_west(&self) -> Option<&TileColliderQueryItem<'w, 's>> {
         self.tiles[0].as_ref()
@@ -646,3 +1247,23 @@ fn wall_collision(distance: f32, speed: f32, radius: f32) -> Option<f32> {
 
     Some((distance - radius) / speed)
 }
+
+fn face_normal(
+    storage: &TileStorage,
+    tile: TilePosition,
+    normal: Dir2,
+    tangent: IVec2,
+    hit_normal: Dir2,
+) -> Dir2 {
+    let along = hit_normal.dot(tangent.as_vec2());
+    if hit_normal.dot(*normal) <= 0.0 || along.abs() < 1e-4 {
+        return normal;
+    }
+
+    let next = if along > 0.0 { tangent } else { -tangent };
+    if storage.get_kind(tile.with_offset(next.x, next.y)) == TileKind::Wall {
+        normal
+    } else {
+        hit_normal
+    }
+}
@@ -0,0 +1,274 @@
+use bevy_math::prelude::*;
+
+pub const MAX_POLYGON_VERTICES: usize = 8;
+
+const MAX_HULL_POINTS: usize = MAX_POLYGON_VERTICES * MAX_POLYGON_VERTICES;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColliderShape {
+    Circle { radius: f32 },
+    Rect { half_size: Vec2 },
+    Polygon(ConvexPolygon),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvexPolygon {
+    vertices: [Vec2; MAX_POLYGON_VERTICES],
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Hull {
+    points: [Vec2; MAX_HULL_POINTS],
+    len: usize,
+    radius: f32,
+}
+
+pub(crate) fn shape_collision(a: &Hull, b: &Hull, delta_velocity: Vec2) -> Option<(f32, Dir2)> {
+    Hull::minkowski_difference(a, b).cast_origin(-delta_velocity)
+}
+
+pub(crate) fn shape_overlap(a: &Hull, b: &Hull) -> bool {
+    Hull::minkowski_difference(a, b).contains_origin().is_some()
+}
+
+impl ColliderShape {
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            ColliderShape::Circle { radius } => radius,
+            ColliderShape::Rect { half_size } => half_size.length(),
+            ColliderShape::Polygon(polygon) => polygon
+                .vertices()
+                .iter()
+                .fold(0.0, |radius, vertex| vertex.length().max(radius)),
+        }
+    }
+
+    pub fn extent(&self, rotation: Rot2, direction: Vec2) -> f32 {
+        let hull = self.hull(Vec2::ZERO, rotation);
+        hull.points()
+            .iter()
+            .fold(f32::NEG_INFINITY, |extent, point| {
+                point.dot(direction).max(extent)
+            })
+            + hull.radius
+    }
+
+    pub(crate) fn hull(&self, position: Vec2, rotation: Rot2) -> Hull {
+        let mut hull = Hull::point(position, 0.0);
+        match *self {
+            ColliderShape::Circle { radius } => hull.radius = radius,
+            ColliderShape::Rect { half_size } => {
+                for (index, corner) in [
+                    Vec2::new(-half_size.x, -half_size.y),
+                    Vec2::new(half_size.x, -half_size.y),
+                    Vec2::new(half_size.x, half_size.y),
+                    Vec2::new(-half_size.x, half_size.y),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    hull.points[index] = position + rotation * corner;
+                }
+                hull.len = 4;
+            }
+            ColliderShape::Polygon(polygon) => {
+                for (index, &vertex) in polygon.vertices().iter().enumerate() {
+                    hull.points[index] = position + rotation * vertex;
+                }
+                hull.len = polygon.len;
+            }
+        }
+        hull
+    }
+}
+
+impl ConvexPolygon {
+    pub fn new(points: &[Vec2]) -> Option<Self> {
+        if points.len() < 3 || points.len() > MAX_POLYGON_VERTICES {
+            return None;
+        }
+
+        let mut sorted = [Vec2::ZERO; MAX_POLYGON_VERTICES];
+        sorted[..points.len()].copy_from_slice(points);
+        let mut hull = [Vec2::ZERO; 2 * MAX_POLYGON_VERTICES];
+        let len = convex_hull(&mut sorted[..points.len()], &mut hull);
+        if len < 3 {
+            return None;
+        }
+
+        let mut vertices = [Vec2::ZERO; MAX_POLYGON_VERTICES];
+        vertices[..len].copy_from_slice(&hull[..len]);
+        Some(ConvexPolygon { vertices, len })
+    }
+
+    pub fn vertices(&self) -> &[Vec2] {
+        &self.vertices[..self.len]
+    }
+}
+
+impl Hull {
+    pub(crate) fn point(position: Vec2, radius: f32) -> Self {
+        let mut points = [Vec2::ZERO; MAX_HULL_POINTS];
+        points[0] = position;
+        Hull {
+            points,
+            len: 1,
+            radius,
+        }
+    }
+
+    pub(crate) fn segment(start: Vec2, end: Vec2) -> Self {
+        let mut hull = Hull::point(start, 0.0);
+        hull.points[1] = end;
+        hull.len = 2;
+        hull
+    }
+
+    fn points(&self) -> &[Vec2] {
+        &self.points[..self.len]
+    }
+
+    fn minkowski_difference(a: &Hull, b: &Hull) -> Self {
+        let mut points = [Vec2::ZERO; MAX_HULL_POINTS];
+        let mut len = 0;
+        for &point_a in a.points() {
+            for &point_b in b.points() {
+                points[len] = point_a - point_b;
+                len += 1;
+            }
+        }
+
+        let mut hull = [Vec2::ZERO; 2 * MAX_HULL_POINTS];
+        let hull_len = convex_hull(&mut points[..len], &mut hull);
+        points[..hull_len].copy_from_slice(&hull[..hull_len]);
+
+        Hull {
+            points,
+            len: hull_len,
+            radius: a.radius + b.radius,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let len = if self.len > 1 { self.len } else { 0 };
+        (0..len).map(move |index| (self.points[index], self.points[(index + 1) % len]))
+    }
+
+    fn cast_origin(&self, direction: Vec2) -> Option<(f32, Dir2)> {
+        if let Some(normal) = self.contains_origin() {
+            return Some((0.0, normal));
+        }
+
+        let mut nearest: Option<(f32, Dir2)> = None;
+        let mut insert = |t: f32, outward: Vec2| {
+            if t >= 0.0 && nearest.is_none_or(|(nearest_t, _)| t < nearest_t) {
+                nearest = Some((t, Dir2::new(-outward).unwrap_or(Dir2::X)));
+            }
+        };
+
+        for (start, end) in self.edges() {
+            let length = start.distance(end);
+            if length == 0.0 {
+                continue;
+            }
+
+            let tangent = (end - start) / length;
+            let outward = Vec2::new(tangent.y, -tangent.x);
+            let speed = direction.dot(outward);
+            if speed >= 0.0 {
+                continue;
+            }
+
+            let t = (-start.dot(outward) - self.radius) / -speed;
+            let along = (direction * t - start).dot(tangent);
+            if (0.0..=length).contains(&along) {
+                insert(t, outward);
+            }
+        }
+
+        if self.radius > 0.0 {
+            for &point in self.points() {
+                if let Some(t) = super::collider_collision(-point, direction, self.radius) {
+                    insert(t, direction * t - point);
+                }
+            }
+        }
+
+        nearest
+    }
+
+    fn contains_origin(&self) -> Option<Dir2> {
+        if self.len >= 3 {
+            let (penetration, outward) = self
+                .edges()
+                .filter_map(|(start, end)| {
+                    let tangent = (end - start).try_normalize()?;
+                    let outward = Vec2::new(tangent.y, -tangent.x);
+                    Some((-start.dot(outward), outward))
+                })
+                .fold((f32::NEG_INFINITY, Vec2::X), |max, edge| {
+                    if edge.0 > max.0 { edge } else { max }
+                });
+            if penetration.is_finite() && penetration <= 0.0 {
+                return Some(Dir2::new(-outward).unwrap_or(Dir2::X));
+            }
+        }
+
+        let nearest = if self.len == 1 {
+            self.points[0]
+        } else {
+            self.edges()
+                .map(|(start, end)| nearest_on_segment(start, end))
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))?
+        };
+
+        if nearest.length_squared() < self.radius * self.radius {
+            Some(Dir2::new(nearest).unwrap_or(Dir2::X))
+        } else {
+            None
+        }
+    }
+}
+
+fn nearest_on_segment(start: Vec2, end: Vec2) -> Vec2 {
+    let edge = end - start;
+    let length_squared = edge.length_squared();
+    if length_squared == 0.0 {
+        return start;
+    }
+
+    let t = (-start.dot(edge) / length_squared).clamp(0.0, 1.0);
+    start + edge * t
+}
+
+fn convex_hull(points: &mut [Vec2], hull: &mut [Vec2]) -> usize {
+    if points.len() < 2 {
+        hull[..points.len()].copy_from_slice(points);
+        return points.len();
+    }
+
+    points.sort_unstable_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+    let cross = |o: Vec2, a: Vec2, b: Vec2| (a - o).perp_dot(b - o);
+
+    let mut len = 0;
+    for &point in points.iter() {
+        while len >= 2 && cross(hull[len - 2], hull[len - 1], point) <= 0.0 {
+            len -= 1;
+        }
+        hull[len] = point;
+        len += 1;
+    }
+
+    let lower = len + 1;
+    for &point in points.iter().rev().skip(1) {
+        while len >= lower && cross(hull[len - 2], hull[len - 1], point) <= 0.0 {
+            len -= 1;
+        }
+        hull[len] = point;
+        len += 1;
+    }
+
+    len - 1
+}
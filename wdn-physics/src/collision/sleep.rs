@@ -0,0 +1,100 @@
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use bevy_time::prelude::*;
+
+use crate::{
+    collision::{
+        Collider, ColliderQuery, ColliderQueryItem, Collisions, SMALL_REACH, TileCollider,
+    },
+    kinematics::GlobalPosition,
+    tile::{index::TileIndex, material::TileMaterialChanged, position::TilePosition},
+};
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Sleep {
+    idle_ticks: u32,
+    position: Vec2,
+}
+
+pub fn wake_colliders(
+    index: Res<TileIndex>,
+    colliders: Query<ColliderQuery, Changed<GlobalPosition>>,
+    tiles: Query<&TilePosition, Changed<TileCollider>>,
+    mut materials: MessageReader<TileMaterialChanged>,
+    mut sleepers: Query<&mut Sleep>,
+    time: Res<Time>,
+) {
+    let delta_secs = time.delta_secs();
+
+    let mut wake = |tile: TilePosition, range: i32| {
+        for y in -range..=range {
+            for x in -range..=range {
+                for &id in index.get_objects(tile.with_offset(x, y)) {
+                    if let Ok(mut sleep) = sleepers.get_mut(id)
+                        && sleep.is_sleeping()
+                    {
+                        sleep.wake();
+                    }
+                }
+            }
+        }
+    };
+
+    // Only colliders that moved since the last tick can have reached a sleeper.
+    for collider in &colliders {
+        if collider.velocity() != Vec2::ZERO || collider.angular_velocity() != 0.0 {
+            let range = (collider.reach(delta_secs) + SMALL_REACH).floor() as i32 + 1;
+            wake(*collider.tile, range);
+        }
+    }
+
+    for &tile in &tiles {
+        wake(tile, 1);
+    }
+
+    for changed in materials.read() {
+        wake(changed.position, 1);
+    }
+}
+
+pub fn update_sleep(mut colliders: Query<(ColliderQuery, Ref<Collider>, &Collisions, &mut Sleep)>) {
+    colliders
+        .iter_mut()
+        .for_each(|(collider, collider_ref, collisions, mut sleep)| {
+            let idle = collider.velocity() == Vec2::ZERO
+                && collider.angular_velocity() == 0.0
+                && collider.position() == sleep.position
+                && collider.shape().bounding_radius() <= SMALL_REACH
+                && !collider_ref.is_changed()
+                && collisions.started().next().is_none()
+                && collisions.ended().next().is_none();
+
+            if idle {
+                if !sleep.is_sleeping() {
+                    sleep.idle_ticks += 1;
+                }
+            } else if sleep.idle_ticks != 0 || sleep.position != collider.position() {
+                sleep.idle_ticks = 0;
+                sleep.position = collider.position();
+            }
+        });
+}
+
+impl Sleep {
+    pub const IDLE_TICKS: u32 = 32;
+
+    pub fn is_sleeping(&self) -> bool {
+        self.idle_ticks >= Sleep::IDLE_TICKS
+    }
+
+    pub fn wake(&mut self) {
+        self.idle_ticks = 0;
+    }
+
+    pub(crate) fn is_resting(&self, collider: &ColliderQueryItem) -> bool {
+        self.is_sleeping()
+            && collider.velocity() == Vec2::ZERO
+            && collider.angular_velocity() == 0.0
+            && collider.position() == self.position
+    }
+}
@@ -1,14 +1,24 @@
-use std::{cmp::Reverse, f32::consts::FRAC_1_SQRT_2, time::Duration};
+use std::{
+    cmp::Reverse,
+    f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2},
+    time::Duration,
+};
 
 use approx::assert_relative_eq;
 use bevy_app::prelude::*;
-use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_ecs::{message::MessageCursor, prelude::*, system::RunSystemOnce};
 use bevy_math::{FloatOrd, prelude::*};
 use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
 
 use crate::{
-    collision::{Collider, CollisionPlugin, CollisionTarget, Collisions, TileCollider},
-    kinematics::{GlobalPosition, KinematicsPlugin, Position, Velocity},
+    collision::{
+        Collider, CollisionEnded, CollisionPlugin, CollisionStarted, CollisionTarget, Collisions,
+        LargeColliderIndex, TileCollider,
+        filter::{CollisionFilter, CollisionGroups},
+        shape::{ColliderShape, ConvexPolygon},
+        sleep::Sleep,
+    },
+    kinematics::{GlobalPosition, GlobalVelocity, KinematicsPlugin, Mass, Position, Velocity},
     layer::Layer,
     tile::{TilePlugin, material::TileMaterial, position::TilePosition, storage::TileStorageMut},
 };
@@ -2259,91 +2269,1076 @@ fn collision_non_solid_tile_collider_inside() {
     }
 }
 
-fn make_app() -> App {
-    let mut app = App::new();
-    app.add_plugins((
-        TaskPoolPlugin::default(),
-        TimePlugin,
-        TilePlugin,
-        KinematicsPlugin,
-        CollisionPlugin,
-    ));
+#[test]
+fn collision_rect_wall_closing() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
 
-    app.insert_resource(Time::<Fixed>::from_duration(Duration::from_secs(1)));
-    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
-    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)));
+    set_tile(&mut app, TilePosition::new(layer, 0, 1));
 
-    app.world_mut()
-        .resource_mut::<Time<Real>>()
-        .update_with_duration(Duration::ZERO);
+    let entity = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.6),
+        Rot2::IDENTITY,
+        Vec2::new(0.0, 0.5),
+        ColliderShape::Rect {
+            half_size: Vec2::new(0.3, 0.1),
+        },
+    );
 
-    app
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.6);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.5, 0.9));
+    assert_eq!(collision.normal, Dir2::NEG_Y);
+    assert!(collision.solid);
+    match collision.target {
+        CollisionTarget::Tile { id, position } => {
+            assert_eq!(position, TilePosition::new(layer, 0, 1));
+            assert!(id.is_none());
+        }
+        _ => panic!("Expected wall collision"),
+    }
 }
 
-fn spawn_layer(app: &mut App) -> Entity {
-    app.world_mut().spawn(Layer::default()).id()
+#[test]
+fn collision_rotated_rect_wall_closing() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 0, 1));
+
+    let entity = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Rot2::degrees(90.0),
+        Vec2::new(0.0, 1.0),
+        ColliderShape::Rect {
+            half_size: Vec2::new(0.2, 0.1),
+        },
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.3, epsilon = 1e-5);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.5, 0.8), epsilon = 1e-5);
+    assert_eq!(collision.normal, Dir2::NEG_Y);
 }
 
-fn spawn_collider(
-    app: &mut App,
-    layer: Entity,
-    position: Vec2,
-    velocity: Vec2,
-    radius: f32,
-) -> Entity {
-    app.world_mut()
-        .spawn((
-            Collider::new(radius, true),
-            Position::new(position, Rot2::IDENTITY),
-            Velocity::new(velocity),
-            ChildOf(layer),
-        ))
-        .id()
+#[test]
+fn collision_rect_wall_sliding() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 0, 1));
+
+    let entity = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.9),
+        Rot2::IDENTITY,
+        Vec2::new(0.5, 0.5),
+        ColliderShape::Rect {
+            half_size: Vec2::new(0.2, 0.1),
+        },
+    );
+
+    app.update();
+
+    let position = app.world().get::<Position>(entity).unwrap();
+    assert_relative_eq!(position.position(), Vec2::new(1.0, 0.9), epsilon = 1e-5);
 }
 
-fn spawn_non_solid_collider(
-    app: &mut App,
-    layer: Entity,
-    position: Vec2,
-    velocity: Vec2,
-    radius: f32,
-) -> Entity {
-    app.world_mut()
-        .spawn((
-            Collider::new(radius, false),
-            Position::new(position, Rot2::IDENTITY),
-            Velocity::new(velocity),
-            ChildOf(layer),
-        ))
-        .id()
+#[test]
+fn collision_rect_corner_closing() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 1, 1));
+
+    let entity = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.6),
+        Rot2::IDENTITY,
+        Vec2::new(0.5, 0.5),
+        ColliderShape::Rect {
+            half_size: Vec2::new(0.1, 0.1),
+        },
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.8, epsilon = 1e-5);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.9, 1.0), epsilon = 1e-5);
+    assert_eq!(collision.normal, Dir2::NEG_X);
+    match collision.target {
+        CollisionTarget::Tile { id, position } => {
+            assert_eq!(position, TilePosition::new(layer, 1, 1));
+            assert!(id.is_none());
+        }
+        _ => panic!("Expected corner collision"),
+    }
 }
 
-fn spawn_tile_collider(app: &mut App, position: TilePosition) -> Entity {
-    app.world_mut()
-        .spawn((
-            TileCollider { solid: true },
-            position,
-            ChildOf(position.layer()),
-        ))
-        .id()
+#[test]
+fn collision_rect_collider() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let rect = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.2, 0.5),
+        Rot2::IDENTITY,
+        Vec2::new(0.5, 0.0),
+        ColliderShape::Rect {
+            half_size: Vec2::new(0.2, 0.2),
+        },
+    );
+    let circle = spawn_collider(&mut app, layer, Vec2::new(0.8, 0.5), Vec2::ZERO, 0.1);
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(rect).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.6, epsilon = 1e-5);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.5, 0.5), epsilon = 1e-5);
+    assert_eq!(collision.normal, Dir2::NEG_X);
+    match collision.target {
+        CollisionTarget::Collider { id, position } => {
+            assert_eq!(id, circle);
+            assert_relative_eq!(position, Vec2::new(0.8, 0.5));
+        }
+        _ => panic!("Expected collider collision"),
+    }
+
+    let collisions = app.world().get::<Collisions>(circle).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.6, epsilon = 1e-5);
+    let collision = collisions.next_collision().unwrap();
+    assert_eq!(collision.normal, Dir2::X);
+    match collision.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, rect),
+        _ => panic!("Expected collider collision"),
+    }
 }
 
-fn spawn_non_solid_tile_collider(app: &mut App, position: TilePosition) -> Entity {
+#[test]
+fn collision_polygon_collider() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let triangle = ConvexPolygon::new(&[
+        Vec2::new(-0.2, -0.2),
+        Vec2::new(0.0, 0.2),
+        Vec2::new(0.2, -0.2),
+    ])
+    .unwrap();
+    let polygon = spawn_shape(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.3),
+        Rot2::IDENTITY,
+        Vec2::new(0.0, 0.5),
+        ColliderShape::Polygon(triangle),
+    );
+    let circle = spawn_collider(&mut app, layer, Vec2::new(0.5, 0.8), Vec2::ZERO, 0.1);
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(polygon).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.4, epsilon = 1e-5);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.5, 0.5), epsilon = 1e-5);
+    assert_eq!(collision.normal, Dir2::NEG_Y);
+    match collision.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, circle),
+        _ => panic!("Expected collider collision"),
+    }
+}
+
+#[test]
+fn convex_polygon_new() {
+    assert!(ConvexPolygon::new(&[Vec2::ZERO, Vec2::X]).is_none());
+    assert!(ConvexPolygon::new(&[Vec2::ZERO, Vec2::X, Vec2::X * 2.0]).is_none());
+    assert!(ConvexPolygon::new(&[Vec2::ZERO; 9]).is_none());
+
+    let polygon = ConvexPolygon::new(&[
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.5, 0.5),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(1.0, 0.0),
+    ])
+    .unwrap();
+    assert_eq!(
+        polygon.vertices(),
+        &[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ]
+    );
+}
+
+#[test]
+fn collision_filter_colliders() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let staff = CollisionFilter::new(
+        CollisionGroups::STAFF,
+        CollisionGroups::all() - CollisionGroups::STAFF,
+    );
+    let entity1 = spawn_collider(&mut app, layer, Vec2::new(0.4, 0.5), Vec2::ZERO, 0.2);
+    let entity2 = spawn_collider(&mut app, layer, Vec2::new(0.6, 0.5), Vec2::ZERO, 0.2);
+    let entity3 = spawn_collider(&mut app, layer, Vec2::new(0.5, 0.6), Vec2::ZERO, 0.2);
+    app.world_mut().entity_mut(entity1).insert(staff);
+    app.world_mut().entity_mut(entity2).insert(staff);
+
+    app.update();
+
+    let targets = |app: &App, entity: Entity| -> Vec<Entity> {
+        let mut targets: Vec<_> = app
+            .world()
+            .get::<Collisions>(entity)
+            .unwrap()
+            .iter()
+            .map(|collision| match collision.target {
+                CollisionTarget::Collider { id, .. } => id,
+                _ => panic!("Expected collider collision"),
+            })
+            .collect();
+        targets.sort();
+        targets
+    };
+
+    assert_eq!(targets(&app, entity1), vec![entity3]);
+    assert_eq!(targets(&app, entity2), vec![entity3]);
+    let mut expected = vec![entity1, entity2];
+    expected.sort();
+    assert_eq!(targets(&app, entity3), expected);
+}
+
+#[test]
+fn collision_filter_sensor() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sensor = spawn_non_solid_collider(&mut app, layer, Vec2::new(0.5, 0.5), Vec2::ZERO, 0.4);
     app.world_mut()
-        .spawn((
-            TileCollider { solid: false },
-            position,
-            ChildOf(position.layer()),
-        ))
-        .id()
+        .entity_mut(sensor)
+        .insert(CollisionFilter::new(
+            CollisionGroups::SENSOR,
+            CollisionGroups::PRISONER,
+        ));
+    let prisoner = spawn_collider(&mut app, layer, Vec2::new(0.3, 0.5), Vec2::ZERO, 0.1);
+    app.world_mut()
+        .entity_mut(prisoner)
+        .insert(CollisionFilter::new(
+            CollisionGroups::PRISONER,
+            CollisionGroups::all(),
+        ));
+    let guard = spawn_collider(&mut app, layer, Vec2::new(0.7, 0.5), Vec2::ZERO, 0.1);
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(sensor).unwrap();
+    assert_eq!(collisions.active().len(), 1);
+    match collisions.active().next().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, prisoner),
+        _ => panic!("Expected collider collision"),
+    }
+
+    let collisions = app.world().get::<Collisions>(guard).unwrap();
+    assert_eq!(collisions.active().len(), 0);
 }
 
-fn set_tile(app: &mut App, position: TilePosition) {
+#[test]
+fn collision_filter_tiles() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 0, 1));
+    spawn_tile_collider(&mut app, TilePosition::new(layer, 0, 0));
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.8),
+        Vec2::new(0.0, 0.5),
+        0.1,
+    );
     app.world_mut()
-        .run_system_once(move |mut storage: TileStorageMut| {
-            storage.set_material(position, TileMaterial::WALL);
-        })
-        .unwrap();
+        .entity_mut(entity)
+        .insert(CollisionFilter::new(
+            CollisionGroups::GHOST,
+            CollisionGroups::all() - CollisionGroups::TILE,
+        ));
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert!(collisions.next_collision().is_none());
+}
+
+#[test]
+fn collision_large_collider() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let large = spawn_collider(&mut app, layer, Vec2::new(0.5, 0.5), Vec2::ZERO, 1.5);
+    let small = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(2.3, 0.5),
+        Vec2::new(-1.0, 0.0),
+        0.1,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(large).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.2, epsilon = 1e-4);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.5, 0.5), epsilon = 1e-4);
+    assert_relative_eq!(*collision.normal, Vec2::NEG_X, epsilon = 1e-4);
+    match collision.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, small),
+        _ => panic!("Expected collider collision"),
+    }
+
+    let collisions = app.world().get::<Collisions>(small).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.2, epsilon = 1e-4);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(2.1, 0.5), epsilon = 1e-4);
+    assert_relative_eq!(*collision.normal, Vec2::X, epsilon = 1e-4);
+    match collision.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, large),
+        _ => panic!("Expected collider collision"),
+    }
+}
+
+#[test]
+fn collision_large_collider_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 0));
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(1.0, 0.0),
+        2.0,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.5, epsilon = 1e-4);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(1.0, 0.5), epsilon = 1e-4);
+    assert_eq!(collision.normal, Dir2::NEG_X);
+    match collision.target {
+        CollisionTarget::Tile { id, position } => {
+            assert_eq!(position, TilePosition::new(layer, 3, 0));
+            assert!(id.is_none());
+        }
+        _ => panic!("Expected wall collision"),
+    }
+}
+
+#[test]
+fn collision_medium_collider_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 2, 0));
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.9, 0.5),
+        Vec2::new(0.0, 0.0),
+        1.2,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 1);
+    assert!(collisions.next_collision().is_none());
+    let collision = collisions.active().next().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(0.9, 0.5));
+    assert_eq!(collision.normal, Dir2::NEG_X);
+    assert!(collision.solid);
+    match collision.target {
+        CollisionTarget::Tile { id, position } => {
+            assert_eq!(position, TilePosition::new(layer, 2, 0));
+            assert!(id.is_none());
+        }
+        _ => panic!("Expected wall collision"),
+    }
+}
+
+#[test]
+fn collision_large_collider_wall_corner() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 3));
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(2.0, 2.0),
+        1.5,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.71967, epsilon = 1e-4);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(
+        collision.position,
+        Vec2::new(1.93934, 1.93934),
+        epsilon = 1e-4
+    );
+    assert_relative_eq!(
+        *collision.normal,
+        Vec2::new(-0.70710677, -0.70710677),
+        epsilon = 1e-4
+    );
+    match collision.target {
+        CollisionTarget::Tile { position, .. } => {
+            assert_eq!(position, TilePosition::new(layer, 3, 3))
+        }
+        _ => panic!("Expected wall collision"),
+    }
+}
+
+#[test]
+fn collision_fast_collider_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 0));
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(5.0, 0.0),
+        0.1,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.48, epsilon = 1e-4);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(2.9, 0.5), epsilon = 1e-4);
+    assert_eq!(collision.normal, Dir2::NEG_X);
+    match collision.target {
+        CollisionTarget::Tile { position, .. } => {
+            assert_eq!(position, TilePosition::new(layer, 3, 0))
+        }
+        _ => panic!("Expected wall collision"),
+    }
+}
+
+#[test]
+fn collision_fast_colliders() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let fast = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(4.0, 0.0),
+        0.2,
+    );
+    let target = spawn_collider(&mut app, layer, Vec2::new(3.5, 0.5), Vec2::ZERO, 0.2);
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(fast).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.65, epsilon = 1e-4);
+    match collisions.next_collision().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, target),
+        _ => panic!("Expected collider collision"),
+    }
+
+    let collisions = app.world().get::<Collisions>(target).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.65, epsilon = 1e-4);
+    match collisions.next_collision().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, fast),
+        _ => panic!("Expected collider collision"),
+    }
+}
+
+#[test]
+fn collision_fast_colliders_reindexed() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let west = spawn_collider(&mut app, layer, Vec2::new(-20.5, 8.5), Vec2::ZERO, 0.2);
+    let east = spawn_collider(&mut app, layer, Vec2::new(-10.5, 8.5), Vec2::ZERO, 0.2);
+
+    app.update();
+
+    let index = app.world().resource::<LargeColliderIndex>();
+    assert!(index.colliders.is_empty());
+
+    for (entity, velocity) in [(west, Vec2::new(10.0, 0.0)), (east, Vec2::new(-10.0, 0.0))] {
+        app.world_mut()
+            .entity_mut(entity)
+            .insert((Velocity::new(velocity), GlobalVelocity::new(velocity, 0.0)));
+    }
+    app.update();
+
+    for (entity, other) in [(west, east), (east, west)] {
+        let collisions = app.world().get::<Collisions>(entity).unwrap();
+        assert_relative_eq!(collisions.next_time().unwrap(), 0.48, epsilon = 1e-4);
+        match collisions.next_collision().unwrap().target {
+            CollisionTarget::Collider { id, .. } => assert_eq!(id, other),
+            _ => panic!("Expected collider collision"),
+        }
+    }
+
+    let velocity = Vec2::new(0.0, 10.0);
+    app.world_mut()
+        .entity_mut(east)
+        .insert((Velocity::new(velocity), GlobalVelocity::new(velocity, 0.0)));
+    app.update();
+    let index = app.world().resource::<LargeColliderIndex>();
+    assert!(index.colliders.contains_key(&east));
+
+    app.world_mut().despawn(east);
+    app.update();
+
+    let index = app.world().resource::<LargeColliderIndex>();
+    assert!(!index.colliders.contains_key(&east));
+    assert!(index.tiles.values().flatten().all(|&id| id != east));
+}
+
+#[test]
+fn collision_fast_colliders_across_tiles() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let fast = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(20.0, 0.0),
+        0.2,
+    );
+    let target = spawn_collider(&mut app, layer, Vec2::new(15.5, 0.5), Vec2::ZERO, 0.2);
+    let missed = spawn_collider(&mut app, layer, Vec2::new(15.5, 3.5), Vec2::ZERO, 0.2);
+    let west = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(-20.5, 8.5),
+        Vec2::new(10.0, 0.0),
+        0.2,
+    );
+    let east = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(-10.5, 8.5),
+        Vec2::new(-10.0, 0.0),
+        0.2,
+    );
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(fast).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.73, epsilon = 1e-4);
+    match collisions.next_collision().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, target),
+        _ => panic!("Expected collider collision"),
+    }
+
+    let collisions = app.world().get::<Collisions>(target).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.73, epsilon = 1e-4);
+    match collisions.next_collision().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, fast),
+        _ => panic!("Expected collider collision"),
+    }
+
+    assert!(
+        app.world()
+            .get::<Collisions>(missed)
+            .unwrap()
+            .next()
+            .is_none()
+    );
+
+    for (entity, other) in [(west, east), (east, west)] {
+        let collisions = app.world().get::<Collisions>(entity).unwrap();
+        assert_relative_eq!(collisions.next_time().unwrap(), 0.48, epsilon = 1e-4);
+        match collisions.next_collision().unwrap().target {
+            CollisionTarget::Collider { id, .. } => assert_eq!(id, other),
+            _ => panic!("Expected collider collision"),
+        }
+    }
+}
+
+#[test]
+fn collision_messages() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sensor = spawn_non_solid_collider(&mut app, layer, Vec2::new(0.5, 0.5), Vec2::ZERO, 0.3);
+    let entity = spawn_collider(&mut app, layer, Vec2::new(0.6, 0.5), Vec2::ZERO, 0.1);
+
+    app.update();
+
+    let mut started_cursor = MessageCursor::default();
+    let mut ended_cursor = MessageCursor::default();
+    let mut started: Vec<_> = started_cursor
+        .read(app.world().resource::<Messages<CollisionStarted>>())
+        .copied()
+        .collect();
+    started.sort_by_key(|message| message.entity);
+    assert_eq!(started.len(), 2);
+    let mut expected = vec![sensor, entity];
+    expected.sort();
+    assert_eq!(
+        started
+            .iter()
+            .map(|message| message.entity)
+            .collect::<Vec<_>>(),
+        expected
+    );
+    let message = started
+        .iter()
+        .find(|message| message.entity == entity)
+        .unwrap();
+    assert_relative_eq!(message.position, Vec2::new(0.6, 0.5));
+    assert_relative_eq!(*message.normal, Vec2::X);
+    match message.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, sensor),
+        _ => panic!("Expected collider collision"),
+    }
+    assert_eq!(
+        ended_cursor
+            .read(app.world().resource::<Messages<CollisionEnded>>())
+            .count(),
+        0
+    );
+
+    app.update();
+
+    assert_eq!(
+        started_cursor
+            .read(app.world().resource::<Messages<CollisionStarted>>())
+            .count(),
+        0
+    );
+    assert_eq!(
+        ended_cursor
+            .read(app.world().resource::<Messages<CollisionEnded>>())
+            .count(),
+        0
+    );
+
+    update_collider(&mut app, entity, Vec2::new(2.5, 0.5), Vec2::ZERO);
+    app.update();
+
+    let ended: Vec<_> = ended_cursor
+        .read(app.world().resource::<Messages<CollisionEnded>>())
+        .copied()
+        .collect();
+    assert_eq!(ended.len(), 2);
+    let message = ended
+        .iter()
+        .find(|message| message.entity == sensor)
+        .unwrap();
+    match message.target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, entity),
+        _ => panic!("Expected collider collision"),
+    }
+    assert_eq!(
+        started_cursor
+            .read(app.world().resource::<Messages<CollisionStarted>>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn collision_orbit_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 0, 1));
+
+    let parent = app
+        .world_mut()
+        .spawn((
+            Position::new(Vec2::new(0.5, 0.5), Rot2::IDENTITY),
+            Velocity::new(Vec2::ZERO).with_angular(FRAC_PI_2),
+            ChildOf(layer),
+        ))
+        .id();
+    let entity = app
+        .world_mut()
+        .spawn((
+            Collider::new(0.1, true),
+            Position::new(Vec2::new(1.0, 0.0), Rot2::IDENTITY),
+            Velocity::default(),
+            ChildOf(parent),
+        ))
+        .id();
+
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(entity).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.5903, epsilon = 1e-2);
+    let collision = collisions.next_collision().unwrap();
+    assert_relative_eq!(collision.position, Vec2::new(1.1, 1.3), epsilon = 1e-2);
+    assert_eq!(collision.normal, Dir2::X);
+    match collision.target {
+        CollisionTarget::Tile { position, .. } => {
+            assert_eq!(position, TilePosition::new(layer, 0, 1))
+        }
+        _ => panic!("Expected wall collision"),
+    }
+}
+
+#[test]
+fn collision_sleep_wake_collider() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sleeper = spawn_collider(&mut app, layer, Vec2::new(2.5, 0.5), Vec2::ZERO, 0.2);
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+
+    let entity = spawn_collider(
+        &mut app,
+        layer,
+        Vec2::new(0.5, 0.5),
+        Vec2::new(2.0, 0.0),
+        0.2,
+    );
+
+    app.update();
+
+    assert!(!app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_relative_eq!(collisions.next_time().unwrap(), 0.8, epsilon = 1e-4);
+    match collisions.next_collision().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, entity),
+        _ => panic!("Expected collider collision"),
+    }
+}
+
+#[test]
+fn collision_sleep_wake_tile() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sleeper = spawn_collider(&mut app, layer, Vec2::new(2.75, 0.5), Vec2::ZERO, 0.3);
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 0));
+    app.update();
+
+    assert!(!app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_eq!(collisions.active().len(), 1);
+    assert_eq!(collisions.active().next().unwrap().normal, Dir2::NEG_X);
+}
+
+#[test]
+fn collision_sleep_distant_tile() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sleeper = spawn_collider(&mut app, layer, Vec2::new(10.5, 0.5), Vec2::ZERO, 0.3);
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 0));
+    app.update();
+
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+}
+
+#[test]
+fn collision_sleep_wake_door() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let door = spawn_tile_collider(&mut app, TilePosition::new(layer, 3, 0));
+    let sleeper = spawn_collider(&mut app, layer, Vec2::new(2.75, 0.5), Vec2::ZERO, 0.3);
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert!(collisions.active().next().unwrap().solid);
+
+    app.world_mut()
+        .get_mut::<TileCollider>(door)
+        .unwrap()
+        .set_solid(false);
+    app.update();
+
+    assert!(!app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_eq!(collisions.active().len(), 1);
+    assert!(!collisions.active().next().unwrap().solid);
+}
+
+#[test]
+fn collision_sleep_despawn_neighbor() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let sleeper = spawn_collider(&mut app, layer, Vec2::new(2.5, 0.5), Vec2::ZERO, 0.2);
+    let neighbor = spawn_non_solid_collider(&mut app, layer, Vec2::new(2.8, 0.5), Vec2::ZERO, 0.2);
+
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert!(app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_eq!(collisions.active().len(), 1);
+
+    app.world_mut().despawn(neighbor);
+    app.update();
+
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_eq!(collisions.active().len(), 0);
+    match collisions.ended().next().unwrap().target {
+        CollisionTarget::Collider { id, .. } => assert_eq!(id, neighbor),
+        _ => panic!("Expected collider collision"),
+    }
+
+    app.update();
+
+    assert!(!app.world().get::<Sleep>(sleeper).unwrap().is_sleeping());
+    let collisions = app.world().get::<Collisions>(sleeper).unwrap();
+    assert_eq!(collisions.iter().count(), 0);
+    assert_eq!(collisions.ended().count(), 0);
+}
+
+#[test]
+fn collision_push_into_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 0));
+    let (pusher, cart) = spawn_pusher_and_cart(&mut app, layer);
+
+    for _ in 0..5 {
+        push(&mut app, pusher);
+
+        let cart_position = app.world().get::<Position>(cart).unwrap().position();
+        let pusher_position = app.world().get::<Position>(pusher).unwrap().position();
+        assert!(cart_position.x <= 2.7 + 1e-4);
+        assert!(pusher_position.x <= cart_position.x - 0.5 + 1e-4);
+    }
+
+    let cart_position = app.world().get::<Position>(cart).unwrap().position();
+    assert_relative_eq!(cart_position.x, 2.7, epsilon = 1e-4);
+}
+
+#[test]
+fn collision_push_into_door() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let door = spawn_tile_collider(&mut app, TilePosition::new(layer, 3, 0));
+    let (pusher, cart) = spawn_pusher_and_cart(&mut app, layer);
+
+    for _ in 0..5 {
+        push(&mut app, pusher);
+
+        let cart_position = app.world().get::<Position>(cart).unwrap().position();
+        let pusher_position = app.world().get::<Position>(pusher).unwrap().position();
+        assert!(cart_position.x <= 2.7 + 1e-4);
+        assert!(pusher_position.x <= cart_position.x - 0.5 + 1e-4);
+    }
+
+    app.world_mut()
+        .get_mut::<TileCollider>(door)
+        .unwrap()
+        .set_solid(false);
+    for _ in 0..5 {
+        push(&mut app, pusher);
+    }
+
+    let cart_position = app.world().get::<Position>(cart).unwrap().position();
+    assert!(cart_position.x > 3.0);
+}
+
+fn make_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        TilePlugin,
+        KinematicsPlugin,
+        CollisionPlugin,
+    ));
+
+    app.insert_resource(Time::<Fixed>::from_duration(Duration::from_secs(1)));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    app
+}
+
+fn spawn_layer(app: &mut App) -> Entity {
+    app.world_mut().spawn(Layer::default()).id()
+}
+
+fn spawn_collider(
+    app: &mut App,
+    layer: Entity,
+    position: Vec2,
+    velocity: Vec2,
+    radius: f32,
+) -> Entity {
+    app.world_mut()
+        .spawn((
+            Collider::new(radius, true),
+            Position::new(position, Rot2::IDENTITY),
+            Velocity::new(velocity),
+            ChildOf(layer),
+        ))
+        .id()
+}
+
+fn spawn_non_solid_collider(
+    app: &mut App,
+    layer: Entity,
+    position: Vec2,
+    velocity: Vec2,
+    radius: f32,
+) -> Entity {
+    app.world_mut()
+        .spawn((
+            Collider::new(radius, false),
+            Position::new(position, Rot2::IDENTITY),
+            Velocity::new(velocity),
+            ChildOf(layer),
+        ))
+        .id()
+}
+
+fn spawn_shape(
+    app: &mut App,
+    layer: Entity,
+    position: Vec2,
+    rotation: Rot2,
+    velocity: Vec2,
+    shape: ColliderShape,
+) -> Entity {
+    app.world_mut()
+        .spawn((
+            Collider::from_shape(shape, true),
+            Position::new(position, rotation),
+            Velocity::new(velocity),
+            ChildOf(layer),
+        ))
+        .id()
+}
+
+fn spawn_tile_collider(app: &mut App, position: TilePosition) -> Entity {
+    app.world_mut()
+        .spawn((
+            TileCollider { solid: true },
+            position,
+            ChildOf(position.layer()),
+        ))
+        .id()
+}
+
+fn spawn_non_solid_tile_collider(app: &mut App, position: TilePosition) -> Entity {
+    app.world_mut()
+        .spawn((
+            TileCollider { solid: false },
+            position,
+            ChildOf(position.layer()),
+        ))
+        .id()
+}
+
+fn set_tile(app: &mut App, position: TilePosition) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, TileMaterial::WALL);
+        })
+        .unwrap();
+}
+
+// A cart resting a small gap from whatever is at (3, 0), with a lighter pusher coming at it.
+fn spawn_pusher_and_cart(app: &mut App, layer: Entity) -> (Entity, Entity) {
+    let cart = spawn_collider(app, layer, Vec2::new(2.6, 0.5), Vec2::ZERO, 0.3);
+    app.world_mut().entity_mut(cart).insert(Mass::new(3.0));
+    for _ in 0..=Sleep::IDLE_TICKS {
+        app.update();
+    }
+    assert_eq!(
+        app.world().get::<Position>(cart).unwrap().position(),
+        Vec2::new(2.6, 0.5)
+    );
+
+    let pusher = spawn_collider(app, layer, Vec2::new(0.5, 0.5), Vec2::ZERO, 0.2);
+    app.world_mut().entity_mut(pusher).insert(Mass::new(1.0));
+    (pusher, cart)
+}
+
+fn push(app: &mut App, pusher: Entity) {
+    app.world_mut().entity_mut(pusher).insert((
+        Velocity::new(Vec2::new(1.0, 0.0)),
+        GlobalVelocity::new(Vec2::new(1.0, 0.0), 0.0),
+    ));
+    app.update();
 }
 
 fn update_collider(app: &mut App, id: Entity, position: Vec2, velocity: Vec2) {
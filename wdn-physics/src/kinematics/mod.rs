@@ -12,7 +12,7 @@ use bevy_time::prelude::*;
 
 use crate::{
     PhysicsSystems,
-    collision::{Collision, Collisions},
+    collision::{Collision, CollisionTarget, Collisions},
     kinematics::sync::{sync_kinematics, sync_kinematics_on_add_global_position},
     tile::position::TilePosition,
 };
@@ -45,41 +45,116 @@ pub struct Velocity {
     angular: f32,
 }
 
+#[derive(Clone, Copy, Component, Debug, PartialEq)]
+#[require(Velocity)]
+pub struct Mass {
+    value: f32,
+}
+
 pub fn update_kinematics(
-    mut query: Query<(&mut Position, &mut Velocity, Option<&Collisions>)>,
+    mut query: Query<(
+        &mut Position,
+        &mut Velocity,
+        Option<&Mass>,
+        Option<&Collisions>,
+    )>,
+    bodies: Query<(&Mass, &GlobalVelocity, Option<&Collisions>)>,
     time: Res<Time>,
 ) {
+    // A body held against a wall or an immovable collider, or about to be this tick, can't be
+    // pushed any further into it, so whatever pushes it collides as if it were static too.
+    let pinned = |collision: &Collision, collisions: Option<&Collisions>| {
+        collisions
+            .into_iter()
+            .flat_map(|collisions| {
+                let next = collisions
+                    .next()
+                    .filter(|&(_, t)| t < time.delta_secs())
+                    .map(|(collision, _)| collision);
+                collisions.active().chain(next)
+            })
+            .any(|contact| {
+                let static_target = match contact.target {
+                    CollisionTarget::Collider { id, .. } => !bodies.contains(id),
+                    CollisionTarget::Tile { .. } => true,
+                };
+                contact.solid && static_target && contact.normal.dot(*collision.normal) > 0.0
+            })
+    };
+
     query
         .par_iter_mut()
         .batching_strategy(BatchingStrategy::new().min_batch_size(16))
-        .for_each(|(mut position, mut velocity, collisions)| {
-            if velocity.is_zero() {
+        .for_each(|(mut position, mut velocity, mass, collisions)| {
+            let body = |collision: &Collision| match (mass, collision.target) {
+                (Some(_), CollisionTarget::Collider { id, .. }) => bodies
+                    .get(id)
+                    .ok()
+                    .filter(|&(_, _, collisions)| !pinned(collision, collisions))
+                    .map(|(mass, velocity, _)| (mass, velocity)),
+                _ => None,
+            };
+
+            // A body at rest only moves when another body is pushing it.
+            if velocity.is_zero()
+                && !collisions.is_some_and(|collisions| {
+                    collisions
+                        .iter()
+                        .any(|collision| collision.solid && body(&collision).is_some())
+                })
+            {
                 return;
             }
 
             let mut delta_secs = time.delta_secs();
 
-            if velocity.linear != Vec2::ZERO {
-                if let Some(collisions) = collisions {
-                    for collision in collisions.active() {
-                        if collision.solid {
-                            velocity.collide(collision);
-                        }
+            if let Some(collisions) = collisions {
+                for collision in collisions.active() {
+                    if collision.solid
+                        && let Some(mass) = mass
+                        && let Some((target_mass, target_velocity)) = body(&collision)
+                    {
+                        velocity.push(collision, mass, target_mass, target_velocity.linear());
                     }
+                }
 
-                    if let Some((collision, t)) = collisions.next() {
-                        position.isometry.translation = collision.position;
-                        delta_secs -= t;
-
+                // Anything already touching stops the body before it can reach the next collision.
+                for collision in collisions.active() {
+                    if collision.solid
+                        && velocity.linear != Vec2::ZERO
+                        && body(&collision).is_none()
+                    {
                         velocity.collide(collision);
                     }
                 }
 
-                if velocity.linear != Vec2::ZERO {
-                    position.isometry.translation += velocity.linear * delta_secs;
+                if let Some((collision, t)) = collisions.next()
+                    && (velocity.linear != Vec2::ZERO || body(&collision).is_some())
+                {
+                    position.isometry.translation = collision.position;
+                    delta_secs -= t;
+
+                    if let Some(mass) = mass
+                        && let Some((target_mass, target_velocity)) = body(&collision)
+                    {
+                        velocity.push(collision, mass, target_mass, target_velocity.linear());
+                    }
+                }
+
+                for collision in collisions.active().chain(collisions.next_collision()) {
+                    if collision.solid
+                        && velocity.linear != Vec2::ZERO
+                        && body(&collision).is_none()
+                    {
+                        velocity.collide(collision);
+                    }
                 }
             }
 
+            if velocity.linear != Vec2::ZERO {
+                position.isometry.translation += velocity.linear * delta_secs;
+            }
+
             if velocity.angular != 0.0 {
                 position.isometry.rotation *= Rot2::radians(velocity.angular * delta_secs);
             }
@@ -109,6 +184,20 @@ impl Plugin for KinematicsPlugin {
     }
 }
 
+impl Mass {
+    pub fn new(value: f32) -> Self {
+        assert!(
+            value > 0.0 && value.is_finite(),
+            "mass must be positive and finite"
+        );
+        Mass { value }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
+
 impl GlobalPosition {
     pub fn new(position: Vec2, rotation: Rot2) -> Self {
         Self {
@@ -212,6 +301,21 @@ impl Velocity {
         self.linear += (target - self.linear).clamp_length_max(accel);
     }
 
+    pub fn push(
+        &mut self,
+        collision: Collision,
+        mass: &Mass,
+        target_mass: &Mass,
+        target_velocity: Vec2,
+    ) {
+        let normal = collision.normal;
+        let projected = (self.linear - target_velocity).dot(*normal);
+        if projected < 0.0 {
+            let share = target_mass.value / (mass.value + target_mass.value);
+            self.linear -= projected * share * normal;
+        }
+    }
+
     pub fn collide(&mut self, collision: Collision) {
         let normal = collision.normal;
         let projected = self.linear.dot(*normal);
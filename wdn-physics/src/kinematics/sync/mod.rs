@@ -7,6 +7,7 @@ use bevy_ecs::{
 use bevy_math::prelude::*;
 
 use crate::{
+    Deterministic,
     kinematics::{GlobalPosition, GlobalVelocity, Position, Velocity},
     layer::Layer,
     tile::position::TilePosition,
@@ -34,7 +35,17 @@ pub fn sync_kinematics(
     mut entities: Query<SyncQuery>,
     parents: Query<SyncRelativeQuery>,
     layers: Query<&Layer>,
+    deterministic: Option<Res<Deterministic>>,
 ) {
+    if deterministic.is_some() {
+        commands.command_scope(|mut commands| {
+            entities
+                .iter_mut()
+                .for_each(|mut item| item.sync(&mut commands, &layers, &parents))
+        });
+        return;
+    }
+
     entities
         .par_iter_mut()
         .batching_strategy(BatchingStrategy::new().min_batch_size(16))
@@ -8,7 +8,7 @@ use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
 
 use crate::{
     collision::{Collision, CollisionTarget, Collisions},
-    kinematics::{KinematicsPlugin, Position, Velocity},
+    kinematics::{GlobalVelocity, KinematicsPlugin, Mass, Position, Velocity},
     layer::Layer,
     tile::TilePlugin,
 };
@@ -363,6 +363,174 @@ fn update_kinematics_non_solid_wall_collision_active() {
     assert_relative_eq!(position.position(), Vec2::new(3.0, -5.0));
 }
 
+#[test]
+fn update_kinematics_push() {
+    let (mut app, layer) = make_app();
+
+    let pawn = app.world_mut().spawn_empty().id();
+    let cart = app.world_mut().spawn_empty().id();
+
+    let mut pawn_collisions = Collisions::default();
+    pawn_collisions.insert(
+        Collision {
+            position: Vec2::new(0.0, 0.0),
+            normal: Dir2::NEG_X,
+            target: CollisionTarget::Collider {
+                id: cart,
+                position: Vec2::new(0.5, 0.0),
+            },
+            solid: true,
+        },
+        0.0,
+    );
+    let mut cart_collisions = Collisions::default();
+    cart_collisions.insert(
+        Collision {
+            position: Vec2::new(0.5, 0.0),
+            normal: Dir2::X,
+            target: CollisionTarget::Collider {
+                id: pawn,
+                position: Vec2::new(0.0, 0.0),
+            },
+            solid: true,
+        },
+        0.0,
+    );
+
+    app.world_mut().entity_mut(pawn).insert((
+        Position::new(Vec2::ZERO, Rot2::IDENTITY),
+        Velocity::new(Vec2::new(2.0, 0.0)),
+        GlobalVelocity::new(Vec2::new(2.0, 0.0), 0.0),
+        Mass::new(1.0),
+        ChildOf(layer),
+        pawn_collisions,
+    ));
+    app.world_mut().entity_mut(cart).insert((
+        Position::new(Vec2::new(0.5, 0.0), Rot2::IDENTITY),
+        Velocity::new(Vec2::ZERO),
+        GlobalVelocity::new(Vec2::ZERO, 0.0),
+        Mass::new(3.0),
+        ChildOf(layer),
+        cart_collisions,
+    ));
+
+    app.update();
+
+    let velocity = app.world().get::<Velocity>(pawn).unwrap();
+    assert_relative_eq!(velocity.linear(), Vec2::new(0.5, 0.0));
+    let position = app.world().get::<Position>(pawn).unwrap();
+    assert_relative_eq!(position.position(), Vec2::new(0.5, 0.0));
+
+    let velocity = app.world().get::<Velocity>(cart).unwrap();
+    assert_relative_eq!(velocity.linear(), Vec2::new(0.5, 0.0));
+    let position = app.world().get::<Position>(cart).unwrap();
+    assert_relative_eq!(position.position(), Vec2::new(1.0, 0.0));
+}
+
+#[test]
+fn update_kinematics_push_wall() {
+    let (mut app, layer) = make_app();
+
+    let pawn = app
+        .world_mut()
+        .spawn((
+            Position::new(Vec2::ZERO, Rot2::IDENTITY),
+            Velocity::new(Vec2::new(2.0, 0.0)),
+            GlobalVelocity::new(Vec2::new(2.0, 0.0), 0.0),
+            Mass::new(1.0),
+            ChildOf(layer),
+        ))
+        .id();
+
+    let mut collisions = Collisions::default();
+    collisions.insert(
+        Collision {
+            position: Vec2::new(0.5, 0.0),
+            normal: Dir2::NEG_X,
+            target: CollisionTarget::Tile {
+                id: None,
+                position: Default::default(),
+            },
+            solid: true,
+        },
+        0.0,
+    );
+    collisions.insert(
+        Collision {
+            position: Vec2::new(0.5, 0.0),
+            normal: Dir2::X,
+            target: CollisionTarget::Collider {
+                id: pawn,
+                position: Vec2::ZERO,
+            },
+            solid: true,
+        },
+        0.0,
+    );
+
+    let cart = app
+        .world_mut()
+        .spawn((
+            Position::new(Vec2::new(0.5, 0.0), Rot2::IDENTITY),
+            Velocity::new(Vec2::ZERO),
+            Mass::new(1.0),
+            ChildOf(layer),
+            collisions,
+        ))
+        .id();
+
+    app.update();
+
+    let velocity = app.world().get::<Velocity>(cart).unwrap();
+    assert_relative_eq!(velocity.linear(), Vec2::ZERO);
+    let position = app.world().get::<Position>(cart).unwrap();
+    assert_relative_eq!(position.position(), Vec2::new(0.5, 0.0));
+}
+
+#[test]
+fn update_kinematics_push_static() {
+    let (mut app, layer) = make_app();
+
+    let other_entity = app
+        .world_mut()
+        .spawn((
+            Position::new(Vec2::new(0.5, 0.0), Rot2::IDENTITY),
+            Velocity::new(Vec2::ZERO),
+            ChildOf(layer),
+        ))
+        .id();
+
+    let mut collisions = Collisions::default();
+    collisions.insert(
+        Collision {
+            position: Vec2::ZERO,
+            normal: Dir2::NEG_X,
+            target: CollisionTarget::Collider {
+                id: other_entity,
+                position: Vec2::new(0.5, 0.0),
+            },
+            solid: true,
+        },
+        0.0,
+    );
+
+    let entity = app
+        .world_mut()
+        .spawn((
+            Position::new(Vec2::ZERO, Rot2::IDENTITY),
+            Velocity::new(Vec2::new(2.0, 1.0)),
+            Mass::new(1.0),
+            ChildOf(layer),
+            collisions,
+        ))
+        .id();
+
+    app.update();
+
+    let velocity = app.world().get::<Velocity>(entity).unwrap();
+    assert_relative_eq!(velocity.linear(), Vec2::new(0.0, 1.0));
+}
+
 fn make_app() -> (App, Entity) {
     let mut app = App::new();
     app.add_plugins((
@@ -384,3 +552,9 @@ fn make_app() -> (App, Entity) {
 
     (app, layer)
 }
+
+#[test]
+#[should_panic = "mass must be positive and finite"]
+fn mass_zero() {
+    Mass::new(0.0);
+}
@@ -1,6 +1,7 @@
 pub mod collision;
 pub mod kinematics;
 pub mod layer;
+pub mod query;
 pub mod tile;
 
 use bevy_app::prelude::*;
@@ -10,6 +11,9 @@ use crate::{collision::CollisionPlugin, kinematics::KinematicsPlugin, tile::Tile
 
 pub struct PhysicsPlugin;
 
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct Deterministic;
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash, SystemSet)]
 pub enum PhysicsSystems {
     Collisions,
@@ -0,0 +1,211 @@
+#[cfg(test)]
+mod tests;
+
+use bevy_ecs::{prelude::*, system::SystemParam};
+use bevy_math::{FloatOrd, prelude::*};
+
+use crate::{
+    collision::{
+        ColliderQuery, ColliderQueryItem, TileCollider,
+        filter::CollisionFilter,
+        shape::{ColliderShape, Hull, shape_overlap},
+    },
+    tile::{index::TileIndex, material::TileKind, position::TilePosition, storage::TileStorage},
+};
+
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's> {
+    pub index: Res<'w, TileIndex>,
+    pub storage: TileStorage<'w, 's>,
+    colliders: Query<'w, 's, ColliderQuery>,
+    tiles: Query<'w, 's, &'static TileCollider>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub distance: f32,
+    pub position: Vec2,
+    pub normal: Dir2,
+    pub tile: TilePosition,
+    pub entity: Option<Entity>,
+}
+
+impl<'w, 's> SpatialQuery<'w, 's> {
+    pub const MARGIN: i32 = 1;
+
+    pub fn overlap_circle(
+        &self,
+        layer: Entity,
+        center: Vec2,
+        radius: f32,
+        filter: &CollisionFilter,
+    ) -> impl Iterator<Item = Entity> {
+        let hull = ColliderShape::Circle { radius }.hull(center, Rot2::IDENTITY);
+        self.overlap(layer, center - radius, center + radius, filter, hull)
+    }
+
+    pub fn overlap_rect(
+        &self,
+        layer: Entity,
+        rect: Rect,
+        filter: &CollisionFilter,
+    ) -> impl Iterator<Item = Entity> {
+        let hull = ColliderShape::Rect {
+            half_size: rect.half_size(),
+        }
+        .hull(rect.center(), Rot2::IDENTITY);
+        self.overlap(layer, rect.min, rect.max, filter, hull)
+    }
+
+    pub fn nearest(
+        &self,
+        layer: Entity,
+        position: Vec2,
+        max_distance: f32,
+        filter: &CollisionFilter,
+    ) -> Option<(Entity, f32)> {
+        let center = TilePosition::floor(layer, position);
+        let max_ring = max_distance.ceil() as i32 + Self::MARGIN;
+
+        let mut nearest: Option<(Entity, f32)> = None;
+        for ring in 0..=max_ring {
+            if let Some((_, distance)) = nearest
+                && distance <= (ring - Self::MARGIN) as f32
+            {
+                break;
+            }
+
+            for tile in ring_tiles(center, ring) {
+                for collider in self.colliders_at(tile, *filter) {
+                    let distance = collider.position().distance(position);
+                    if distance > max_distance {
+                        continue;
+                    }
+
+                    let candidate = (collider.id(), distance);
+                    if nearest.is_none_or(|nearest| {
+                        (FloatOrd(distance), collider.id()) < (FloatOrd(nearest.1), nearest.0)
+                    }) {
+                        nearest = Some(candidate);
+                    }
+                }
+            }
+        }
+
+        nearest
+    }
+
+    pub fn cast_ray(
+        &self,
+        layer: Entity,
+        origin: Vec2,
+        direction: Dir2,
+        max_distance: f32,
+    ) -> Option<RayHit> {
+        let mut tile = TilePosition::floor(layer, origin);
+        let step = IVec2::new(direction.x.signum() as i32, direction.y.signum() as i32);
+        let delta = Vec2::new(1.0 / direction.x.abs(), 1.0 / direction.y.abs());
+        let mut next = Vec2::new(
+            boundary_distance(origin.x, tile.x(), direction.x),
+            boundary_distance(origin.y, tile.y(), direction.y),
+        );
+
+        let mut distance = 0.0;
+        let mut normal = -direction;
+        loop {
+            if self.blocks_sight(tile) {
+                return Some(RayHit {
+                    distance,
+                    position: origin + direction * distance,
+                    normal,
+                    tile,
+                    entity: self.index.get_tile(tile),
+                });
+            }
+
+            if next.x < next.y {
+                distance = next.x;
+                next.x += delta.x;
+                tile = tile.with_offset(step.x, 0);
+                normal = if step.x > 0 { Dir2::NEG_X } else { Dir2::X };
+            } else {
+                distance = next.y;
+                next.y += delta.y;
+                tile = tile.with_offset(0, step.y);
+                normal = if step.y > 0 { Dir2::NEG_Y } else { Dir2::Y };
+            }
+
+            if distance > max_distance {
+                return None;
+            }
+        }
+    }
+
+    pub fn line_of_sight(&self, layer: Entity, from: Vec2, to: Vec2) -> bool {
+        match Dir2::new(to - from) {
+            Ok(direction) => self
+                .cast_ray(layer, from, direction, from.distance(to))
+                .is_none(),
+            Err(_) => !self.blocks_sight(TilePosition::floor(layer, from)),
+        }
+    }
+
+    pub fn blocks_sight(&self, tile: TilePosition) -> bool {
+        if self.storage.get_kind(tile) == TileKind::Wall {
+            return true;
+        }
+
+        self.index
+            .get_tile(tile)
+            .and_then(|id| self.tiles.get(id).ok())
+            .is_some_and(|collider| collider.solid())
+    }
+
+    fn overlap(
+        &self,
+        layer: Entity,
+        min: Vec2,
+        max: Vec2,
+        filter: &CollisionFilter,
+        hull: Hull,
+    ) -> impl Iterator<Item = Entity> {
+        let min = TilePosition::floor(layer, min).with_offset(-Self::MARGIN, -Self::MARGIN);
+        let max = TilePosition::floor(layer, max).with_offset(Self::MARGIN, Self::MARGIN);
+        let filter = *filter;
+
+        (min.y()..=max.y())
+            .flat_map(move |y| (min.x()..=max.x()).map(move |x| TilePosition::new(layer, x, y)))
+            .flat_map(move |tile| self.colliders_at(tile, filter))
+            .filter(move |collider| shape_overlap(&collider.hull(), &hull))
+            .map(|collider| collider.id())
+    }
+
+    fn colliders_at(
+        &self,
+        tile: TilePosition,
+        filter: CollisionFilter,
+    ) -> impl Iterator<Item = ColliderQueryItem<'_, 's>> {
+        self.index
+            .get_objects(tile)
+            .iter()
+            .filter_map(|&id| self.colliders.get(id).ok())
+            .filter(move |collider| filter.interacts(collider.filter()))
+    }
+}
+
+fn ring_tiles(center: TilePosition, ring: i32) -> impl Iterator<Item = TilePosition> {
+    (-ring..=ring)
+        .flat_map(move |y| (-ring..=ring).map(move |x| (x, y)))
+        .filter(move |&(x, y)| x.abs() == ring || y.abs() == ring)
+        .map(move |(x, y)| center.with_offset(x, y))
+}
+
+fn boundary_distance(origin: f32, tile: i32, direction: f32) -> f32 {
+    if direction > 0.0 {
+        (tile as f32 + 1.0 - origin) / direction
+    } else if direction < 0.0 {
+        (tile as f32 - origin) / direction
+    } else {
+        f32::INFINITY
+    }
+}
@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use approx::assert_relative_eq;
+use bevy_app::prelude::*;
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
+
+use crate::{
+    collision::{
+        Collider, CollisionPlugin, TileCollider,
+        filter::{CollisionFilter, CollisionGroups},
+    },
+    kinematics::{KinematicsPlugin, Position},
+    layer::Layer,
+    query::SpatialQuery,
+    tile::{TilePlugin, material::TileMaterial, position::TilePosition, storage::TileStorageMut},
+};
+
+#[test]
+fn overlap_circle() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let near = spawn_collider(&mut app, layer, Vec2::new(0.5, 0.5), 0.2);
+    let edge = spawn_collider(&mut app, layer, Vec2::new(1.6, 0.5), 0.2);
+    spawn_collider(&mut app, layer, Vec2::new(3.5, 0.5), 0.2);
+    let prisoner = spawn_collider(&mut app, layer, Vec2::new(0.5, 1.5), 0.2);
+    app.world_mut()
+        .entity_mut(prisoner)
+        .insert(CollisionFilter::new(
+            CollisionGroups::PRISONER,
+            CollisionGroups::all(),
+        ));
+
+    app.update();
+
+    let mut found = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            query
+                .overlap_circle(layer, Vec2::new(0.5, 0.5), 1.0, &CollisionFilter::DEFAULT)
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    found.sort();
+    let mut expected = vec![near, edge, prisoner];
+    expected.sort();
+    assert_eq!(found, expected);
+
+    let found = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            let filter = CollisionFilter::new(CollisionGroups::SENSOR, CollisionGroups::PRISONER);
+            query
+                .overlap_circle(layer, Vec2::new(0.5, 0.5), 1.0, &filter)
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    assert_eq!(found, vec![prisoner]);
+}
+
+#[test]
+fn overlap_rect() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    let inside = spawn_collider(&mut app, layer, Vec2::new(1.5, 1.5), 0.2);
+    let touching = spawn_collider(&mut app, layer, Vec2::new(3.1, 1.5), 0.2);
+    spawn_collider(&mut app, layer, Vec2::new(3.5, 1.5), 0.2);
+
+    app.update();
+
+    let mut found = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            query
+                .overlap_rect(
+                    layer,
+                    Rect::new(1.0, 1.0, 3.0, 2.0),
+                    &CollisionFilter::DEFAULT,
+                )
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    found.sort();
+    let mut expected = vec![inside, touching];
+    expected.sort();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn nearest() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    spawn_collider(&mut app, layer, Vec2::new(4.5, 0.5), 0.2);
+    let closest = spawn_collider(&mut app, layer, Vec2::new(-1.5, 2.5), 0.2);
+    let prisoner = spawn_collider(&mut app, layer, Vec2::new(0.5, -3.5), 0.2);
+    app.world_mut()
+        .entity_mut(prisoner)
+        .insert(CollisionFilter::new(
+            CollisionGroups::PRISONER,
+            CollisionGroups::all(),
+        ));
+
+    app.update();
+
+    let (nearest, filtered, limited) = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            let filter = CollisionFilter::new(CollisionGroups::STAFF, CollisionGroups::PRISONER);
+            (
+                query.nearest(layer, Vec2::new(0.5, 0.5), 10.0, &CollisionFilter::DEFAULT),
+                query.nearest(layer, Vec2::new(0.5, 0.5), 10.0, &filter),
+                query.nearest(layer, Vec2::new(0.5, 0.5), 2.0, &CollisionFilter::DEFAULT),
+            )
+        })
+        .unwrap();
+
+    let (id, distance) = nearest.unwrap();
+    assert_eq!(id, closest);
+    assert_relative_eq!(distance, 8.0f32.sqrt());
+    let (id, distance) = filtered.unwrap();
+    assert_eq!(id, prisoner);
+    assert_relative_eq!(distance, 4.0);
+    assert!(limited.is_none());
+}
+
+#[test]
+fn cast_ray_wall() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 3, 1), TileMaterial::WALL);
+
+    app.update();
+
+    let (hit, miss, short) = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            (
+                query.cast_ray(layer, Vec2::new(0.5, 1.5), Dir2::X, 10.0),
+                query.cast_ray(layer, Vec2::new(0.5, 1.5), Dir2::Y, 10.0),
+                query.cast_ray(layer, Vec2::new(0.5, 1.5), Dir2::X, 2.0),
+            )
+        })
+        .unwrap();
+
+    let hit = hit.unwrap();
+    assert_relative_eq!(hit.distance, 2.5);
+    assert_relative_eq!(hit.position, Vec2::new(3.0, 1.5));
+    assert_eq!(hit.normal, Dir2::NEG_X);
+    assert_eq!(hit.tile, TilePosition::new(layer, 3, 1));
+    assert_eq!(hit.entity, None);
+    assert!(miss.is_none());
+    assert!(short.is_none());
+}
+
+#[test]
+fn line_of_sight_doors() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+
+    set_tile(&mut app, TilePosition::new(layer, 2, 0), TileMaterial::WALL);
+    set_tile(&mut app, TilePosition::new(layer, 2, 1), TileMaterial::DOOR);
+    set_tile(&mut app, TilePosition::new(layer, 2, 2), TileMaterial::DOOR);
+    let closed = spawn_tile_collider(&mut app, TilePosition::new(layer, 2, 1), true);
+    spawn_tile_collider(&mut app, TilePosition::new(layer, 2, 2), false);
+
+    app.update();
+
+    let (wall, closed_door, open_door, diagonal, hit) = app
+        .world_mut()
+        .run_system_once(move |query: SpatialQuery| {
+            (
+                query.line_of_sight(layer, Vec2::new(0.5, 0.5), Vec2::new(4.5, 0.5)),
+                query.line_of_sight(layer, Vec2::new(0.5, 1.5), Vec2::new(4.5, 1.5)),
+                query.line_of_sight(layer, Vec2::new(0.5, 2.5), Vec2::new(4.5, 2.5)),
+                query.line_of_sight(layer, Vec2::new(0.5, 3.5), Vec2::new(4.5, 2.8)),
+                query.cast_ray(layer, Vec2::new(4.5, 1.5), Dir2::NEG_X, 10.0),
+            )
+        })
+        .unwrap();
+
+    assert!(!wall);
+    assert!(!closed_door);
+    assert!(open_door);
+    assert!(diagonal);
+
+    let hit = hit.unwrap();
+    assert_relative_eq!(hit.distance, 1.5);
+    assert_eq!(hit.normal, Dir2::X);
+    assert_eq!(hit.entity, Some(closed));
+}
+
+fn make_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        TilePlugin,
+        KinematicsPlugin,
+        CollisionPlugin,
+    ));
+
+    app.insert_resource(Time::<Fixed>::from_duration(Duration::from_secs(1)));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    app
+}
+
+fn spawn_layer(app: &mut App) -> Entity {
+    app.world_mut().spawn(Layer::default()).id()
+}
+
+fn spawn_collider(app: &mut App, layer: Entity, position: Vec2, radius: f32) -> Entity {
+    app.world_mut()
+        .spawn((
+            Collider::new(radius, true),
+            Position::new(position, Rot2::IDENTITY),
+            ChildOf(layer),
+        ))
+        .id()
+}
+
+fn spawn_tile_collider(app: &mut App, position: TilePosition, solid: bool) -> Entity {
+    app.world_mut()
+        .spawn((
+            TileCollider::new(solid),
+            position,
+            ChildOf(position.layer()),
+        ))
+        .id()
+}
+
+fn set_tile(app: &mut App, position: TilePosition, material: TileMaterial) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, material);
+        })
+        .unwrap();
+}
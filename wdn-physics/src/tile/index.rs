@@ -65,6 +65,10 @@ impl TileIndex {
             None => None,
         }
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TilePosition, &TileIndexEntry)> {
+        self.index.iter().map(|(&tile, entry)| (tile, entry))
+    }
 }
 
 impl TileIndexEntry {
@@ -12,6 +12,13 @@ pub const TILE_FAST_SPEED: f32 = 1.4;
 #[require(TilePosition)]
 pub struct TileMaterial(u16);
 
+#[derive(Clone, Copy, Debug, Message)]
+pub struct TileMaterialChanged {
+    pub position: TilePosition,
+    pub old: TileMaterial,
+    pub new: TileMaterial,
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum TileKind {
@@ -34,6 +41,7 @@ impl TileMaterial {
     pub const EMPTY: Self = TileMaterial::new(TileKind::Empty, TileMoveSpeed::Medium, 0);
     pub const WALL: Self = TileMaterial::new(TileKind::Wall, TileMoveSpeed::Medium, 0);
     pub const DOOR: Self = TileMaterial::new(TileKind::Door, TileMoveSpeed::Medium, 0);
+    pub const STAIRS: Self = TileMaterial::new(TileKind::Stairs, TileMoveSpeed::Medium, 0);
     pub const SLOW: Self = TileMaterial::new(TileKind::Empty, TileMoveSpeed::Slow, 0);
     pub const FAST: Self = TileMaterial::new(TileKind::Empty, TileMoveSpeed::Fast, 0);
 
@@ -53,6 +61,18 @@ impl TileMaterial {
     pub fn move_speed(&self) -> TileMoveSpeed {
         TileMoveSpeed::from_bits((self.0 >> 12) & 0b11)
     }
+
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn try_from_bits(bits: u16) -> Option<Self> {
+        if (bits >> 12) & 0b11 == 0b11 {
+            None
+        } else {
+            Some(TileMaterial(bits))
+        }
+    }
 }
 
 impl Default for TileMaterial {
@@ -142,7 +162,13 @@ fn test_pack_tile_material() {
                 assert_eq!(material.kind(), kind);
                 assert_eq!(material.move_speed(), speed);
                 assert_eq!(material.id(), id);
+                assert_eq!(TileMaterial::try_from_bits(material.bits()), Some(material));
             }
         }
     }
 }
+
+#[test]
+fn test_tile_material_invalid_bits() {
+    assert_eq!(TileMaterial::try_from_bits(0b0011_0000_0000_0000), None);
+}
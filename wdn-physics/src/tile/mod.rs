@@ -14,7 +14,7 @@ use bevy_ecs::{component::Component, name::Name};
 use crate::tile::{
     adjacency::TileAdjacency,
     index::TileIndex,
-    material::TileMaterial,
+    material::{TileMaterial, TileMaterialChanged},
     position::{TilePosition, on_insert_tile_position},
     storage::{TileMap, TileMapBuffer},
 };
@@ -34,6 +34,8 @@ impl Plugin for TilePlugin {
             .init_resource::<TileMap>()
             .init_resource::<TileMapBuffer>();
 
+        app.add_message::<TileMaterialChanged>();
+
         app.world_mut()
             .add_observer(on_insert_tile_position)
             .insert(Name::new(format!(
@@ -0,0 +1,82 @@
+use bevy_ecs::prelude::*;
+use bevy_platform::collections::HashMap;
+
+use crate::tile::{CHUNK_SIZE_SQUARED, material::TileMaterial};
+
+const MAX_RUN_LENGTH: usize = u8::MAX as usize + 1;
+
+pub fn encode_chunk(materials: &[TileMaterial], bytes: &mut Vec<u8>) {
+    debug_assert_eq!(materials.len(), CHUNK_SIZE_SQUARED);
+
+    let mut palette = Vec::new();
+    let mut palette_indices = HashMap::new();
+    let indices: Vec<u16> = materials
+        .iter()
+        .map(|&material| {
+            *palette_indices.entry(material).or_insert_with(|| {
+                palette.push(material);
+                (palette.len() - 1) as u16
+            })
+        })
+        .collect();
+
+    bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    for material in &palette {
+        bytes.extend_from_slice(&material.bits().to_le_bytes());
+    }
+
+    let wide = palette.len() > u8::MAX as usize + 1;
+    for run in indices.chunk_by(|a, b| a == b) {
+        for run in run.chunks(MAX_RUN_LENGTH) {
+            bytes.push((run.len() - 1) as u8);
+            if wide {
+                bytes.extend_from_slice(&run[0].to_le_bytes());
+            } else {
+                bytes.push(run[0] as u8);
+            }
+        }
+    }
+}
+
+pub fn decode_chunk(bytes: &mut &[u8]) -> Result<Vec<TileMaterial>> {
+    let palette_len = u16::from_le_bytes(read_array(bytes)?) as usize;
+    if palette_len == 0 || palette_len > CHUNK_SIZE_SQUARED {
+        return Err(format!("invalid chunk palette length {palette_len}").into());
+    }
+
+    let mut palette = Vec::with_capacity(palette_len);
+    for _ in 0..palette_len {
+        let bits = u16::from_le_bytes(read_array(bytes)?);
+        palette.push(TileMaterial::try_from_bits(bits).ok_or("invalid tile material")?);
+    }
+
+    let wide = palette_len > u8::MAX as usize + 1;
+    let mut materials = Vec::with_capacity(CHUNK_SIZE_SQUARED);
+    while materials.len() < CHUNK_SIZE_SQUARED {
+        let [run_len] = read_array(bytes)?;
+        let index = if wide {
+            u16::from_le_bytes(read_array(bytes)?) as usize
+        } else {
+            u8::from_le_bytes(read_array(bytes)?) as usize
+        };
+
+        let material = *palette.get(index).ok_or("invalid chunk palette index")?;
+        let run_len = run_len as usize + 1;
+        if materials.len() + run_len > CHUNK_SIZE_SQUARED {
+            return Err("chunk run exceeds chunk size".into());
+        }
+
+        materials.extend(std::iter::repeat_n(material, run_len));
+    }
+
+    Ok(materials)
+}
+
+fn read_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N]> {
+    let Some((array, rest)) = bytes.split_first_chunk::<N>() else {
+        return Err("unexpected end of chunk data".into());
+    };
+
+    *bytes = rest;
+    Ok(*array)
+}
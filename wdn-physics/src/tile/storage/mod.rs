@@ -1,3 +1,4 @@
+pub mod codec;
 #[cfg(test)]
 mod tests;
 
@@ -15,7 +16,7 @@ use crate::tile::{
     CHUNK_SIZE_SQUARED,
     adjacency::{Adjacency, TileAdjacency},
     index::TileIndex,
-    material::{TileKind, TileMaterial, TileMoveSpeed},
+    material::{TileKind, TileMaterial, TileMaterialChanged, TileMoveSpeed},
     position::{TileChunkOffset, TileChunkPosition, TilePosition},
 };
 
@@ -33,6 +34,7 @@ pub struct TileStorageMut<'w, 's> {
     pub chunks: Query<'w, 's, &'static mut TileChunk>,
     pub materials: Query<'w, 's, &'static mut TileMaterial>,
     pub adjacencies: Query<'w, 's, &'static mut TileAdjacency>,
+    changed: MessageWriter<'w, TileMaterialChanged>,
     buffer: ResMut<'w, TileMapBuffer>,
     deferred: Deferred<'s, TileStorageDeferred>,
 }
@@ -150,6 +152,11 @@ impl TileStorageMut<'_, '_> {
         }
 
         tile.material = material;
+        self.changed.write(TileMaterialChanged {
+            position,
+            old: prev_material,
+            new: material,
+        });
 
         self.visit_entity_materials(position, |entity_material| {
             *entity_material = material;
@@ -173,6 +180,15 @@ impl TileStorageMut<'_, '_> {
         }
     }
 
+    pub fn set_chunk(&mut self, position: TileChunkPosition, materials: &[TileMaterial]) {
+        debug_assert_eq!(materials.len(), CHUNK_SIZE_SQUARED);
+
+        self.chunk_mut(position);
+        for (offset, &material) in TileChunkOffset::iter().zip(materials) {
+            self.set_material(TilePosition::from((position, offset)), material);
+        }
+    }
+
     pub fn chunk(&'_ self, position: TileChunkPosition) -> Option<&TileChunk> {
         if let Some(chunk_entity) = self.map.get(position) {
             Some(self.chunks.get(chunk_entity).expect("invalid chunk entity"))
@@ -293,6 +309,10 @@ impl TileChunk {
         &mut self.tiles[offset.index()]
     }
 
+    pub fn materials(&self) -> impl ExactSizeIterator<Item = TileMaterial> {
+        self.tiles.iter().map(|tile| tile.material)
+    }
+
     pub fn tiles(&self) -> impl ExactSizeIterator<Item = (TileChunkOffset, TileData)> {
         self.tiles
             .iter()
@@ -7,9 +7,12 @@ use crate::{
     tile::{
         CHUNK_SIZE_SQUARED, Tile, TilePlugin,
         adjacency::TileAdjacency,
-        material::TileMaterial,
+        material::{TileMaterial, TileMoveSpeed},
         position::{TileChunkOffset, TileChunkPosition, TilePosition},
-        storage::{Adjacency, TileChunk, TileKind, TileMap, TileStorage, TileStorageMut},
+        storage::{
+            Adjacency, TileChunk, TileKind, TileMap, TileStorage, TileStorageMut,
+            codec::{decode_chunk, encode_chunk},
+        },
     },
 };
 
@@ -1342,3 +1345,135 @@ fn tile_storage_sync_material_adjacency() {
         Adjacency::NONE
     );
 }
+
+#[test]
+fn chunk_codec_empty() {
+    let chunk = TileChunk::empty(TileChunkPosition::new(Entity::PLACEHOLDER, 0, 0));
+    let materials: Vec<_> = chunk.materials().collect();
+
+    let mut bytes = Vec::new();
+    encode_chunk(&materials, &mut bytes);
+    assert!(bytes.len() <= 16);
+
+    let mut reader = bytes.as_slice();
+    assert_eq!(decode_chunk(&mut reader).unwrap(), materials);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn chunk_codec_round_trip() {
+    let mut materials = vec![TileMaterial::EMPTY; CHUNK_SIZE_SQUARED];
+    for (index, material) in materials.iter_mut().enumerate() {
+        *material = match index % 7 {
+            0 | 1 => TileMaterial::WALL,
+            2 => TileMaterial::DOOR,
+            3 => TileMaterial::SLOW,
+            _ => TileMaterial::EMPTY,
+        };
+    }
+
+    let mut bytes = Vec::new();
+    encode_chunk(&materials, &mut bytes);
+    encode_chunk(&materials, &mut bytes);
+
+    let mut reader = bytes.as_slice();
+    assert_eq!(decode_chunk(&mut reader).unwrap(), materials);
+    assert_eq!(decode_chunk(&mut reader).unwrap(), materials);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn chunk_codec_wide_palette() {
+    let materials: Vec<_> = (0..CHUNK_SIZE_SQUARED)
+        .map(|id| TileMaterial::new(TileKind::Wall, TileMoveSpeed::Medium, id as u16))
+        .collect();
+
+    let mut bytes = Vec::new();
+    encode_chunk(&materials, &mut bytes);
+
+    let mut reader = bytes.as_slice();
+    assert_eq!(decode_chunk(&mut reader).unwrap(), materials);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn chunk_codec_invalid() {
+    let materials = vec![TileMaterial::WALL; CHUNK_SIZE_SQUARED];
+    let mut bytes = Vec::new();
+    encode_chunk(&materials, &mut bytes);
+
+    assert!(decode_chunk(&mut &bytes[..bytes.len() - 1]).is_err());
+    assert!(decode_chunk(&mut &[0u8, 0][..]).is_err());
+    assert!(decode_chunk(&mut &[1u8, 0, 0, 0b0011_0000][..]).is_err());
+    assert!(decode_chunk(&mut &[1u8, 0, 0, 0, 255, 1][..]).is_err());
+}
+
+#[test]
+fn tile_storage_set_chunk() {
+    let mut app = App::new();
+    app.add_plugins(TilePlugin);
+
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    let loaded_layer = app.world_mut().spawn(Layer::default()).id();
+
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            for x in 0..32 {
+                storage.set_material(TilePosition::new(layer, x, 0), TileMaterial::WALL);
+            }
+            storage.set_material(TilePosition::new(layer, 31, 5), TileMaterial::DOOR);
+            storage.set_material(TilePosition::new(layer, 10, 10), TileMaterial::FAST);
+        })
+        .unwrap();
+
+    let bytes = app
+        .world_mut()
+        .run_system_once(move |storage: TileStorage| {
+            let chunk = storage.chunk(TileChunkPosition::new(layer, 0, 0)).unwrap();
+            let materials: Vec<_> = chunk.materials().collect();
+
+            let mut bytes = Vec::new();
+            encode_chunk(&materials, &mut bytes);
+            bytes
+        })
+        .unwrap();
+
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            let materials = decode_chunk(&mut bytes.as_slice()).unwrap();
+            storage.set_chunk(TileChunkPosition::new(loaded_layer, 0, 0), &materials);
+        })
+        .unwrap();
+
+    app.world_mut()
+        .run_system_once(move |storage: TileStorage| {
+            for offset in TileChunkOffset::iter() {
+                let expected = TilePosition::from((TileChunkPosition::new(layer, 0, 0), offset));
+                let loaded =
+                    TilePosition::from((TileChunkPosition::new(loaded_layer, 0, 0), offset));
+
+                assert_eq!(
+                    storage.get(loaded).unwrap().material(),
+                    storage.get(expected).unwrap().material()
+                );
+                assert_eq!(
+                    storage.get_adjacency(loaded),
+                    storage.get_adjacency(expected)
+                );
+            }
+
+            assert_eq!(
+                storage
+                    .chunk_id(TileChunkPosition::new(loaded_layer, 1, 0))
+                    .is_some(),
+                storage
+                    .chunk_id(TileChunkPosition::new(layer, 1, 0))
+                    .is_some()
+            );
+            assert_eq!(
+                storage.get_adjacency(TilePosition::new(loaded_layer, 32, 5)),
+                storage.get_adjacency(TilePosition::new(layer, 32, 5))
+            );
+        })
+        .unwrap();
+}
@@ -2,9 +2,13 @@ use bevy_app::prelude::*;
 use bevy_color::Color;
 use bevy_ecs::prelude::*;
 use bevy_gizmos::prelude::*;
+use bevy_math::prelude::*;
+use wdn_physics::collision::{Collisions, TileCollider};
 use wdn_physics::kinematics::Position;
+use wdn_physics::tile::index::TileIndex;
+use wdn_physics::tile::position::{TileLayerOffset, TilePosition};
 use wdn_world::path::find::PathStep;
-use wdn_world::path::flow::FlowField;
+use wdn_world::path::flow::{CostField, FlowField};
 use wdn_world::path::region::RegionTiles;
 use wdn_world::pawn::Pawn;
 use wdn_world::pawn::path::PawnPath;
@@ -13,10 +17,16 @@ use crate::RenderSystems;
 
 pub struct DevPlugin;
 
+// Tile index occupancy at which the gizmo colour stops changing.
+const TILE_INDEX_FULL: usize = 16;
+
 #[derive(Resource)]
 pub struct DevRenderSettings {
     pub draw_pawn_colliders: bool,
     pub draw_pawn_paths: Option<Entity>,
+    pub draw_contacts: bool,
+    pub draw_tile_colliders: bool,
+    pub draw_tile_index: bool,
 }
 
 pub fn draw_pawn_colliders_enabled(settings: Res<DevRenderSettings>) -> bool {
@@ -63,30 +73,39 @@ pub fn draw_pawn_paths(
     for entry in path.iter() {
         match entry {
             PathStep::RegionCostField {
-                cost_field, region, ..
+                cost_field,
+                region,
+                version,
             } => {
                 let Ok(tiles) = regions.get(*region) else {
                     return;
                 };
+                if tiles.version() != *version {
+                    continue;
+                }
 
-                for (tile_index, tile) in tiles.tiles() {
-                    if tile.position() == target.layer_offset() {
-                        continue;
-                    }
-
-                    if cost_field.contains(tile_index) {
-                        let tile_pos = tile.position();
-                        let dir = cost_field.flow_vector(tile_index, tile).as_vec2();
-                        let color = Color::srgb(0.3, 0.95, 0.35);
-
-                        let center = tile_pos.center_position();
-                        let end = center + dir * 0.4;
-                        gizmos.arrow_2d(center, end, color);
-                    }
+                draw_cost_field(&mut gizmos, tiles, cost_field, target.layer_offset());
+            }
+            PathStep::WaypointCostField {
+                cost_field,
+                region,
+                version,
+                goal,
+            } => {
+                let Ok(tiles) = regions.get(*region) else {
+                    return;
+                };
+                if tiles.version() != *version {
+                    continue;
                 }
+
+                draw_cost_field(&mut gizmos, tiles, cost_field, goal.layer_offset());
             }
             PathStep::DoorFlowField {
                 flow_field, region, ..
+            }
+            | PathStep::StairsFlowField {
+                flow_field, region, ..
             } => {
                 let Ok(flow) = flow_fields.get(*flow_field) else {
                     return;
@@ -101,6 +120,109 @@ pub fn draw_pawn_paths(
                     gizmos.arrow_2d(center, end, Color::srgb(0.6, 0.3, 0.8));
                 }
             }
+            PathStep::Stairs { from, .. } => {
+                gizmos.rect_2d(
+                    from.center_position(),
+                    Vec2::splat(0.8),
+                    Color::srgb(0.6, 0.3, 0.8),
+                );
+            }
+        }
+    }
+}
+
+fn draw_cost_field(
+    gizmos: &mut Gizmos,
+    tiles: &RegionTiles,
+    cost_field: &CostField,
+    goal: TileLayerOffset,
+) {
+    for (tile_index, tile) in tiles.tiles() {
+        if tile.position() == goal {
+            continue;
+        }
+
+        if cost_field.contains(tile_index) {
+            let tile_pos = tile.position();
+            let dir = cost_field.flow_vector(tile_index, tile).as_vec2();
+            let color = Color::srgb(0.3, 0.95, 0.35);
+
+            let center = tile_pos.center_position();
+            let end = center + dir * 0.4;
+            gizmos.arrow_2d(center, end, color);
+        }
+    }
+}
+
+pub fn draw_contacts_enabled(settings: Res<DevRenderSettings>) -> bool {
+    settings.draw_contacts
+}
+
+pub fn draw_contacts(mut gizmos: Gizmos, colliders: Query<&Collisions>) {
+    colliders.iter().for_each(|collisions| {
+        for collision in collisions.active() {
+            let color = if collision.solid {
+                Color::srgb(0.95, 0.3, 0.3)
+            } else {
+                Color::srgb(0.95, 0.85, 0.3)
+            };
+            gizmos.circle_2d(collision.position, 0.03, color);
+            gizmos.arrow_2d(
+                collision.position,
+                collision.position + *collision.normal * 0.25,
+                color,
+            );
+        }
+
+        if let Some(collision) = collisions.next_collision() {
+            let color = Color::srgb(0.3, 0.6, 0.95);
+            gizmos.circle_2d(collision.position, 0.03, color);
+            gizmos.arrow_2d(
+                collision.position,
+                collision.position + *collision.normal * 0.25,
+                color,
+            );
+        }
+    });
+}
+
+pub fn draw_tile_colliders_enabled(settings: Res<DevRenderSettings>) -> bool {
+    settings.draw_tile_colliders
+}
+
+pub fn draw_tile_colliders(mut gizmos: Gizmos, tiles: Query<(&TileCollider, &TilePosition)>) {
+    tiles.iter().for_each(|(collider, position)| {
+        let color = if collider.solid() {
+            Color::srgb(0.95, 0.3, 0.3)
+        } else {
+            Color::srgb(0.3, 0.95, 0.35)
+        };
+        gizmos.rect_2d(position.center_position(), Vec2::splat(0.9), color);
+    });
+}
+
+pub fn draw_tile_index_enabled(settings: Res<DevRenderSettings>) -> bool {
+    settings.draw_tile_index
+}
+
+pub fn draw_tile_index(mut gizmos: Gizmos, index: Res<TileIndex>) {
+    for (position, entry) in index.iter() {
+        let count = entry.objects().len();
+        if count == 0 {
+            continue;
+        }
+
+        // Dots only count up to nine objects, so the colour also shifts from purple to red as the
+        // tile fills up.
+        let fill = (count - 1) as f32 / (TILE_INDEX_FULL - 1) as f32;
+        let fill = fill.min(1.0);
+        let color = Color::srgb(0.6 + 0.35 * fill, 0.3, 0.8 - 0.5 * fill);
+
+        let center = position.center_position();
+        gizmos.rect_2d(center, Vec2::splat(0.96), color);
+        for slot in 0..count.min(9) {
+            let offset = Vec2::new((slot % 3) as f32 - 1.0, (slot / 3) as f32 - 1.0) * 0.12;
+            gizmos.circle_2d(center + Vec2::new(0.0, -0.3) + offset, 0.04, color);
         }
     }
 }
@@ -110,6 +232,9 @@ impl Plugin for DevPlugin {
         app.insert_resource(DevRenderSettings {
             draw_pawn_colliders: true,
             draw_pawn_paths: None,
+            draw_contacts: false,
+            draw_tile_colliders: false,
+            draw_tile_index: false,
         });
 
         app.add_systems(
@@ -117,6 +242,9 @@ impl Plugin for DevPlugin {
             (
                 draw_pawn_colliders.run_if(draw_pawn_colliders_enabled),
                 draw_pawn_paths.run_if(draw_pawn_paths_enabled),
+                draw_contacts.run_if(draw_contacts_enabled),
+                draw_tile_colliders.run_if(draw_tile_colliders_enabled),
+                draw_tile_index.run_if(draw_tile_index_enabled),
             )
                 .in_set(RenderSystems::RenderDev),
         );
@@ -0,0 +1,1199 @@
+use std::time::Duration;
+
+use bevy_ecs::{
+    entity::{EntityHashMap, EntityHashSet},
+    prelude::*,
+    system::RunSystemOnce,
+};
+use bevy_math::prelude::*;
+use wdn_physics::{
+    collision::filter::CollisionGroups,
+    kinematics::{Mass, Position, Velocity},
+    layer::{Layer, LayerStack},
+    tile::{
+        material::TileMaterial,
+        position::{TileChunkPosition, TilePosition},
+        storage::{TileChunk, TileStorageMut},
+    },
+};
+use wdn_tasks::{
+    blueprint::{Blueprint, BuildingMaterials, Stockpile},
+    task::{Task, TaskKind, TaskQueue, TaskStatus, TaskWorker},
+};
+use wdn_world::{
+    combat::{Health, Projectile},
+    command::CommandedAttacks,
+    door::{Door, DoorAccess, DoorSchedule, DoorState},
+    path::cost::{AvoidZone, DangerZones},
+    pawn::{Pawn, PawnProjectile, Staff, action::PawnAction, path::PawnPath},
+};
+
+use crate::format::{SaveReader, SaveWriter};
+
+#[derive(Debug, Clone, Default)]
+pub struct SaveData {
+    pub stacks: u32,
+    pub layers: Vec<LayerData>,
+    pub chunks: Vec<ChunkData>,
+    pub doors: Vec<DoorData>,
+    pub pawns: Vec<PawnData>,
+    pub projectiles: Vec<ProjectileData>,
+    pub danger_zones: Vec<DangerZoneData>,
+    pub tasks: Vec<TaskData>,
+    pub stockpiles: Vec<StockpileData>,
+    pub avoid_zones: Vec<AvoidZoneData>,
+    pub building_materials: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SaveEntities {
+    pub layers: Vec<Entity>,
+    pub pawns: Vec<Entity>,
+    pub tasks: Vec<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerData {
+    pub stack: Option<u32>,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkData {
+    pub layer: u32,
+    pub x: i16,
+    pub y: i16,
+    pub materials: Vec<TileMaterial>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoorData {
+    pub layer: u32,
+    pub position: IVec2,
+    pub state: DoorState,
+    pub access: Option<DoorAccessData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoorAccessData {
+    pub locked: bool,
+    pub staff_only: bool,
+    pub owners: Vec<u32>,
+    pub schedule: Option<DoorSchedule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PawnData {
+    pub layer: u32,
+    pub position: Vec2,
+    pub rotation: Rot2,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+    pub health: u32,
+    pub max_health: u32,
+    pub staff: bool,
+    pub mass: Option<f32>,
+    pub target: Option<(u32, IVec2)>,
+    pub action: PawnAction,
+    // Whether the action came from an attack command, which only lasts a tick.
+    pub commanded: bool,
+    pub task: Option<TaskWorkerData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskWorkerData {
+    pub task: u32,
+    pub carrying: u32,
+    pub unreachable: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileData {
+    pub pawn: u32,
+    pub position: Vec2,
+    pub rotation: Rot2,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+    pub damage: u32,
+    pub elapsed: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DangerZoneData {
+    pub layer: u32,
+    pub position: IVec2,
+    pub remaining: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskData {
+    pub layer: u32,
+    pub position: IVec2,
+    pub kind: TaskKindData,
+    pub priority: i32,
+    pub work: Duration,
+    pub worked: Duration,
+    // Position in the task queue, for tasks waiting on a worker.
+    pub queue: Option<u32>,
+    pub blueprint: Option<BlueprintData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskKindData {
+    Build(TileMaterial),
+    PlaceDoor,
+    Remove,
+    Haul {
+        layer: u32,
+        destination: IVec2,
+        amount: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlueprintData {
+    pub material: TileMaterial,
+    pub cost: u32,
+    pub delivered: u32,
+    pub paid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StockpileData {
+    pub layer: u32,
+    pub position: IVec2,
+    pub amount: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvoidZoneData {
+    pub tiles: Vec<(u32, IVec2)>,
+    pub groups: u32,
+    pub cost: u32,
+}
+
+impl SaveData {
+    pub fn capture(world: &mut World) -> Result<Self> {
+        Ok(SaveData::capture_entities(world)?.0)
+    }
+
+    pub fn capture_entities(world: &mut World) -> Result<(Self, SaveEntities)> {
+        let mut data = SaveData::default();
+        let mut entities = SaveEntities::default();
+
+        let mut stack_ids = EntityHashMap::default();
+        let mut stacks = world.query_filtered::<Entity, With<LayerStack>>();
+        for stack in stacks.iter(world) {
+            stack_ids.insert(stack, data.stacks);
+            data.stacks += 1;
+        }
+
+        let mut layer_ids = EntityHashMap::default();
+        let mut layers = world.query::<(Entity, &Layer, Option<&ChildOf>)>();
+        for (layer_id, layer, parent) in layers.iter(world) {
+            layer_ids.insert(layer_id, data.layers.len() as u32);
+            entities.layers.push(layer_id);
+            data.layers.push(LayerData {
+                stack: parent.and_then(|parent| stack_ids.get(&parent.parent()).copied()),
+                height: layer.height(),
+            });
+        }
+
+        let layer_index = |layer: Entity| -> Result<u32> {
+            layer_ids
+                .get(&layer)
+                .copied()
+                .ok_or_else(|| format!("layer {layer:?} not found").into())
+        };
+
+        let mut chunks = world.query::<&TileChunk>();
+        for chunk in chunks.iter(world) {
+            data.chunks.push(ChunkData {
+                layer: layer_index(chunk.layer())?,
+                x: chunk.position().x(),
+                y: chunk.position().y(),
+                materials: chunk.materials().collect(),
+            });
+        }
+        data.chunks
+            .sort_by_key(|chunk| (chunk.layer, chunk.x, chunk.y));
+
+        let queued: EntityHashMap<u32> = match world.get_resource::<TaskQueue>() {
+            Some(queue) => {
+                let mut queued = EntityHashMap::default();
+                for task in queue.iter() {
+                    let rank = queued.len() as u32;
+                    queued.entry(task).or_insert(rank);
+                }
+                queued
+            }
+            None => EntityHashMap::default(),
+        };
+
+        let mut task_ids = EntityHashMap::default();
+        let mut tasks = world.query::<(Entity, &Task, &TaskStatus, Option<&Blueprint>)>();
+        // Tasks are saved in the order they were first queued, which restoring them keeps.
+        let mut tasks: Vec<_> = tasks.iter(world).collect();
+        tasks.sort_unstable_by_key(|(_, task, ..)| task.sequence());
+        for (task_id, task, status, blueprint) in tasks {
+            let kind = match task.kind() {
+                TaskKind::Build(material) => TaskKindData::Build(material),
+                TaskKind::PlaceDoor => TaskKindData::PlaceDoor,
+                TaskKind::Remove => TaskKindData::Remove,
+                TaskKind::Haul {
+                    destination,
+                    amount,
+                } => TaskKindData::Haul {
+                    layer: layer_index(destination.layer())?,
+                    destination: destination.position(),
+                    amount,
+                },
+            };
+            let queue = match status {
+                TaskStatus::Queued => queued.get(&task_id).copied(),
+                _ => None,
+            };
+
+            task_ids.insert(task_id, data.tasks.len() as u32);
+            entities.tasks.push(task_id);
+            data.tasks.push(TaskData {
+                layer: layer_index(task.position().layer())?,
+                position: task.position().position(),
+                kind,
+                priority: task.priority(),
+                work: task.work(),
+                worked: task.worked(),
+                queue,
+                blueprint: blueprint.map(|blueprint| BlueprintData {
+                    material: blueprint.material(),
+                    cost: blueprint.cost(),
+                    delivered: blueprint.delivered(),
+                    paid: blueprint.is_paid(),
+                }),
+            });
+        }
+
+        let mut pawn_ids = EntityHashMap::default();
+        let mut pawns = world.query_filtered::<(
+            Entity,
+            &ChildOf,
+            &Position,
+            &Velocity,
+            &Health,
+            Has<Staff>,
+            Option<&Mass>,
+            &PawnPath,
+            &PawnAction,
+            Option<&TaskWorker>,
+        ), With<Pawn>>();
+        let attacks = world.get_resource::<CommandedAttacks>();
+        for (pawn_id, parent, position, velocity, health, staff, mass, path, &action, worker) in
+            pawns.iter(world)
+        {
+            let target = match path.target() {
+                Some(target) => Some((layer_index(target.layer())?, target.position())),
+                None => None,
+            };
+            let task = worker.and_then(|worker| {
+                Some(TaskWorkerData {
+                    task: *task_ids.get(&worker.task())?,
+                    carrying: worker.carrying(),
+                    unreachable: worker.unreachable(),
+                })
+            });
+
+            pawn_ids.insert(pawn_id, data.pawns.len() as u32);
+            entities.pawns.push(pawn_id);
+            data.pawns.push(PawnData {
+                layer: layer_index(parent.parent())?,
+                position: position.position(),
+                rotation: position.rotation(),
+                linear_velocity: velocity.linear(),
+                angular_velocity: velocity.angular(),
+                health: health.current,
+                max_health: health.max,
+                staff,
+                mass: mass.map(Mass::value),
+                target,
+                action,
+                commanded: attacks.is_some_and(|attacks| attacks.contains(pawn_id)),
+                task,
+            });
+        }
+
+        let mut projectiles = world
+            .query_filtered::<(&ChildOf, &Projectile, &Position, &Velocity), With<PawnProjectile>>(
+            );
+        for (parent, projectile, position, velocity) in projectiles.iter(world) {
+            let Some(&pawn) = pawn_ids.get(&parent.parent()) else {
+                continue;
+            };
+
+            data.projectiles.push(ProjectileData {
+                pawn,
+                position: position.position(),
+                rotation: position.rotation(),
+                linear_velocity: velocity.linear(),
+                angular_velocity: velocity.angular(),
+                damage: projectile.damage,
+                elapsed: projectile.timer.elapsed(),
+                duration: projectile.timer.duration(),
+            });
+        }
+
+        let mut doors = world.query::<(&Door, &TilePosition, Option<&DoorAccess>)>();
+        for (door, position, access) in doors.iter(world) {
+            data.doors.push(DoorData {
+                layer: layer_index(position.layer())?,
+                position: position.position(),
+                state: door.state(),
+                access: access.map(|access| {
+                    let mut owners: Vec<u32> = access
+                        .owners()
+                        .iter()
+                        .filter_map(|owner| pawn_ids.get(owner).copied())
+                        .collect();
+                    owners.sort_unstable();
+                    DoorAccessData {
+                        locked: access.is_locked(),
+                        staff_only: access.is_staff_only(),
+                        owners,
+                        schedule: access.schedule(),
+                    }
+                }),
+            });
+        }
+
+        if let Some(zones) = world.get_resource::<DangerZones>() {
+            for zone in zones.iter() {
+                data.danger_zones.push(DangerZoneData {
+                    layer: layer_index(zone.position().layer())?,
+                    position: zone.position().position(),
+                    remaining: zone.remaining(),
+                });
+            }
+        }
+
+        let mut stockpiles = world.query::<(&Stockpile, &TilePosition)>();
+        for (stockpile, position) in stockpiles.iter(world) {
+            data.stockpiles.push(StockpileData {
+                layer: layer_index(position.layer())?,
+                position: position.position(),
+                amount: stockpile.amount,
+            });
+        }
+
+        let mut avoid_zones = world.query::<&AvoidZone>();
+        for zone in avoid_zones.iter(world) {
+            let mut tiles = zone
+                .tiles()
+                .iter()
+                .map(|tile| Ok((layer_index(tile.layer())?, tile.position())))
+                .collect::<Result<Vec<_>>>()?;
+            tiles.sort_unstable_by_key(|&(layer, position)| (layer, position.to_array()));
+            data.avoid_zones.push(AvoidZoneData {
+                tiles,
+                groups: zone.groups().bits(),
+                cost: zone.tile_cost(),
+            });
+        }
+
+        data.building_materials = world
+            .get_resource::<BuildingMaterials>()
+            .map(|materials| materials.amount);
+
+        Ok((data, entities))
+    }
+
+    pub fn restore(self, world: &mut World) -> Result {
+        self.restore_entities(world)?;
+        Ok(())
+    }
+
+    pub fn restore_entities(self, world: &mut World) -> Result<SaveEntities> {
+        let stacks: Vec<Entity> = (0..self.stacks)
+            .map(|_| world.spawn(LayerStack::default()).id())
+            .collect();
+
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for layer in &self.layers {
+            let mut entity = world.spawn(Layer::new(layer.height));
+            if let Some(stack) = layer.stack {
+                let stack = *stacks
+                    .get(stack as usize)
+                    .ok_or("invalid layer stack index")?;
+                entity.insert(ChildOf(stack));
+            }
+            layers.push(entity.id());
+        }
+
+        let layer = |index: u32| -> Result<Entity> {
+            layers
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| "invalid layer index".into())
+        };
+
+        let mut chunks = Vec::with_capacity(self.chunks.len());
+        for chunk in self.chunks {
+            chunks.push((
+                TileChunkPosition::new(layer(chunk.layer)?, chunk.x, chunk.y),
+                chunk.materials,
+            ));
+        }
+
+        world
+            .run_system_once(move |mut storage: TileStorageMut| {
+                for (chunk_position, materials) in &chunks {
+                    storage.set_chunk(*chunk_position, materials);
+                }
+            })
+            .map_err(|err| err.to_string())?;
+
+        let mut door_access = Vec::new();
+        for door in self.doors {
+            let layer = layer(door.layer)?;
+            let door_id = world
+                .spawn((
+                    Door::from_state(door.state),
+                    TilePosition::from_vec(layer, door.position),
+                    ChildOf(layer),
+                ))
+                .id();
+            if let Some(access) = door.access {
+                door_access.push((door_id, access));
+            }
+        }
+
+        let mut tasks = Vec::with_capacity(self.tasks.len());
+        let mut queued = EntityHashSet::default();
+        for data in &self.tasks {
+            let position = TilePosition::from_vec(layer(data.layer)?, data.position);
+            let kind = match data.kind {
+                TaskKindData::Build(material) => TaskKind::Build(material),
+                TaskKindData::PlaceDoor => TaskKind::PlaceDoor,
+                TaskKindData::Remove => TaskKind::Remove,
+                TaskKindData::Haul {
+                    layer: destination_layer,
+                    destination,
+                    amount,
+                } => TaskKind::Haul {
+                    destination: TilePosition::from_vec(layer(destination_layer)?, destination),
+                    amount,
+                },
+            };
+
+            let mut entity = world.spawn((
+                Task::new(kind, position)
+                    .with_priority(data.priority)
+                    .with_work(data.work)
+                    .with_worked(data.worked),
+                position,
+            ));
+            if let Some(blueprint) = data.blueprint {
+                entity.insert(
+                    Blueprint::new(blueprint.material, blueprint.cost)
+                        .with_delivered(blueprint.delivered)
+                        .with_paid(blueprint.paid),
+                );
+            }
+            if data.queue.is_some() {
+                queued.insert(entity.id());
+            }
+            tasks.push(entity.id());
+        }
+
+        // Spawning tasks queues all of them in their saved order, so only the ones that were
+        // waiting on a worker are kept.
+        if let Some(mut queue) = world.get_resource_mut::<TaskQueue>() {
+            queue.retain(|task| queued.contains(&task));
+        }
+
+        let mut pawns = Vec::with_capacity(self.pawns.len());
+        let mut attacks = CommandedAttacks::default();
+        for pawn in self.pawns {
+            let mut path = PawnPath::default();
+            if let Some((target_layer, target_position)) = pawn.target {
+                path.set_target(TilePosition::from_vec(
+                    layer(target_layer)?,
+                    target_position,
+                ));
+            }
+
+            let mut entity = world.spawn((
+                Pawn::default(),
+                ChildOf(layer(pawn.layer)?),
+                Position::new(pawn.position, pawn.rotation),
+                Velocity::new(pawn.linear_velocity).with_angular(pawn.angular_velocity),
+                Health {
+                    current: pawn.health,
+                    max: pawn.max_health,
+                },
+                path,
+                pawn.action,
+            ));
+            if pawn.staff {
+                entity.insert(Staff);
+            }
+            if let Some(mass) = pawn.mass {
+                entity.insert(Mass::new(mass));
+            }
+            let pawn_id = entity.id();
+            if let Some(worker) = pawn.task {
+                let task = *tasks
+                    .get(worker.task as usize)
+                    .ok_or("invalid task index")?;
+                world.entity_mut(pawn_id).insert(
+                    TaskWorker::new(task, worker.carrying).with_unreachable(worker.unreachable),
+                );
+                world
+                    .entity_mut(task)
+                    .insert(TaskStatus::Assigned { worker: pawn_id });
+            }
+            if pawn.commanded {
+                attacks.insert(pawn_id);
+            }
+            pawns.push(pawn_id);
+        }
+        world.insert_resource(attacks);
+
+        for data in self.projectiles {
+            let pawn = *pawns.get(data.pawn as usize).ok_or("invalid pawn index")?;
+            // Pawn filters aren't saved, since staff and prisoners always use their own.
+            let filter = if world.entity(pawn).contains::<Staff>() {
+                Staff::FILTER
+            } else {
+                Pawn::FILTER
+            };
+
+            let mut entity = world.spawn(PawnProjectile::bundle(
+                pawn,
+                &filter,
+                data.position,
+                data.linear_velocity,
+            ));
+            entity.insert((
+                Position::new(data.position, data.rotation),
+                Velocity::new(data.linear_velocity).with_angular(data.angular_velocity),
+            ));
+            let mut projectile = entity
+                .get_mut::<Projectile>()
+                .ok_or("projectile not spawned")?;
+            projectile.damage = data.damage;
+            projectile.timer.set_duration(data.duration);
+            projectile.timer.set_elapsed(data.elapsed);
+        }
+
+        for (door, data) in door_access {
+            let mut access = DoorAccess::default();
+            access.set_locked(data.locked);
+            access.set_staff_only(data.staff_only);
+            access.set_schedule(data.schedule);
+            for owner in data.owners {
+                access.add_owner(*pawns.get(owner as usize).ok_or("invalid pawn index")?);
+            }
+            world.entity_mut(door).insert(access);
+        }
+
+        for stockpile in self.stockpiles {
+            world.spawn((
+                Stockpile::new(stockpile.amount),
+                TilePosition::from_vec(layer(stockpile.layer)?, stockpile.position),
+            ));
+        }
+
+        for zone in self.avoid_zones {
+            let tiles = zone
+                .tiles
+                .into_iter()
+                .map(|(index, position)| Ok(TilePosition::from_vec(layer(index)?, position)))
+                .collect::<Result<Vec<_>>>()?;
+            world.spawn(AvoidZone::new(
+                tiles,
+                CollisionGroups::from_bits_retain(zone.groups),
+                zone.cost,
+            ));
+        }
+
+        match self.building_materials {
+            Some(amount) => world.insert_resource(BuildingMaterials { amount }),
+            None => {
+                world.remove_resource::<BuildingMaterials>();
+            }
+        }
+
+        let mut zones = DangerZones::default();
+        for zone in self.danger_zones {
+            zones.insert_for(
+                TilePosition::from_vec(layer(zone.layer)?, zone.position),
+                zone.remaining,
+            );
+        }
+        world.insert_resource(zones);
+
+        Ok(SaveEntities {
+            layers,
+            pawns,
+            tasks,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = SaveWriter::new();
+
+        writer.write_u32(self.stacks);
+
+        writer.write_len(self.layers.len());
+        for layer in &self.layers {
+            match layer.stack {
+                Some(stack) => {
+                    writer.write_bool(true);
+                    writer.write_u32(stack);
+                }
+                None => writer.write_bool(false),
+            }
+            writer.write_i32(layer.height);
+        }
+
+        writer.write_len(self.chunks.len());
+        for chunk in &self.chunks {
+            writer.write_u32(chunk.layer);
+            writer.write_i16(chunk.x);
+            writer.write_i16(chunk.y);
+            writer.write_chunk(&chunk.materials);
+        }
+
+        writer.write_len(self.doors.len());
+        for door in &self.doors {
+            writer.write_u32(door.layer);
+            writer.write_i32(door.position.x);
+            writer.write_i32(door.position.y);
+            match door.state {
+                DoorState::Closed => writer.write_u8(0),
+                DoorState::Opening { position } => {
+                    writer.write_u8(1);
+                    writer.write_f32(position);
+                }
+                DoorState::Open { timer } => {
+                    writer.write_u8(2);
+                    writer.write_duration(timer);
+                }
+                DoorState::Closing { position } => {
+                    writer.write_u8(3);
+                    writer.write_f32(position);
+                }
+            }
+            match &door.access {
+                Some(access) => {
+                    writer.write_bool(true);
+                    writer.write_bool(access.locked);
+                    writer.write_bool(access.staff_only);
+                    writer.write_len(access.owners.len());
+                    for &owner in &access.owners {
+                        writer.write_u32(owner);
+                    }
+                    match access.schedule {
+                        Some(schedule) => {
+                            writer.write_bool(true);
+                            writer.write_duration(schedule.period());
+                            writer.write_duration(schedule.start());
+                            writer.write_duration(schedule.end());
+                        }
+                        None => writer.write_bool(false),
+                    }
+                }
+                None => writer.write_bool(false),
+            }
+        }
+
+        writer.write_len(self.pawns.len());
+        for pawn in &self.pawns {
+            writer.write_u32(pawn.layer);
+            writer.write_vec2(pawn.position);
+            writer.write_f32(pawn.rotation.cos);
+            writer.write_f32(pawn.rotation.sin);
+            writer.write_vec2(pawn.linear_velocity);
+            writer.write_f32(pawn.angular_velocity);
+            writer.write_u32(pawn.health);
+            writer.write_u32(pawn.max_health);
+            writer.write_bool(pawn.staff);
+            match pawn.mass {
+                Some(mass) => {
+                    writer.write_bool(true);
+                    writer.write_f32(mass);
+                }
+                None => writer.write_bool(false),
+            }
+            match pawn.target {
+                Some((layer, position)) => {
+                    writer.write_bool(true);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                }
+                None => writer.write_bool(false),
+            }
+            writer.write_u8(match pawn.action {
+                PawnAction::Stand => 0,
+                PawnAction::Walk => 1,
+                PawnAction::TurnLeft => 2,
+                PawnAction::TurnRight => 3,
+                PawnAction::SteerLeft => 4,
+                PawnAction::SteerRight => 5,
+                PawnAction::AttackLeft => 6,
+                PawnAction::AttackRight => 7,
+            });
+            writer.write_bool(pawn.commanded);
+            match pawn.task {
+                Some(worker) => {
+                    writer.write_bool(true);
+                    writer.write_u32(worker.task);
+                    writer.write_u32(worker.carrying);
+                    writer.write_u8(worker.unreachable);
+                }
+                None => writer.write_bool(false),
+            }
+        }
+
+        writer.write_len(self.projectiles.len());
+        for projectile in &self.projectiles {
+            writer.write_u32(projectile.pawn);
+            writer.write_vec2(projectile.position);
+            writer.write_f32(projectile.rotation.cos);
+            writer.write_f32(projectile.rotation.sin);
+            writer.write_vec2(projectile.linear_velocity);
+            writer.write_f32(projectile.angular_velocity);
+            writer.write_u32(projectile.damage);
+            writer.write_duration(projectile.elapsed);
+            writer.write_duration(projectile.duration);
+        }
+
+        writer.write_len(self.danger_zones.len());
+        for zone in &self.danger_zones {
+            writer.write_u32(zone.layer);
+            writer.write_i32(zone.position.x);
+            writer.write_i32(zone.position.y);
+            writer.write_duration(zone.remaining);
+        }
+
+        writer.write_len(self.tasks.len());
+        for task in &self.tasks {
+            writer.write_u32(task.layer);
+            writer.write_i32(task.position.x);
+            writer.write_i32(task.position.y);
+            match task.kind {
+                TaskKindData::Build(material) => {
+                    writer.write_u8(0);
+                    writer.write_u16(material.bits());
+                }
+                TaskKindData::PlaceDoor => writer.write_u8(1),
+                TaskKindData::Remove => writer.write_u8(2),
+                TaskKindData::Haul {
+                    layer,
+                    destination,
+                    amount,
+                } => {
+                    writer.write_u8(3);
+                    writer.write_u32(layer);
+                    writer.write_i32(destination.x);
+                    writer.write_i32(destination.y);
+                    writer.write_u32(amount);
+                }
+            }
+            writer.write_i32(task.priority);
+            writer.write_duration(task.work);
+            writer.write_duration(task.worked);
+            match task.queue {
+                Some(rank) => {
+                    writer.write_bool(true);
+                    writer.write_u32(rank);
+                }
+                None => writer.write_bool(false),
+            }
+            match task.blueprint {
+                Some(blueprint) => {
+                    writer.write_bool(true);
+                    writer.write_u16(blueprint.material.bits());
+                    writer.write_u32(blueprint.cost);
+                    writer.write_u32(blueprint.delivered);
+                    writer.write_bool(blueprint.paid);
+                }
+                None => writer.write_bool(false),
+            }
+        }
+
+        writer.write_len(self.stockpiles.len());
+        for stockpile in &self.stockpiles {
+            writer.write_u32(stockpile.layer);
+            writer.write_i32(stockpile.position.x);
+            writer.write_i32(stockpile.position.y);
+            writer.write_u32(stockpile.amount);
+        }
+
+        writer.write_len(self.avoid_zones.len());
+        for zone in &self.avoid_zones {
+            writer.write_len(zone.tiles.len());
+            for &(layer, position) in &zone.tiles {
+                writer.write_u32(layer);
+                writer.write_i32(position.x);
+                writer.write_i32(position.y);
+            }
+            writer.write_u32(zone.groups);
+            writer.write_u32(zone.cost);
+        }
+
+        match self.building_materials {
+            Some(amount) => {
+                writer.write_bool(true);
+                writer.write_u32(amount);
+            }
+            None => writer.write_bool(false),
+        }
+
+        writer.into_bytes()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SaveReader::new(bytes)?;
+        let mut data = SaveData {
+            stacks: reader.read_u32()?,
+            ..Default::default()
+        };
+
+        let layer_count = reader.read_len()?;
+        for _ in 0..layer_count {
+            let stack = if reader.read_bool()? {
+                Some(reader.read_u32()?)
+            } else {
+                None
+            };
+            data.layers.push(LayerData {
+                stack,
+                height: reader.read_i32()?,
+            });
+        }
+
+        let chunk_count = reader.read_len()?;
+        for _ in 0..chunk_count {
+            let layer = reader.read_u32()?;
+            let x = reader.read_i16()?;
+            let y = reader.read_i16()?;
+
+            let materials = reader.read_chunk()?;
+
+            data.chunks.push(ChunkData {
+                layer,
+                x,
+                y,
+                materials,
+            });
+        }
+
+        let door_count = reader.read_len()?;
+        for _ in 0..door_count {
+            let layer = reader.read_u32()?;
+            let position = IVec2::new(reader.read_i32()?, reader.read_i32()?);
+            let state = match reader.read_u8()? {
+                0 => DoorState::Closed,
+                1 => DoorState::Opening {
+                    position: reader.read_f32()?,
+                },
+                2 => DoorState::Open {
+                    timer: reader.read_duration()?,
+                },
+                3 => DoorState::Closing {
+                    position: reader.read_f32()?,
+                },
+                tag => return Err(format!("invalid door state {tag}").into()),
+            };
+
+            let access = if reader.read_bool()? {
+                let locked = reader.read_bool()?;
+                let staff_only = reader.read_bool()?;
+                let owner_count = reader.read_len()?;
+                let mut owners = Vec::new();
+                for _ in 0..owner_count {
+                    owners.push(reader.read_u32()?);
+                }
+                let schedule = if reader.read_bool()? {
+                    let period = reader.read_duration()?;
+                    if period.is_zero() {
+                        return Err("invalid door schedule period".into());
+                    }
+                    Some(DoorSchedule::new(
+                        period,
+                        reader.read_duration()?,
+                        reader.read_duration()?,
+                    ))
+                } else {
+                    None
+                };
+
+                Some(DoorAccessData {
+                    locked,
+                    staff_only,
+                    owners,
+                    schedule,
+                })
+            } else {
+                None
+            };
+
+            data.doors.push(DoorData {
+                layer,
+                position,
+                state,
+                access,
+            });
+        }
+
+        let pawn_count = reader.read_len()?;
+        for _ in 0..pawn_count {
+            let layer = reader.read_u32()?;
+            let position = reader.read_vec2()?;
+            let cos = reader.read_f32()?;
+            let sin = reader.read_f32()?;
+            let linear_velocity = reader.read_vec2()?;
+            let angular_velocity = reader.read_f32()?;
+            let health = reader.read_u32()?;
+            let max_health = reader.read_u32()?;
+            let staff = reader.read_bool()?;
+            let mass = if reader.read_bool()? {
+                Some(reader.read_f32()?)
+            } else {
+                None
+            };
+            let target = if reader.read_bool()? {
+                Some((
+                    reader.read_u32()?,
+                    IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                ))
+            } else {
+                None
+            };
+            let action = match reader.read_u8()? {
+                0 => PawnAction::Stand,
+                1 => PawnAction::Walk,
+                2 => PawnAction::TurnLeft,
+                3 => PawnAction::TurnRight,
+                4 => PawnAction::SteerLeft,
+                5 => PawnAction::SteerRight,
+                6 => PawnAction::AttackLeft,
+                7 => PawnAction::AttackRight,
+                tag => return Err(format!("invalid pawn action {tag}").into()),
+            };
+            let commanded = reader.read_bool()?;
+            let task = if reader.read_bool()? {
+                Some(TaskWorkerData {
+                    task: reader.read_u32()?,
+                    carrying: reader.read_u32()?,
+                    unreachable: reader.read_u8()?,
+                })
+            } else {
+                None
+            };
+
+            data.pawns.push(PawnData {
+                layer,
+                position,
+                rotation: Rot2::from_sin_cos(sin, cos),
+                linear_velocity,
+                angular_velocity,
+                health,
+                max_health,
+                staff,
+                mass,
+                target,
+                action,
+                commanded,
+                task,
+            });
+        }
+
+        let projectile_count = reader.read_len()?;
+        for _ in 0..projectile_count {
+            let pawn = reader.read_u32()?;
+            let position = reader.read_vec2()?;
+            let cos = reader.read_f32()?;
+            let sin = reader.read_f32()?;
+            data.projectiles.push(ProjectileData {
+                pawn,
+                position,
+                rotation: Rot2::from_sin_cos(sin, cos),
+                linear_velocity: reader.read_vec2()?,
+                angular_velocity: reader.read_f32()?,
+                damage: reader.read_u32()?,
+                elapsed: reader.read_duration()?,
+                duration: reader.read_duration()?,
+            });
+        }
+
+        let zone_count = reader.read_len()?;
+        for _ in 0..zone_count {
+            data.danger_zones.push(DangerZoneData {
+                layer: reader.read_u32()?,
+                position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                remaining: reader.read_duration()?,
+            });
+        }
+
+        let task_count = reader.read_len()?;
+        for _ in 0..task_count {
+            let layer = reader.read_u32()?;
+            let position = IVec2::new(reader.read_i32()?, reader.read_i32()?);
+            let kind = match reader.read_u8()? {
+                0 => TaskKindData::Build(read_material(&mut reader)?),
+                1 => TaskKindData::PlaceDoor,
+                2 => TaskKindData::Remove,
+                3 => TaskKindData::Haul {
+                    layer: reader.read_u32()?,
+                    destination: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                    amount: reader.read_u32()?,
+                },
+                tag => return Err(format!("invalid task kind {tag}").into()),
+            };
+            let priority = reader.read_i32()?;
+            let work = reader.read_duration()?;
+            let worked = reader.read_duration()?;
+            let queue = if reader.read_bool()? {
+                Some(reader.read_u32()?)
+            } else {
+                None
+            };
+            let blueprint = if reader.read_bool()? {
+                Some(BlueprintData {
+                    material: read_material(&mut reader)?,
+                    cost: reader.read_u32()?,
+                    delivered: reader.read_u32()?,
+                    paid: reader.read_bool()?,
+                })
+            } else {
+                None
+            };
+
+            data.tasks.push(TaskData {
+                layer,
+                position,
+                kind,
+                priority,
+                work,
+                worked,
+                queue,
+                blueprint,
+            });
+        }
+
+        let stockpile_count = reader.read_len()?;
+        for _ in 0..stockpile_count {
+            data.stockpiles.push(StockpileData {
+                layer: reader.read_u32()?,
+                position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                amount: reader.read_u32()?,
+            });
+        }
+
+        let avoid_zone_count = reader.read_len()?;
+        for _ in 0..avoid_zone_count {
+            let tile_count = reader.read_len()?;
+            let mut tiles = Vec::new();
+            for _ in 0..tile_count {
+                tiles.push((
+                    reader.read_u32()?,
+                    IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                ));
+            }
+
+            data.avoid_zones.push(AvoidZoneData {
+                tiles,
+                groups: reader.read_u32()?,
+                cost: reader.read_u32()?,
+            });
+        }
+
+        data.building_materials = if reader.read_bool()? {
+            Some(reader.read_u32()?)
+        } else {
+            None
+        };
+
+        if !reader.is_empty() {
+            return Err("unexpected trailing data in save file".into());
+        }
+
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// Checks that every index refers to something in the save and every mass is usable, so
+    /// restoring can't fail halfway through after the old world has been cleared.
+    pub fn validate(&self) -> Result {
+        let layer = |index: u32| -> Result {
+            if index as usize >= self.layers.len() {
+                return Err("invalid layer index".into());
+            }
+            Ok(())
+        };
+        let pawn = |index: u32| -> Result {
+            if index as usize >= self.pawns.len() {
+                return Err("invalid pawn index".into());
+            }
+            Ok(())
+        };
+
+        for data in &self.layers {
+            if data.stack.is_some_and(|stack| stack >= self.stacks) {
+                return Err("invalid layer stack index".into());
+            }
+        }
+        for chunk in &self.chunks {
+            layer(chunk.layer)?;
+        }
+        for door in &self.doors {
+            layer(door.layer)?;
+            if let Some(access) = &door.access {
+                access.owners.iter().try_for_each(|&owner| pawn(owner))?;
+            }
+        }
+        for data in &self.pawns {
+            layer(data.layer)?;
+            if data
+                .mass
+                .is_some_and(|mass| !(mass > 0.0 && mass.is_finite()))
+            {
+                return Err("invalid pawn mass".into());
+            }
+            if let Some((target, _)) = data.target {
+                layer(target)?;
+            }
+            if let Some(worker) = data.task
+                && worker.task as usize >= self.tasks.len()
+            {
+                return Err("invalid task index".into());
+            }
+        }
+        for projectile in &self.projectiles {
+            pawn(projectile.pawn)?;
+        }
+        for zone in &self.danger_zones {
+            layer(zone.layer)?;
+        }
+        for task in &self.tasks {
+            layer(task.layer)?;
+            if let TaskKindData::Haul { layer: index, .. } = task.kind {
+                layer(index)?;
+            }
+        }
+        for stockpile in &self.stockpiles {
+            layer(stockpile.layer)?;
+        }
+        for zone in &self.avoid_zones {
+            zone.tiles.iter().try_for_each(|&(index, _)| layer(index))?;
+        }
+        Ok(())
+    }
+}
+
+fn read_material(reader: &mut SaveReader) -> Result<TileMaterial> {
+    TileMaterial::try_from_bits(reader.read_u16()?).ok_or_else(|| "invalid tile material".into())
+}
@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use wdn_physics::tile::{
+    material::TileMaterial,
+    storage::codec::{decode_chunk, encode_chunk},
+};
+
+pub const SAVE_MAGIC: [u8; 4] = *b"WDNS";
+pub const SAVE_VERSION: u32 = 6;
+
+#[derive(Default)]
+pub struct SaveWriter {
+    bytes: Vec<u8>,
+}
+
+pub struct SaveReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl SaveWriter {
+    pub fn new() -> Self {
+        SaveWriter::with_header(SAVE_MAGIC, SAVE_VERSION)
+    }
+
+    pub fn with_header(magic: [u8; 4], version: u32) -> Self {
+        let mut writer = SaveWriter::default();
+        writer.write_bytes(&magic);
+        writer.write_u32(version);
+        writer
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_duration(&mut self, value: Duration) {
+        self.write_u64(value.as_secs());
+        self.write_u32(value.subsec_nanos());
+    }
+
+    pub fn write_vec2(&mut self, value: Vec2) {
+        self.write_f32(value.x);
+        self.write_f32(value.y);
+    }
+
+    pub fn write_len(&mut self, len: usize) {
+        self.write_u32(len as u32);
+    }
+
+    pub fn write_chunk(&mut self, materials: &[TileMaterial]) {
+        encode_chunk(materials, &mut self.bytes);
+    }
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        SaveReader::with_header(bytes, SAVE_MAGIC, SAVE_VERSION)
+    }
+
+    pub fn with_header(bytes: &'a [u8], magic: [u8; 4], version: u32) -> Result<Self> {
+        let mut reader = SaveReader { bytes };
+        if reader.read_array::<4>()? != magic {
+            return Err("not a save file".into());
+        }
+
+        let found = reader.read_u32()?;
+        if found != version {
+            return Err(format!("unsupported save version {found}").into());
+        }
+
+        Ok(reader)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err("unexpected end of save file".into());
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(format!("invalid bool value {value}").into()),
+        }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(u8::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_duration(&mut self) -> Result<Duration> {
+        let secs = self.read_u64()?;
+        let nanos = self.read_u32()?;
+        if nanos >= 1_000_000_000 {
+            return Err(format!("invalid duration nanos {nanos}").into());
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+
+    pub fn read_vec2(&mut self) -> Result<Vec2> {
+        Ok(Vec2::new(self.read_f32()?, self.read_f32()?))
+    }
+
+    pub fn read_len(&mut self) -> Result<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    pub fn read_chunk(&mut self) -> Result<Vec<TileMaterial>> {
+        decode_chunk(&mut self.bytes)
+    }
+}
@@ -1,7 +1,151 @@
+pub mod data;
+pub mod format;
+pub mod replay;
+#[cfg(test)]
+mod tests;
+
+use std::{fs, path::Path, path::PathBuf};
+
 use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use wdn_physics::layer::{Layer, LayerStack};
+use wdn_tasks::{blueprint::Stockpile, task::Task};
+use wdn_world::{WorldSystems, door::Door, path::cost::AvoidZone, pawn::Pawn, trigger::Trigger};
+
+use crate::{
+    data::SaveData,
+    replay::{
+        load_replay, play_replay_commands, record_player_commands, save_replay, start_recording,
+    },
+};
 
 pub struct SavePlugin;
 
+#[derive(Message, Debug, Clone)]
+pub struct SaveWorld {
+    pub path: PathBuf,
+}
+
+#[derive(Message, Debug, Clone)]
+pub struct LoadWorld {
+    pub path: PathBuf,
+}
+
+#[derive(Message, Debug, Clone)]
+pub struct StartRecording;
+
+#[derive(Message, Debug, Clone)]
+pub struct SaveReplay {
+    pub path: PathBuf,
+}
+
+#[derive(Message, Debug, Clone)]
+pub struct LoadReplay {
+    pub path: PathBuf,
+}
+
 impl Plugin for SavePlugin {
-    fn build(&self, _: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.add_message::<SaveWorld>();
+        app.add_message::<LoadWorld>();
+        app.add_message::<StartRecording>();
+        app.add_message::<SaveReplay>();
+        app.add_message::<LoadReplay>();
+
+        app.add_systems(Update, handle_save_messages);
+        app.add_systems(
+            FixedUpdate,
+            (play_replay_commands, record_player_commands)
+                .chain()
+                .before(WorldSystems::ApplyCommands),
+        );
+    }
+}
+
+pub fn save_world(world: &mut World, path: impl AsRef<Path>) -> Result {
+    let data = SaveData::capture(world)?;
+    fs::write(path, data.encode())?;
+    Ok(())
+}
+
+pub fn load_world(world: &mut World, path: impl AsRef<Path>) -> Result {
+    let data = SaveData::decode(&fs::read(path)?)?;
+    clear_world(world);
+    data.restore(world)
+}
+
+// Triggers are set up by whatever placed them rather than saved, so loading drops them too.
+pub(crate) fn clear_world(world: &mut World) {
+    let mut existing = world.query_filtered::<Entity, Or<(
+        With<LayerStack>,
+        With<Layer>,
+        With<Door>,
+        With<Pawn>,
+        With<Task>,
+        With<Stockpile>,
+        With<AvoidZone>,
+        With<Trigger>,
+    )>>();
+    let mut existing: Vec<Entity> = existing.iter(world).collect();
+    // Removing a task drops whatever its worker carries, so workers are cleared first.
+    existing.sort_by_key(|&entity| world.get::<Task>(entity).is_some());
+    for entity in existing {
+        if let Ok(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+    }
+}
+
+pub fn handle_save_messages(
+    mut commands: Commands,
+    mut save_messages: MessageReader<SaveWorld>,
+    mut load_messages: MessageReader<LoadWorld>,
+    mut record_messages: MessageReader<StartRecording>,
+    mut save_replay_messages: MessageReader<SaveReplay>,
+    mut load_replay_messages: MessageReader<LoadReplay>,
+) {
+    for message in save_messages.read() {
+        let path = message.path.clone();
+        commands.queue(move |world: &mut World| {
+            if let Err(error) = save_world(world, &path) {
+                error!("failed to save world to {}: {error}", path.display());
+            }
+        });
+    }
+
+    for message in load_messages.read() {
+        let path = message.path.clone();
+        commands.queue(move |world: &mut World| {
+            if let Err(error) = load_world(world, &path) {
+                error!("failed to load world from {}: {error}", path.display());
+            }
+        });
+    }
+
+    for _ in record_messages.read() {
+        commands.queue(|world: &mut World| {
+            if let Err(error) = start_recording(world) {
+                error!("failed to start recording replay: {error}");
+            }
+        });
+    }
+
+    for message in save_replay_messages.read() {
+        let path = message.path.clone();
+        commands.queue(move |world: &mut World| {
+            if let Err(error) = save_replay(world, &path) {
+                error!("failed to save replay to {}: {error}", path.display());
+            }
+        });
+    }
+
+    for message in load_replay_messages.read() {
+        let path = message.path.clone();
+        commands.queue(move |world: &mut World| {
+            if let Err(error) = load_replay(world, &path) {
+                error!("failed to load replay from {}: {error}", path.display());
+            }
+        });
+    }
 }
@@ -0,0 +1,487 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use bevy_ecs::{entity::EntityHashMap, prelude::*};
+use bevy_log::prelude::*;
+use bevy_math::prelude::*;
+use wdn_physics::tile::{material::TileMaterial, position::TilePosition};
+use wdn_world::{command::PlayerCommand, pawn::path::PawnPath};
+
+use crate::{
+    clear_world,
+    data::{SaveData, SaveEntities},
+    format::{SaveReader, SaveWriter},
+};
+
+pub const REPLAY_MAGIC: [u8; 4] = *b"WDNR";
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct ReplayData {
+    pub initial: SaveData,
+    pub commands: Vec<(u64, ReplayCommand)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayCommand {
+    SetTarget {
+        pawn: u32,
+        layer: u32,
+        target: IVec2,
+    },
+    AttackLeft {
+        pawn: u32,
+    },
+    AttackRight {
+        pawn: u32,
+    },
+    SetMaterial {
+        layer: u32,
+        position: IVec2,
+        material: TileMaterial,
+    },
+    SpawnDoor {
+        layer: u32,
+        position: IVec2,
+    },
+    DespawnDoor {
+        layer: u32,
+        position: IVec2,
+    },
+    PlaceBlueprint {
+        layer: u32,
+        position: IVec2,
+        material: TileMaterial,
+    },
+    RemoveBlueprint {
+        layer: u32,
+        position: IVec2,
+    },
+}
+
+#[derive(Resource, Debug)]
+pub struct ReplayRecorder {
+    data: ReplayData,
+    layers: EntityHashMap<u32>,
+    pawns: EntityHashMap<u32>,
+    tick: u64,
+    // The first command that couldn't be recorded, which makes the whole replay unusable.
+    error: Option<String>,
+}
+
+#[derive(Resource, Debug)]
+pub struct ReplayPlayer {
+    commands: VecDeque<(u64, PlayerCommand)>,
+    tick: u64,
+}
+
+/// Starts recording player commands from the current world.
+///
+/// Saves only keep each pawn's target, so every pawn with a target replans its path from
+/// where it stands, exactly as playback will. Pawns may take a different route than they
+/// were about to.
+pub fn start_recording(world: &mut World) -> Result {
+    let (initial, entities) = SaveData::capture_entities(world)?;
+
+    // Saves don't keep paths, so replan now to match what the replay will do on load.
+    let mut paths = world.query::<&mut PawnPath>();
+    for mut path in paths.iter_mut(world) {
+        if let Some(target) = path.target() {
+            path.set_target(target);
+        }
+    }
+
+    world.insert_resource(ReplayRecorder {
+        data: ReplayData {
+            initial,
+            commands: Vec::new(),
+        },
+        layers: index_entities(&entities.layers),
+        pawns: index_entities(&entities.pawns),
+        tick: 0,
+        error: None,
+    });
+    Ok(())
+}
+
+pub fn finish_recording(world: &mut World) -> Result<ReplayData> {
+    let recorder = world
+        .remove_resource::<ReplayRecorder>()
+        .ok_or("no replay is being recorded")?;
+    match recorder.error {
+        Some(error) => Err(error.into()),
+        None => Ok(recorder.data),
+    }
+}
+
+pub fn save_replay(world: &mut World, path: impl AsRef<Path>) -> Result {
+    let recorder = world
+        .get_resource::<ReplayRecorder>()
+        .ok_or("no replay is being recorded")?;
+    if let Some(error) = &recorder.error {
+        return Err(error.clone().into());
+    }
+    fs::write(path, recorder.data.encode())?;
+    Ok(())
+}
+
+pub fn load_replay(world: &mut World, path: impl AsRef<Path>) -> Result {
+    let data = ReplayData::decode(&fs::read(path)?)?;
+    play_replay(world, data)
+}
+
+pub fn play_replay(world: &mut World, data: ReplayData) -> Result {
+    data.validate()?;
+    clear_world(world);
+    world.remove_resource::<ReplayRecorder>();
+
+    let entities = data.initial.restore_entities(world)?;
+    let commands = data
+        .commands
+        .into_iter()
+        .map(|(tick, command)| Ok((tick, command.resolve(&entities)?)))
+        .collect::<Result<_>>()?;
+
+    world.insert_resource(ReplayPlayer { commands, tick: 0 });
+    Ok(())
+}
+
+pub fn play_replay_commands(
+    player: Option<ResMut<ReplayPlayer>>,
+    mut writer: MessageWriter<PlayerCommand>,
+) {
+    let Some(mut player) = player else {
+        return;
+    };
+
+    while let Some(&(tick, command)) = player.commands.front()
+        && tick <= player.tick
+    {
+        writer.write(command);
+        player.commands.pop_front();
+    }
+
+    player.tick += 1;
+}
+
+pub fn record_player_commands(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    mut messages: MessageReader<PlayerCommand>,
+) {
+    let Some(mut recorder) = recorder else {
+        messages.clear();
+        return;
+    };
+
+    let tick = recorder.tick;
+    for command in messages.read() {
+        match ReplayCommand::capture(command, &recorder.layers, &recorder.pawns) {
+            Ok(command) => recorder.data.commands.push((tick, command)),
+            Err(error) => {
+                if recorder.error.is_none() {
+                    error!("failed to record replay command at tick {tick}: {error}");
+                    recorder.error = Some(format!("tick {tick}: {error}"));
+                }
+            }
+        }
+    }
+
+    recorder.tick += 1;
+}
+
+fn index_entities(entities: &[Entity]) -> EntityHashMap<u32> {
+    entities
+        .iter()
+        .enumerate()
+        .map(|(index, &entity)| (entity, index as u32))
+        .collect()
+}
+
+impl ReplayData {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = SaveWriter::with_header(REPLAY_MAGIC, REPLAY_VERSION);
+
+        let initial = self.initial.encode();
+        writer.write_len(initial.len());
+        writer.write_bytes(&initial);
+
+        writer.write_len(self.commands.len());
+        for &(tick, command) in &self.commands {
+            writer.write_u64(tick);
+            match command {
+                ReplayCommand::SetTarget {
+                    pawn,
+                    layer,
+                    target,
+                } => {
+                    writer.write_u8(0);
+                    writer.write_u32(pawn);
+                    writer.write_u32(layer);
+                    writer.write_i32(target.x);
+                    writer.write_i32(target.y);
+                }
+                ReplayCommand::AttackLeft { pawn } => {
+                    writer.write_u8(1);
+                    writer.write_u32(pawn);
+                }
+                ReplayCommand::AttackRight { pawn } => {
+                    writer.write_u8(2);
+                    writer.write_u32(pawn);
+                }
+                ReplayCommand::SetMaterial {
+                    layer,
+                    position,
+                    material,
+                } => {
+                    writer.write_u8(3);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                    writer.write_u16(material.bits());
+                }
+                ReplayCommand::SpawnDoor { layer, position } => {
+                    writer.write_u8(4);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                }
+                ReplayCommand::DespawnDoor { layer, position } => {
+                    writer.write_u8(5);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                }
+                ReplayCommand::PlaceBlueprint {
+                    layer,
+                    position,
+                    material,
+                } => {
+                    writer.write_u8(6);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                    writer.write_u16(material.bits());
+                }
+                ReplayCommand::RemoveBlueprint { layer, position } => {
+                    writer.write_u8(7);
+                    writer.write_u32(layer);
+                    writer.write_i32(position.x);
+                    writer.write_i32(position.y);
+                }
+            }
+        }
+
+        writer.into_bytes()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = SaveReader::with_header(bytes, REPLAY_MAGIC, REPLAY_VERSION)?;
+
+        let initial_len = reader.read_len()?;
+        let initial = SaveData::decode(reader.read_bytes(initial_len)?)?;
+
+        let command_count = reader.read_len()?;
+        let mut commands = Vec::new();
+        for _ in 0..command_count {
+            let tick = reader.read_u64()?;
+            let command = match reader.read_u8()? {
+                0 => ReplayCommand::SetTarget {
+                    pawn: reader.read_u32()?,
+                    layer: reader.read_u32()?,
+                    target: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                },
+                1 => ReplayCommand::AttackLeft {
+                    pawn: reader.read_u32()?,
+                },
+                2 => ReplayCommand::AttackRight {
+                    pawn: reader.read_u32()?,
+                },
+                3 => ReplayCommand::SetMaterial {
+                    layer: reader.read_u32()?,
+                    position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                    material: TileMaterial::try_from_bits(reader.read_u16()?)
+                        .ok_or("invalid tile material")?,
+                },
+                4 => ReplayCommand::SpawnDoor {
+                    layer: reader.read_u32()?,
+                    position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                },
+                5 => ReplayCommand::DespawnDoor {
+                    layer: reader.read_u32()?,
+                    position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                },
+                6 => ReplayCommand::PlaceBlueprint {
+                    layer: reader.read_u32()?,
+                    position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                    material: TileMaterial::try_from_bits(reader.read_u16()?)
+                        .ok_or("invalid tile material")?,
+                },
+                7 => ReplayCommand::RemoveBlueprint {
+                    layer: reader.read_u32()?,
+                    position: IVec2::new(reader.read_i32()?, reader.read_i32()?),
+                },
+                tag => return Err(format!("invalid replay command {tag}").into()),
+            };
+            commands.push((tick, command));
+        }
+
+        if !reader.is_empty() {
+            return Err("unexpected trailing data in replay file".into());
+        }
+
+        Ok(ReplayData { initial, commands })
+    }
+
+    /// Checks that the initial save and every command only refer to what the save restores.
+    pub fn validate(&self) -> Result {
+        self.initial.validate()?;
+        for (tick, command) in &self.commands {
+            let (layer, pawn) = command.indices();
+            if layer.is_some_and(|index| index as usize >= self.initial.layers.len()) {
+                return Err(format!("tick {tick}: invalid layer index").into());
+            }
+            if pawn.is_some_and(|index| index as usize >= self.initial.pawns.len()) {
+                return Err(format!("tick {tick}: invalid pawn index").into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReplayCommand {
+    fn capture(
+        command: &PlayerCommand,
+        layers: &EntityHashMap<u32>,
+        pawns: &EntityHashMap<u32>,
+    ) -> Result<Self> {
+        // Replays only restore what existed when recording began, so anything spawned since
+        // can't be referenced on playback.
+        let layer = |position: TilePosition| -> Result<u32> {
+            layers
+                .get(&position.layer())
+                .copied()
+                .ok_or_else(|| format!("layer {} is not in the replay", position.layer()).into())
+        };
+        let pawn = |pawn: Entity| -> Result<u32> {
+            pawns
+                .get(&pawn)
+                .copied()
+                .ok_or_else(|| format!("pawn {pawn} is not in the replay").into())
+        };
+
+        Ok(match *command {
+            PlayerCommand::SetTarget { pawn: id, target } => ReplayCommand::SetTarget {
+                pawn: pawn(id)?,
+                layer: layer(target)?,
+                target: target.position(),
+            },
+            PlayerCommand::AttackLeft { pawn: id } => ReplayCommand::AttackLeft { pawn: pawn(id)? },
+            PlayerCommand::AttackRight { pawn: id } => {
+                ReplayCommand::AttackRight { pawn: pawn(id)? }
+            }
+            PlayerCommand::SetMaterial { position, material } => ReplayCommand::SetMaterial {
+                layer: layer(position)?,
+                position: position.position(),
+                material,
+            },
+            PlayerCommand::SpawnDoor { position } => ReplayCommand::SpawnDoor {
+                layer: layer(position)?,
+                position: position.position(),
+            },
+            PlayerCommand::DespawnDoor { position } => ReplayCommand::DespawnDoor {
+                layer: layer(position)?,
+                position: position.position(),
+            },
+            PlayerCommand::PlaceBlueprint { position, material } => ReplayCommand::PlaceBlueprint {
+                layer: layer(position)?,
+                position: position.position(),
+                material,
+            },
+            PlayerCommand::RemoveBlueprint { position } => ReplayCommand::RemoveBlueprint {
+                layer: layer(position)?,
+                position: position.position(),
+            },
+        })
+    }
+
+    fn indices(&self) -> (Option<u32>, Option<u32>) {
+        match *self {
+            ReplayCommand::SetTarget { pawn, layer, .. } => (Some(layer), Some(pawn)),
+            ReplayCommand::AttackLeft { pawn } | ReplayCommand::AttackRight { pawn } => {
+                (None, Some(pawn))
+            }
+            ReplayCommand::SetMaterial { layer, .. }
+            | ReplayCommand::SpawnDoor { layer, .. }
+            | ReplayCommand::DespawnDoor { layer, .. }
+            | ReplayCommand::PlaceBlueprint { layer, .. }
+            | ReplayCommand::RemoveBlueprint { layer, .. } => (Some(layer), None),
+        }
+    }
+
+    fn resolve(self, entities: &SaveEntities) -> Result<PlayerCommand> {
+        let layer = |index: u32| -> Result<Entity> {
+            entities
+                .layers
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| "invalid layer index".into())
+        };
+        let pawn = |index: u32| -> Result<Entity> {
+            entities
+                .pawns
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| "invalid pawn index".into())
+        };
+
+        Ok(match self {
+            ReplayCommand::SetTarget {
+                pawn: index,
+                layer: layer_index,
+                target,
+            } => PlayerCommand::SetTarget {
+                pawn: pawn(index)?,
+                target: TilePosition::from_vec(layer(layer_index)?, target),
+            },
+            ReplayCommand::AttackLeft { pawn: index } => {
+                PlayerCommand::AttackLeft { pawn: pawn(index)? }
+            }
+            ReplayCommand::AttackRight { pawn: index } => {
+                PlayerCommand::AttackRight { pawn: pawn(index)? }
+            }
+            ReplayCommand::SetMaterial {
+                layer: index,
+                position,
+                material,
+            } => PlayerCommand::SetMaterial {
+                position: TilePosition::from_vec(layer(index)?, position),
+                material,
+            },
+            ReplayCommand::SpawnDoor {
+                layer: index,
+                position,
+            } => PlayerCommand::SpawnDoor {
+                position: TilePosition::from_vec(layer(index)?, position),
+            },
+            ReplayCommand::DespawnDoor {
+                layer: index,
+                position,
+            } => PlayerCommand::DespawnDoor {
+                position: TilePosition::from_vec(layer(index)?, position),
+            },
+            ReplayCommand::PlaceBlueprint {
+                layer: index,
+                position,
+                material,
+            } => PlayerCommand::PlaceBlueprint {
+                position: TilePosition::from_vec(layer(index)?, position),
+                material,
+            },
+            ReplayCommand::RemoveBlueprint {
+                layer: index,
+                position,
+            } => PlayerCommand::RemoveBlueprint {
+                position: TilePosition::from_vec(layer(index)?, position),
+            },
+        })
+    }
+}
@@ -0,0 +1,720 @@
+use std::{env, fs, path::PathBuf, process, time::Duration};
+
+use bevy_app::{TaskPoolPlugin, prelude::*};
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use bevy_time::TimePlugin;
+use wdn_physics::{
+    PhysicsPlugin,
+    collision::filter::{CollisionFilter, CollisionGroups},
+    kinematics::{Mass, Position, Velocity},
+    layer::{Layer, LayerStack},
+    tile::{
+        material::TileMaterial,
+        position::TilePosition,
+        storage::{TileChunk, TileStorage, TileStorageMut},
+    },
+};
+use wdn_tasks::{
+    TasksPlugin,
+    blueprint::{Blueprint, BlueprintCost, BuildingMaterials, Stockpile},
+    task::{Task, TaskKind, TaskQueue, TaskStatus, TaskWorker},
+};
+use wdn_world::{
+    WorldPlugin,
+    combat::Health,
+    door::{Door, DoorAccess, DoorState},
+    path::{
+        cost::{AvoidZone, DangerZones},
+        region::Region,
+    },
+    pawn::{Pawn, PawnProjectile, Staff, action::PawnAction, path::PawnPath},
+    trigger::{TileTrigger, Trigger},
+};
+
+use crate::{
+    data::{PawnData, ProjectileData, SaveData, TaskWorkerData},
+    format::{SAVE_MAGIC, SaveWriter},
+    load_world,
+    replay::{ReplayCommand, ReplayData, play_replay},
+    save_world,
+};
+
+#[test]
+fn save_load_round_trip() {
+    let path = save_path("round_trip");
+
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(TilePosition::new(layer, 0, 0), TileMaterial::WALL);
+            storage.set_material(TilePosition::new(layer, 0, 1), TileMaterial::DOOR);
+            storage.set_material(TilePosition::new(layer, -40, 3), TileMaterial::WALL);
+        })
+        .unwrap();
+    app.world_mut().spawn((
+        Door::from_state(DoorState::Open {
+            timer: Duration::from_millis(1500),
+        }),
+        TilePosition::new(layer, 0, 1),
+        ChildOf(layer),
+    ));
+    let mut target = PawnPath::default();
+    target.set_target(TilePosition::new(layer, 5, 5));
+    let pawn = app.world_mut().spawn((
+        Pawn::default(),
+        ChildOf(layer),
+        Position::new(Vec2::new(2.5, 3.5), Rot2::degrees(45.0)),
+        Velocity::new(Vec2::new(1.0, -0.5)).with_angular(0.25),
+        Health {
+            current: 7,
+            max: 10,
+        },
+        Staff,
+        Mass::new(2.0),
+        target,
+    ));
+    let pawn = pawn.id();
+    app.world_mut().spawn(PawnProjectile::bundle(
+        pawn,
+        &Staff::FILTER,
+        Vec2::new(2.5, 3.7),
+        Vec2::new(0.0, 0.5),
+    ));
+    app.update();
+    app.world_mut()
+        .resource_mut::<DangerZones>()
+        .insert_for(TilePosition::new(layer, 3, 4), Duration::from_secs(12));
+
+    save_world(app.world_mut(), &path).unwrap();
+
+    let mut loaded = make_app();
+    load_world(loaded.world_mut(), &path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let layer = loaded
+        .world_mut()
+        .query_filtered::<Entity, With<Layer>>()
+        .single(loaded.world())
+        .unwrap();
+
+    let mut pawns = loaded.world_mut().query_filtered::<(
+        &ChildOf,
+        &Position,
+        &Velocity,
+        &Health,
+        Has<Staff>,
+        &CollisionFilter,
+        &Mass,
+        &PawnPath,
+    ), With<Pawn>>();
+    let (parent, position, velocity, health, staff, filter, mass, path) =
+        pawns.single(loaded.world()).unwrap();
+    assert_eq!(parent.parent(), layer);
+    assert_eq!(position.position(), Vec2::new(2.5, 3.5));
+    assert!((position.rotation().as_degrees() - 45.0).abs() < 1e-4);
+    assert_eq!(velocity.linear(), Vec2::new(1.0, -0.5));
+    assert_eq!(velocity.angular(), 0.25);
+    assert_eq!(health.current, 7);
+    assert_eq!(health.max, 10);
+    assert!(staff);
+    assert_eq!(*filter, Staff::FILTER);
+    assert_eq!(mass.value(), 2.0);
+    assert_eq!(path.target(), Some(TilePosition::new(layer, 5, 5)));
+
+    let mut projectiles = loaded
+        .world_mut()
+        .query_filtered::<&CollisionFilter, With<PawnProjectile>>();
+    assert_eq!(
+        *projectiles.single(loaded.world()).unwrap(),
+        PawnProjectile::filter(&Staff::FILTER)
+    );
+
+    let zones = loaded.world().resource::<DangerZones>();
+    let zone = zones.iter().next().unwrap();
+    assert_eq!(zones.iter().count(), 1);
+    assert_eq!(zone.position(), TilePosition::new(layer, 3, 4));
+    assert_eq!(zone.remaining(), Duration::from_secs(12));
+
+    loaded.world_mut().run_schedule(FixedUpdate);
+
+    assert!(
+        loaded
+            .world()
+            .get::<ChildOf>(layer)
+            .is_some_and(|parent| loaded.world().get::<LayerStack>(parent.parent()).is_some())
+    );
+
+    let region_count = loaded
+        .world_mut()
+        .query::<&Region>()
+        .iter(loaded.world())
+        .count();
+    assert!(region_count > 0);
+
+    let chunk_count = loaded
+        .world_mut()
+        .query::<&TileChunk>()
+        .iter(loaded.world())
+        .count();
+    assert_eq!(
+        chunk_count,
+        app.world_mut()
+            .query::<&TileChunk>()
+            .iter(app.world())
+            .count()
+    );
+
+    loaded
+        .world_mut()
+        .run_system_once(move |storage: TileStorage| {
+            assert_eq!(
+                storage
+                    .get(TilePosition::new(layer, 0, 0))
+                    .unwrap()
+                    .material(),
+                TileMaterial::WALL
+            );
+            assert_eq!(
+                storage
+                    .get(TilePosition::new(layer, 0, 1))
+                    .unwrap()
+                    .material(),
+                TileMaterial::DOOR
+            );
+            assert_eq!(
+                storage
+                    .get(TilePosition::new(layer, -40, 3))
+                    .unwrap()
+                    .material(),
+                TileMaterial::WALL
+            );
+            assert_eq!(
+                storage
+                    .get(TilePosition::new(layer, 1, 0))
+                    .unwrap()
+                    .material(),
+                TileMaterial::EMPTY
+            );
+            assert!(
+                !storage
+                    .get_adjacency(TilePosition::new(layer, 1, 0))
+                    .walls()
+                    .is_empty()
+            );
+        })
+        .unwrap();
+
+    let mut doors = loaded.world_mut().query::<(&Door, &TilePosition)>();
+    let (door, door_position) = doors.single(loaded.world()).unwrap();
+    assert_eq!(*door_position, TilePosition::new(layer, 0, 1));
+    assert!(matches!(door.state(), DoorState::Open { .. }));
+}
+
+#[test]
+fn load_replaces_existing_world() {
+    let path = save_path("replace");
+
+    let mut app = make_app();
+    spawn_layer(&mut app);
+    app.update();
+    save_world(app.world_mut(), &path).unwrap();
+
+    let mut loaded = make_app();
+    let old_layer = spawn_layer(&mut loaded);
+    let old_blueprint = loaded
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            TilePosition::new(old_layer, 2, 2),
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 1,
+                work: Duration::ZERO,
+            },
+        ))
+        .id();
+    let old_pawn = loaded
+        .world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(old_layer),
+            Position::new(Vec2::new(0.5, 0.5), Rot2::IDENTITY),
+            TaskWorker::new(old_blueprint, 2),
+        ))
+        .id();
+    *loaded
+        .world_mut()
+        .get_mut::<TaskStatus>(old_blueprint)
+        .unwrap() = TaskStatus::Assigned { worker: old_pawn };
+    let old_stockpile = loaded
+        .world_mut()
+        .spawn((Stockpile::new(3), TilePosition::new(old_layer, 4, 4)))
+        .id();
+    let old_trigger = loaded
+        .world_mut()
+        .spawn(TileTrigger::new([TilePosition::new(old_layer, 1, 1)]))
+        .id();
+    loaded.update();
+
+    load_world(loaded.world_mut(), &path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(loaded.world().get_entity(old_layer).is_err());
+    assert!(loaded.world().get_entity(old_blueprint).is_err());
+    assert!(loaded.world().get_entity(old_stockpile).is_err());
+    assert_eq!(
+        loaded
+            .world_mut()
+            .query::<&Stockpile>()
+            .iter(loaded.world())
+            .count(),
+        0
+    );
+    assert!(loaded.world().get_entity(old_trigger).is_err());
+    assert!(loaded.world().resource::<TaskQueue>().is_empty());
+    assert_eq!(
+        loaded
+            .world_mut()
+            .query::<&Layer>()
+            .iter(loaded.world())
+            .count(),
+        1
+    );
+    assert_eq!(
+        loaded
+            .world_mut()
+            .query::<&Pawn>()
+            .iter(loaded.world())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn load_invalid_index_keeps_existing_world() {
+    let path = save_path("invalid_index");
+
+    let mut app = make_app();
+    spawn_layer(&mut app);
+    app.update();
+    let data = SaveData::capture(app.world_mut()).unwrap();
+
+    let mut loaded = make_app();
+    let old_layer = spawn_layer(&mut loaded);
+    loaded.update();
+
+    let pawn = PawnData {
+        layer: 0,
+        position: Vec2::new(0.5, 0.5),
+        rotation: Rot2::IDENTITY,
+        linear_velocity: Vec2::ZERO,
+        angular_velocity: 0.0,
+        health: 1,
+        max_health: 1,
+        staff: false,
+        mass: None,
+        target: None,
+        action: PawnAction::default(),
+        commanded: false,
+        task: None,
+    };
+    let invalid = [
+        SaveData {
+            stacks: 0,
+            ..data.clone()
+        },
+        SaveData {
+            pawns: vec![PawnData { layer: 1, ..pawn }],
+            ..data.clone()
+        },
+        SaveData {
+            pawns: vec![PawnData {
+                mass: Some(0.0),
+                ..pawn
+            }],
+            ..data.clone()
+        },
+        SaveData {
+            pawns: vec![PawnData {
+                task: Some(TaskWorkerData {
+                    task: 0,
+                    carrying: 0,
+                    unreachable: 0,
+                }),
+                ..pawn
+            }],
+            ..data.clone()
+        },
+        SaveData {
+            projectiles: vec![ProjectileData {
+                pawn: 0,
+                position: Vec2::ZERO,
+                rotation: Rot2::IDENTITY,
+                linear_velocity: Vec2::ZERO,
+                angular_velocity: 0.0,
+                damage: 1,
+                elapsed: Duration::ZERO,
+                duration: Duration::from_secs(1),
+            }],
+            ..data.clone()
+        },
+    ];
+    for data in invalid {
+        fs::write(&path, data.encode()).unwrap();
+        assert!(load_world(loaded.world_mut(), &path).is_err());
+        assert!(loaded.world().get_entity(old_layer).is_ok());
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn save_load_tasks_round_trip() {
+    let path = save_path("tasks");
+
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(TilePosition::new(layer, 0, 1), TileMaterial::DOOR);
+        })
+        .unwrap();
+    app.insert_resource(BuildingMaterials { amount: 4 });
+    let site = TilePosition::new(layer, 3, 3);
+    app.world_mut().spawn((
+        Blueprint::new(TileMaterial::WALL, 2).with_delivered(1),
+        site,
+        Task::new(TaskKind::Build(TileMaterial::WALL), site).with_work(Duration::from_secs(2)),
+    ));
+    let haul = app
+        .world_mut()
+        .spawn(
+            Task::new(
+                TaskKind::Haul {
+                    destination: TilePosition::new(layer, 3, 3),
+                    amount: 1,
+                },
+                TilePosition::new(layer, -3, 0),
+            )
+            .with_priority(2),
+        )
+        .id();
+    let pawn = app
+        .world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(Vec2::new(-1.5, 0.5), Rot2::IDENTITY),
+            TaskWorker::new(haul, 1),
+        ))
+        .id();
+    *app.world_mut().get_mut::<TaskStatus>(haul).unwrap() = TaskStatus::Assigned { worker: pawn };
+    let mut access = DoorAccess::owned([pawn]);
+    access.set_locked(true);
+    app.world_mut().spawn((
+        Door::default(),
+        access,
+        TilePosition::new(layer, 0, 1),
+        ChildOf(layer),
+    ));
+    app.world_mut()
+        .spawn((Stockpile::new(5), TilePosition::new(layer, -3, 0)));
+    app.world_mut().spawn(AvoidZone::new(
+        [
+            TilePosition::new(layer, 1, 1),
+            TilePosition::new(layer, 1, 2),
+        ],
+        CollisionGroups::PRISONER,
+        30,
+    ));
+    app.world_mut()
+        .spawn(TileTrigger::new([TilePosition::new(layer, 2, 2)]));
+
+    save_world(app.world_mut(), &path).unwrap();
+
+    let mut loaded = make_app();
+    load_world(loaded.world_mut(), &path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let layer = loaded
+        .world_mut()
+        .query_filtered::<Entity, With<Layer>>()
+        .single(loaded.world())
+        .unwrap();
+    let (pawn, worker) = loaded
+        .world_mut()
+        .query::<(Entity, &TaskWorker)>()
+        .single(loaded.world())
+        .unwrap();
+    let haul = worker.task();
+    assert_eq!(worker.carrying(), 1);
+
+    let task = loaded.world().get::<Task>(haul).unwrap();
+    assert_eq!(task.priority(), 2);
+    assert_eq!(task.position(), TilePosition::new(layer, -3, 0));
+    assert_eq!(
+        task.kind(),
+        TaskKind::Haul {
+            destination: TilePosition::new(layer, 3, 3),
+            amount: 1,
+        }
+    );
+    assert_eq!(
+        *loaded.world().get::<TaskStatus>(haul).unwrap(),
+        TaskStatus::Assigned { worker: pawn }
+    );
+
+    let (blueprint, blueprint_data, task, status) = loaded
+        .world_mut()
+        .query::<(Entity, &Blueprint, &Task, &TaskStatus)>()
+        .single(loaded.world())
+        .unwrap();
+    assert_eq!(blueprint_data.material(), TileMaterial::WALL);
+    assert_eq!(blueprint_data.cost(), 2);
+    assert_eq!(blueprint_data.delivered(), 1);
+    assert!(!blueprint_data.is_paid());
+    assert_eq!(task.position(), TilePosition::new(layer, 3, 3));
+    assert_eq!(task.work(), Duration::from_secs(2));
+    assert_eq!(*status, TaskStatus::Queued);
+    assert_eq!(
+        loaded
+            .world()
+            .resource::<TaskQueue>()
+            .iter()
+            .collect::<Vec<_>>(),
+        vec![blueprint]
+    );
+
+    let access = loaded
+        .world_mut()
+        .query_filtered::<&DoorAccess, With<Door>>()
+        .single(loaded.world())
+        .unwrap();
+    assert!(access.is_locked());
+    assert!(!access.allows(pawn, true, Duration::ZERO));
+    assert!(access.owners().contains(&pawn));
+
+    assert_eq!(
+        loaded
+            .world_mut()
+            .query::<(&Stockpile, &TilePosition)>()
+            .iter(loaded.world())
+            .map(|(stockpile, &position)| (stockpile.amount, position))
+            .collect::<Vec<_>>(),
+        vec![(5, TilePosition::new(layer, -3, 0))]
+    );
+    assert_eq!(loaded.world().resource::<BuildingMaterials>().amount, 4);
+
+    let zone = loaded
+        .world_mut()
+        .query::<&AvoidZone>()
+        .single(loaded.world())
+        .unwrap();
+    assert_eq!(zone.groups(), CollisionGroups::PRISONER);
+    assert_eq!(zone.tile_cost(), 30);
+    assert_eq!(zone.tiles().len(), 2);
+    assert!(zone.tiles().contains(&TilePosition::new(layer, 1, 2)));
+
+    assert_eq!(
+        loaded
+            .world_mut()
+            .query::<&Trigger>()
+            .iter(loaded.world())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn decode_round_trip() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(TilePosition::new(layer, 3, 3), TileMaterial::WALL);
+        })
+        .unwrap();
+    app.world_mut().spawn((
+        Door::from_state(DoorState::Closing { position: 0.75 }),
+        TilePosition::new(layer, 4, 3),
+        ChildOf(layer),
+    ));
+    app.update();
+    app.world_mut()
+        .resource_mut::<DangerZones>()
+        .insert_for(TilePosition::new(layer, 4, 4), Duration::from_millis(2500));
+
+    let data = SaveData::capture(app.world_mut()).unwrap();
+    let bytes = data.encode();
+    let decoded = SaveData::decode(&bytes).unwrap();
+
+    assert_eq!(decoded.stacks, data.stacks);
+    assert_eq!(decoded.layers, data.layers);
+    assert_eq!(decoded.doors, data.doors);
+    assert_eq!(decoded.pawns, data.pawns);
+    assert_eq!(decoded.danger_zones.len(), 1);
+    assert_eq!(decoded.danger_zones, data.danger_zones);
+    assert_eq!(decoded.chunks.len(), data.chunks.len());
+    for (decoded, chunk) in decoded.chunks.iter().zip(&data.chunks) {
+        assert_eq!(
+            (decoded.layer, decoded.x, decoded.y),
+            (chunk.layer, chunk.x, chunk.y)
+        );
+        assert_eq!(decoded.materials, chunk.materials);
+    }
+    assert_eq!(decoded.encode(), bytes);
+}
+
+#[test]
+fn decode_invalid() {
+    assert!(SaveData::decode(b"").is_err());
+    assert!(SaveData::decode(b"NOPE\x01\x00\x00\x00").is_err());
+
+    let mut bytes = SAVE_MAGIC.to_vec();
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    assert!(SaveData::decode(&bytes).is_err());
+
+    let mut truncated = SaveData::default().encode();
+    truncated.pop();
+    assert!(SaveData::decode(&truncated).is_err());
+
+    let mut trailing = SaveWriter::new();
+    trailing.write_u32(0);
+    for _ in 0..9 {
+        trailing.write_len(0);
+    }
+    trailing.write_bool(false);
+    trailing.write_u8(0);
+    assert!(SaveData::decode(&trailing.into_bytes()).is_err());
+
+    let mut overflow = SaveWriter::new();
+    overflow.write_u32(0);
+    overflow.write_len(0);
+    overflow.write_len(0);
+    overflow.write_len(1);
+    overflow.write_u32(0);
+    overflow.write_i32(0);
+    overflow.write_i32(0);
+    overflow.write_u8(2);
+    overflow.write_u64(u64::MAX);
+    overflow.write_u32(u32::MAX);
+    overflow.write_len(0);
+    overflow.write_len(0);
+    assert!(SaveData::decode(&overflow.into_bytes()).is_err());
+}
+
+#[test]
+fn replay_decode_round_trip() {
+    let mut app = make_app();
+    let layer = spawn_layer(&mut app);
+    app.world_mut().spawn((
+        Pawn::default(),
+        ChildOf(layer),
+        Position::new(Vec2::new(1.5, 1.5), Rot2::IDENTITY),
+    ));
+    app.update();
+
+    let data = ReplayData {
+        initial: SaveData::capture(app.world_mut()).unwrap(),
+        commands: vec![
+            (
+                0,
+                ReplayCommand::SetTarget {
+                    pawn: 0,
+                    layer: 0,
+                    target: IVec2::new(4, -2),
+                },
+            ),
+            (3, ReplayCommand::AttackLeft { pawn: 0 }),
+            (
+                3,
+                ReplayCommand::SetMaterial {
+                    layer: 0,
+                    position: IVec2::new(2, 2),
+                    material: TileMaterial::WALL,
+                },
+            ),
+            (
+                7,
+                ReplayCommand::SpawnDoor {
+                    layer: 0,
+                    position: IVec2::new(2, 3),
+                },
+            ),
+            (
+                9,
+                ReplayCommand::PlaceBlueprint {
+                    layer: 0,
+                    position: IVec2::new(-1, 4),
+                    material: TileMaterial::DOOR,
+                },
+            ),
+            (
+                12,
+                ReplayCommand::RemoveBlueprint {
+                    layer: 0,
+                    position: IVec2::new(-1, 4),
+                },
+            ),
+        ],
+    };
+    let bytes = data.encode();
+    let decoded = ReplayData::decode(&bytes).unwrap();
+
+    assert_eq!(decoded.commands, data.commands);
+    assert_eq!(decoded.initial.encode(), data.initial.encode());
+    assert_eq!(decoded.encode(), bytes);
+
+    assert!(ReplayData::decode(&data.initial.encode()).is_err());
+}
+
+#[test]
+fn play_replay_invalid_index_keeps_existing_world() {
+    let mut app = make_app();
+    spawn_layer(&mut app);
+    app.update();
+    let initial = SaveData::capture(app.world_mut()).unwrap();
+
+    let mut loaded = make_app();
+    let old_layer = spawn_layer(&mut loaded);
+    loaded.update();
+
+    let invalid = [
+        ReplayCommand::AttackLeft { pawn: 0 },
+        ReplayCommand::SpawnDoor {
+            layer: 1,
+            position: IVec2::ZERO,
+        },
+    ];
+    for command in invalid {
+        let data = ReplayData {
+            initial: initial.clone(),
+            commands: vec![(0, command)],
+        };
+        assert!(play_replay(loaded.world_mut(), data).is_err());
+        assert!(loaded.world().get_entity(old_layer).is_ok());
+    }
+}
+
+fn make_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        PhysicsPlugin,
+        WorldPlugin,
+        TasksPlugin,
+    ));
+    app
+}
+
+fn spawn_layer(app: &mut App) -> Entity {
+    let stack = app.world_mut().spawn(LayerStack::default()).id();
+    app.world_mut()
+        .spawn((Layer::default(), ChildOf(stack)))
+        .id()
+}
+
+fn save_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("wdn-save-{name}-{}.wdn", process::id()))
+}
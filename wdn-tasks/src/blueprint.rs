@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use bevy_platform::collections::HashMap;
+use wdn_physics::tile::{
+    index::TileIndex,
+    material::{TileKind, TileMaterial},
+    position::TilePosition,
+    storage::TileStorage,
+};
+use wdn_world::command::PlayerCommand;
+
+use crate::task::{Task, TaskKind};
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Blueprint {
+    material: TileMaterial,
+    cost: u32,
+    delivered: u32,
+    paid: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlueprintCost {
+    pub materials: u32,
+    pub work: Duration,
+}
+
+#[derive(Resource, Debug)]
+pub struct BlueprintCosts {
+    costs: HashMap<TileMaterial, BlueprintCost>,
+    default: BlueprintCost,
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct BuildingMaterials {
+    pub amount: u32,
+}
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Stockpile {
+    pub amount: u32,
+}
+
+pub fn apply_blueprint_commands(
+    mut commands: Commands,
+    mut messages: MessageReader<PlayerCommand>,
+    storage: TileStorage,
+    index: Res<TileIndex>,
+    blueprints: Query<(), With<Blueprint>>,
+    costs: Res<BlueprintCosts>,
+) {
+    for &command in messages.read() {
+        match command {
+            PlayerCommand::PlaceBlueprint { position, material } => {
+                if storage.get_kind(position) == material.kind()
+                    || index
+                        .get_objects(position)
+                        .iter()
+                        .any(|&object| blueprints.contains(object))
+                {
+                    continue;
+                }
+
+                commands.spawn(Blueprint::bundle(position, material, costs.get(material)));
+            }
+            PlayerCommand::RemoveBlueprint { position } => {
+                for &object in index.get_objects(position) {
+                    if blueprints.contains(object) {
+                        commands.entity(object).despawn();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Blueprint {
+    pub fn bundle(
+        position: TilePosition,
+        material: TileMaterial,
+        cost: BlueprintCost,
+    ) -> impl Bundle {
+        let kind = match material.kind() {
+            TileKind::Door => TaskKind::PlaceDoor,
+            _ => TaskKind::Build(material),
+        };
+
+        (
+            Blueprint::new(material, cost.materials),
+            position,
+            Task::new(kind, position).with_work(cost.work),
+        )
+    }
+
+    pub fn new(material: TileMaterial, cost: u32) -> Self {
+        Blueprint {
+            material,
+            cost,
+            delivered: 0,
+            paid: false,
+        }
+    }
+
+    pub fn with_delivered(mut self, delivered: u32) -> Self {
+        self.delivered = delivered;
+        self
+    }
+
+    pub fn with_paid(mut self, paid: bool) -> Self {
+        self.paid = paid;
+        self
+    }
+
+    pub fn material(&self) -> TileMaterial {
+        self.material
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    pub fn delivered(&self) -> u32 {
+        self.delivered
+    }
+
+    pub fn is_paid(&self) -> bool {
+        self.paid
+    }
+
+    pub(crate) fn deliver(&mut self, amount: u32) {
+        self.delivered += amount;
+    }
+
+    // Delivered materials count towards the cost before the shared pool is drawn on.
+    fn owed(&self) -> u32 {
+        self.cost.saturating_sub(self.delivered)
+    }
+
+    pub(crate) fn can_pay(&self, materials: Option<&BuildingMaterials>) -> bool {
+        self.paid
+            || self.owed() == 0
+            || materials.is_none_or(|materials| materials.amount >= self.owed())
+    }
+
+    pub(crate) fn pay(&mut self, materials: Option<&mut BuildingMaterials>) -> bool {
+        let owed = self.owed();
+        if owed > 0
+            && let Some(materials) = materials
+        {
+            if materials.amount < owed {
+                return false;
+            }
+
+            materials.amount -= owed;
+        }
+
+        self.paid = true;
+        true
+    }
+}
+
+impl Stockpile {
+    pub fn new(amount: u32) -> Self {
+        Stockpile { amount }
+    }
+
+    pub(crate) fn take(&mut self, amount: u32) -> u32 {
+        let taken = self.amount.min(amount);
+        self.amount -= taken;
+        taken
+    }
+}
+
+impl BlueprintCosts {
+    pub fn get(&self, material: TileMaterial) -> BlueprintCost {
+        self.costs.get(&material).copied().unwrap_or(self.default)
+    }
+
+    pub fn insert(&mut self, material: TileMaterial, cost: BlueprintCost) {
+        self.costs.insert(material, cost);
+    }
+}
+
+impl Default for BlueprintCosts {
+    fn default() -> Self {
+        let mut costs = HashMap::default();
+        costs.insert(
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 2,
+                work: Duration::from_secs(2),
+            },
+        );
+        costs.insert(
+            TileMaterial::DOOR,
+            BlueprintCost {
+                materials: 3,
+                work: Duration::from_secs(3),
+            },
+        );
+
+        BlueprintCosts {
+            costs,
+            default: BlueprintCost {
+                materials: 1,
+                work: Duration::from_secs(1),
+            },
+        }
+    }
+}
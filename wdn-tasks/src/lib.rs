@@ -1,7 +1,66 @@
+pub mod blueprint;
+pub mod task;
+#[cfg(test)]
+mod tests;
+
+use std::any::type_name_of_val;
+
 use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use wdn_physics::PhysicsSystems;
+use wdn_world::WorldSystems;
+
+use crate::blueprint::{BlueprintCosts, apply_blueprint_commands};
+use crate::task::{
+    TaskCompleted, TaskFailed, TaskQueue, assign_tasks, on_add_task, on_remove_task,
+    update_task_workers,
+};
 
 pub struct TasksPlugin;
 
+#[derive(Debug, PartialEq, Eq, Clone, Hash, SystemSet)]
+pub enum TasksSystems {
+    UpdateTasks,
+}
+
 impl Plugin for TasksPlugin {
-    fn build(&self, _: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TaskQueue>()
+            .init_resource::<BlueprintCosts>();
+
+        app.add_message::<TaskCompleted>()
+            .add_message::<TaskFailed>();
+
+        app.configure_sets(
+            FixedUpdate,
+            TasksSystems::UpdateTasks
+                .after(WorldSystems::ApplyCommands)
+                .before(WorldSystems::UpdateRegions)
+                .before(PhysicsSystems::Collisions),
+        );
+
+        app.add_systems(
+            FixedUpdate,
+            apply_blueprint_commands.in_set(WorldSystems::ApplyCommands),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (update_task_workers, assign_tasks)
+                .chain()
+                .in_set(TasksSystems::UpdateTasks),
+        );
+
+        app.world_mut()
+            .add_observer(on_add_task)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_add_task)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_task)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_task)
+            )));
+    }
 }
@@ -0,0 +1,581 @@
+use std::{cmp::Ordering, collections::BinaryHeap, time::Duration};
+
+use bevy_ecs::prelude::*;
+use bevy_time::prelude::*;
+use wdn_physics::{
+    collision::Collider,
+    kinematics::Position,
+    tile::{
+        material::{TileKind, TileMaterial},
+        position::TilePosition,
+        storage::{TileStorage, TileStorageMut},
+    },
+};
+use wdn_world::{
+    door::Door,
+    pawn::{Pawn, action::PawnAction, path::PawnPath},
+};
+
+use crate::blueprint::{Blueprint, BuildingMaterials, Stockpile};
+
+#[derive(Component, Clone, Copy, Debug)]
+#[require(TaskStatus)]
+pub struct Task {
+    kind: TaskKind,
+    position: TilePosition,
+    priority: i32,
+    work: Duration,
+    progress: Duration,
+    // Order the task was first queued in, kept when it's requeued so it doesn't lose its place.
+    sequence: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskKind {
+    Build(TileMaterial),
+    PlaceDoor,
+    Remove,
+    Haul {
+        destination: TilePosition,
+        amount: u32,
+    },
+}
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub enum TaskStatus {
+    #[default]
+    Queued,
+    Assigned {
+        worker: Entity,
+    },
+}
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct NoTasks;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TaskWorker {
+    task: Entity,
+    carrying: u32,
+    // Sides of the site, as bits in `work_sides` order, that the worker failed to path to.
+    unreachable: u8,
+}
+
+#[derive(Message, Clone, Copy, Debug)]
+pub struct TaskCompleted {
+    pub task: Entity,
+    pub worker: Entity,
+}
+
+#[derive(Message, Clone, Copy, Debug)]
+pub struct TaskFailed {
+    pub task: Entity,
+    pub worker: Entity,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct TaskQueue {
+    tasks: BinaryHeap<QueuedTask>,
+    next_sequence: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct QueuedTask {
+    priority: i32,
+    sequence: u64,
+    task: Entity,
+}
+
+type IdlePawnData = (
+    Entity,
+    &'static Position,
+    &'static TilePosition,
+    &'static mut PawnPath,
+);
+type IdlePawnFilter = (With<Pawn>, Without<TaskWorker>, Without<NoTasks>);
+
+pub fn on_add_task(
+    trigger: On<Add, Task>,
+    mut tasks: Query<&mut Task>,
+    mut queue: ResMut<TaskQueue>,
+) -> Result {
+    let mut task = tasks.get_mut(trigger.entity)?;
+    task.sequence = queue.push(trigger.entity, task.priority);
+    Ok(())
+}
+
+pub fn on_remove_task(
+    trigger: On<Remove, Task>,
+    mut commands: Commands,
+    statuses: Query<&TaskStatus>,
+    mut workers: Query<(&TaskWorker, &TilePosition, &mut PawnPath, &mut PawnAction)>,
+) -> Result {
+    let TaskStatus::Assigned { worker } = *statuses.get(trigger.entity)? else {
+        return Ok(());
+    };
+
+    if let Ok((task_worker, &tile_position, mut path, mut action)) = workers.get_mut(worker)
+        && task_worker.task == trigger.entity
+    {
+        if task_worker.carrying > 0 {
+            commands.spawn((Stockpile::new(task_worker.carrying), tile_position));
+        }
+        path.clear_target();
+        *action = PawnAction::Stand;
+        commands.entity(worker).try_remove::<TaskWorker>();
+    }
+
+    Ok(())
+}
+
+pub fn assign_tasks(
+    mut commands: Commands,
+    mut queue: ResMut<TaskQueue>,
+    mut tasks: Query<(&Task, &mut TaskStatus, Option<&Blueprint>)>,
+    storage: TileStorage,
+    building_materials: Option<Res<BuildingMaterials>>,
+    mut pawns: Query<IdlePawnData, IdlePawnFilter>,
+) {
+    let mut idle: Vec<Entity> = pawns.iter().map(|(id, ..)| id).collect();
+    let mut unaffordable = Vec::new();
+
+    while !idle.is_empty() {
+        let Some(task_id) = queue.pop() else {
+            break;
+        };
+
+        let Ok((task, mut status, blueprint)) = tasks.get_mut(task_id) else {
+            continue;
+        };
+        if *status != TaskStatus::Queued {
+            continue;
+        }
+        if blueprint.is_some_and(|blueprint| !blueprint.can_pay(building_materials.as_deref())) {
+            unaffordable.push((task_id, *task));
+            continue;
+        }
+
+        let site = task.position();
+        let Some((index, _)) = idle
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &pawn)| {
+                let (_, position, tile_position, _) = pawns.get(pawn).ok()?;
+                let other_layer = tile_position.layer() != site.layer();
+                let distance = position.position().distance_squared(site.center_position());
+                Some((index, (other_layer, distance)))
+            })
+            .min_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        else {
+            queue.requeue(task_id, task);
+            break;
+        };
+
+        let worker = idle.swap_remove(index);
+        let Ok((_, _, &tile_position, mut path)) = pawns.get_mut(worker) else {
+            continue;
+        };
+
+        match work_position(site, tile_position, 0, |tile| storage.get_kind(tile)) {
+            Some(target) => path.set_target(target),
+            None => path.clear_target(),
+        }
+        *status = TaskStatus::Assigned { worker };
+        commands.entity(worker).insert(TaskWorker::new(task_id, 0));
+    }
+
+    for (task_id, task) in unaffordable {
+        queue.requeue(task_id, &task);
+    }
+}
+
+#[expect(clippy::too_many_arguments)]
+pub fn update_task_workers(
+    mut commands: Commands,
+    mut storage: TileStorageMut,
+    mut workers: Query<(
+        Entity,
+        &mut TaskWorker,
+        &TilePosition,
+        &mut PawnPath,
+        &mut PawnAction,
+    )>,
+    mut tasks: Query<(&mut Task, &mut TaskStatus, Option<&mut Blueprint>)>,
+    mut stockpiles: Query<&mut Stockpile>,
+    colliders: Query<&Collider>,
+    mut queue: ResMut<TaskQueue>,
+    mut building_materials: Option<ResMut<BuildingMaterials>>,
+    mut completed: MessageWriter<TaskCompleted>,
+    mut failed: MessageWriter<TaskFailed>,
+    time: Res<Time>,
+) {
+    let mut deliveries = Vec::new();
+    for (worker, mut task_worker, &tile_position, mut path, mut action) in &mut workers {
+        let task_id = task_worker.task;
+        let Ok((mut task, mut status, blueprint)) = tasks.get_mut(task_id) else {
+            path.clear_target();
+            *action = PawnAction::Stand;
+            commands.entity(worker).remove::<TaskWorker>();
+            continue;
+        };
+
+        let site = match task.kind() {
+            TaskKind::Haul { destination, .. } if task_worker.carrying > 0 => destination,
+            _ => task.position(),
+        };
+        if in_range(tile_position, site) {
+            if let TaskKind::Haul {
+                destination,
+                amount,
+            } = task.kind()
+                && task_worker.carrying == 0
+            {
+                let carrying = pick_up(&storage, &mut stockpiles, site, amount);
+                if carrying == 0 {
+                    path.clear_target();
+                    *action = PawnAction::Stand;
+                    commands.entity(worker).remove::<TaskWorker>();
+                    commands.entity(task_id).despawn();
+                    failed.write(TaskFailed {
+                        task: task_id,
+                        worker,
+                    });
+                    continue;
+                }
+
+                task_worker.carrying = carrying;
+                task_worker.unreachable = 0;
+                match work_position(destination, tile_position, 0, |tile| storage.get_kind(tile)) {
+                    Some(target) => path.set_target(target),
+                    None => path.clear_target(),
+                }
+                continue;
+            }
+
+            // A wall can't be raised on top of anything solid, so the worker waits for it to move.
+            if let TaskKind::Build(material) = task.kind()
+                && material.kind() == TileKind::Wall
+                && storage
+                    .index
+                    .get_objects(site)
+                    .iter()
+                    .any(|&object| colliders.get(object).is_ok_and(|collider| collider.solid()))
+            {
+                path.clear_target();
+                *action = PawnAction::Stand;
+                continue;
+            }
+
+            if let Some(mut blueprint) = blueprint
+                && !blueprint.is_paid()
+                && !blueprint.pay(building_materials.as_deref_mut())
+            {
+                *status = TaskStatus::Queued;
+                queue.requeue(task_id, &task);
+
+                path.clear_target();
+                *action = PawnAction::Stand;
+                commands.entity(worker).remove::<TaskWorker>();
+                continue;
+            }
+
+            task.progress += time.delta();
+            if task.progress < task.work {
+                path.clear_target();
+                *action = PawnAction::Stand;
+                continue;
+            }
+
+            if let TaskKind::Haul { destination, .. } = task.kind() {
+                deliveries.push((destination, task_worker.carrying));
+            }
+            task.apply(&mut commands, &mut storage);
+
+            path.clear_target();
+            *action = PawnAction::Stand;
+            commands.entity(worker).remove::<TaskWorker>();
+            commands.entity(task_id).despawn();
+            completed.write(TaskCompleted {
+                task: task_id,
+                worker,
+            });
+        } else {
+            let sides = work_sides(site);
+            let side = path
+                .target()
+                .and_then(|target| sides.iter().position(|&tile| tile == target));
+            if let Some(side) = side
+                && path.is_failed()
+            {
+                task_worker.unreachable |= 1 << side;
+            } else if let Some(side) = side
+                && is_open(storage.get_kind(sides[side]))
+            {
+                continue;
+            }
+
+            let Some(target) =
+                work_position(site, tile_position, task_worker.unreachable, |tile| {
+                    storage.get_kind(tile)
+                })
+            else {
+                // Failed tasks are dropped rather than left queued, so their tile can be planned
+                // again.
+                path.clear_target();
+                *action = PawnAction::Stand;
+                commands.entity(worker).remove::<TaskWorker>();
+                commands.entity(task_id).despawn();
+                failed.write(TaskFailed {
+                    task: task_id,
+                    worker,
+                });
+                continue;
+            };
+            path.set_target(target);
+        }
+    }
+
+    for (destination, amount) in deliveries {
+        deliver(
+            &mut commands,
+            &storage,
+            &mut tasks,
+            &mut stockpiles,
+            destination,
+            amount,
+        );
+    }
+}
+
+fn pick_up(
+    storage: &TileStorageMut,
+    stockpiles: &mut Query<&mut Stockpile>,
+    source: TilePosition,
+    amount: u32,
+) -> u32 {
+    let mut carrying = 0;
+    for &object in storage.index.get_objects(source) {
+        if let Ok(mut stockpile) = stockpiles.get_mut(object) {
+            carrying += stockpile.take(amount - carrying);
+        }
+    }
+    carrying
+}
+
+// Materials go to a blueprint or stockpile at the destination, or start a new stockpile there.
+fn deliver(
+    commands: &mut Commands,
+    storage: &TileStorageMut,
+    tasks: &mut Query<(&mut Task, &mut TaskStatus, Option<&mut Blueprint>)>,
+    stockpiles: &mut Query<&mut Stockpile>,
+    destination: TilePosition,
+    amount: u32,
+) {
+    for &object in storage.index.get_objects(destination) {
+        if let Ok((_, _, Some(mut blueprint))) = tasks.get_mut(object) {
+            blueprint.deliver(amount);
+            return;
+        }
+        if let Ok(mut stockpile) = stockpiles.get_mut(object) {
+            stockpile.amount += amount;
+            return;
+        }
+    }
+
+    commands.spawn((Stockpile::new(amount), destination));
+}
+
+// Workers stand beside the site rather than on it or diagonal to it, so they never work through
+// a wall corner. The nearest open side they haven't failed to reach is tried first.
+fn work_position(
+    site: TilePosition,
+    from: TilePosition,
+    unreachable: u8,
+    kind: impl Fn(TilePosition) -> TileKind,
+) -> Option<TilePosition> {
+    work_sides(site)
+        .into_iter()
+        .enumerate()
+        .filter(|&(side, tile)| unreachable & (1 << side) == 0 && is_open(kind(tile)))
+        .map(|(_, tile)| tile)
+        .min_by_key(|tile| (tile.position() - from.position()).length_squared())
+}
+
+fn work_sides(site: TilePosition) -> [TilePosition; 4] {
+    [site.north(), site.east(), site.south(), site.west()]
+}
+
+fn is_open(kind: TileKind) -> bool {
+    matches!(kind, TileKind::Empty | TileKind::Door)
+}
+
+fn in_range(position: TilePosition, site: TilePosition) -> bool {
+    position.layer() == site.layer() && work_sides(site).contains(&position)
+}
+
+impl Task {
+    pub fn new(kind: TaskKind, position: TilePosition) -> Self {
+        Task {
+            kind,
+            position,
+            priority: 0,
+            work: Duration::ZERO,
+            progress: Duration::ZERO,
+            sequence: 0,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_work(mut self, work: Duration) -> Self {
+        self.work = work;
+        self
+    }
+
+    pub fn kind(&self) -> TaskKind {
+        self.kind
+    }
+
+    pub fn position(&self) -> TilePosition {
+        self.position
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn work(&self) -> Duration {
+        self.work
+    }
+
+    pub fn worked(&self) -> Duration {
+        self.progress
+    }
+
+    pub fn with_worked(mut self, worked: Duration) -> Self {
+        self.progress = worked;
+        self
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.work.is_zero() {
+            return 1.0;
+        }
+
+        (self.progress.as_secs_f32() / self.work.as_secs_f32()).min(1.0)
+    }
+
+    fn apply(&self, commands: &mut Commands, storage: &mut TileStorageMut) {
+        match self.kind {
+            TaskKind::Build(material) => {
+                storage.set_material(self.position, material);
+            }
+            TaskKind::PlaceDoor => {
+                storage.set_material(self.position, TileMaterial::DOOR);
+                commands.spawn((Door::default(), self.position));
+            }
+            TaskKind::Remove => {
+                if storage.get_kind(self.position) == TileKind::Door
+                    && let Some(door_id) = storage.index.get_tile(self.position)
+                {
+                    commands.entity(door_id).despawn();
+                }
+                storage.set_material(self.position, TileMaterial::EMPTY);
+            }
+            TaskKind::Haul { .. } => {}
+        }
+    }
+}
+
+impl TaskWorker {
+    pub fn new(task: Entity, carrying: u32) -> Self {
+        TaskWorker {
+            task,
+            carrying,
+            unreachable: 0,
+        }
+    }
+
+    pub fn with_unreachable(mut self, unreachable: u8) -> Self {
+        self.unreachable = unreachable;
+        self
+    }
+
+    pub fn task(&self) -> Entity {
+        self.task
+    }
+
+    pub fn carrying(&self) -> u32 {
+        self.carrying
+    }
+
+    pub fn unreachable(&self) -> u8 {
+        self.unreachable
+    }
+}
+
+impl TaskQueue {
+    pub fn push(&mut self, task: Entity, priority: i32) -> u64 {
+        let sequence = self.next_sequence;
+        self.tasks.push(QueuedTask {
+            priority,
+            sequence,
+            task,
+        });
+        self.next_sequence += 1;
+        sequence
+    }
+
+    // Puts a task back in the place it was first queued in, ahead of anything queued since.
+    pub fn requeue(&mut self, id: Entity, task: &Task) {
+        self.tasks.push(QueuedTask {
+            priority: task.priority,
+            sequence: task.sequence,
+            task: id,
+        });
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        self.tasks.retain(|queued| keep(queued.task));
+    }
+
+    pub fn pop(&mut self) -> Option<Entity> {
+        self.tasks.pop().map(|queued| queued.task)
+    }
+
+    // Queued tasks in the order they will be popped.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> {
+        let mut tasks = self.tasks.clone().into_sorted_vec();
+        tasks.reverse();
+        tasks.into_iter().map(|queued| queued.task)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,677 @@
+use std::time::Duration;
+
+use bevy_app::{TaskPoolPlugin, prelude::*};
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
+use wdn_physics::{
+    PhysicsPlugin,
+    kinematics::Position,
+    layer::Layer,
+    tile::{
+        material::{TileKind, TileMaterial},
+        position::TilePosition,
+        storage::{TileStorage, TileStorageMut},
+    },
+};
+use wdn_world::{
+    WorldPlugin,
+    door::Door,
+    pawn::{Pawn, action::PawnAction, path::PawnPath},
+};
+
+use crate::{
+    TasksPlugin,
+    blueprint::{Blueprint, BlueprintCost, BuildingMaterials, Stockpile},
+    task::{NoTasks, Task, TaskFailed, TaskKind, TaskQueue, TaskStatus, TaskWorker},
+};
+
+#[test]
+fn task_queue_priority() {
+    let mut queue = TaskQueue::default();
+    let low = Entity::from_raw_u32(1).unwrap();
+    let high = Entity::from_raw_u32(2).unwrap();
+    let first = Entity::from_raw_u32(3).unwrap();
+    let second = Entity::from_raw_u32(4).unwrap();
+
+    queue.push(low, -1);
+    queue.push(first, 0);
+    queue.push(high, 5);
+    queue.push(second, 0);
+
+    assert_eq!(queue.len(), 4);
+    assert_eq!(queue.pop(), Some(high));
+    assert_eq!(queue.pop(), Some(first));
+    assert_eq!(queue.pop(), Some(second));
+    assert_eq!(queue.pop(), Some(low));
+    assert_eq!(queue.pop(), None);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn build_wall_task() {
+    let (mut app, layer) = make_app();
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 3, 0);
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Build(TileMaterial::WALL), site))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    assert_eq!(app.world().get::<PawnPath>(pawn).unwrap().target(), None);
+    assert!(matches!(
+        app.world().get::<PawnAction>(pawn),
+        Some(PawnAction::Stand)
+    ));
+}
+
+#[test]
+fn build_from_cardinal_side() {
+    let (mut app, layer) = make_app();
+    let site = TilePosition::new(layer, 2, 2);
+    set_material(&mut app, site.west(), TileMaterial::WALL);
+    set_material(&mut app, site.south(), TileMaterial::WALL);
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(1.5, 1.5));
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Build(TileMaterial::WALL), site))
+        .id();
+
+    app.update();
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+    let position = *app.world().get::<TilePosition>(pawn).unwrap();
+    assert!(position == site.north() || position == site.east());
+}
+
+#[test]
+fn build_wall_waits_for_collider() {
+    let (mut app, layer) = make_app();
+    let site = TilePosition::new(layer, 3, 0);
+    let blocker = app
+        .world_mut()
+        .spawn((
+            NoTasks,
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(Vec2::new(3.5, 0.5), Rot2::IDENTITY),
+        ))
+        .id();
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Build(TileMaterial::WALL), site))
+        .id();
+
+    for _ in 0..200 {
+        app.update();
+    }
+    assert!(app.world().get_entity(task).is_ok());
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+
+    *app.world_mut().get_mut::<Position>(blocker).unwrap() =
+        Position::new(Vec2::new(3.5, 6.5), Rot2::IDENTITY);
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+}
+
+#[test]
+fn place_door_task() {
+    let (mut app, layer) = make_app();
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 0, 3);
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::PlaceDoor, site))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Door);
+    let doors: Vec<TilePosition> = app
+        .world_mut()
+        .query_filtered::<&TilePosition, With<Door>>()
+        .iter(app.world())
+        .copied()
+        .collect();
+    assert_eq!(doors, vec![site]);
+}
+
+#[test]
+fn remove_task() {
+    let (mut app, layer) = make_app();
+    let site = TilePosition::new(layer, 2, 2);
+    set_material(&mut app, site, TileMaterial::WALL);
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Remove, site))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+}
+
+#[test]
+fn haul_task_supplies_blueprint() {
+    let (mut app, layer) = make_app();
+    app.insert_resource(BuildingMaterials { amount: 0 });
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let source = TilePosition::new(layer, 4, 0);
+    let site = TilePosition::new(layer, -4, 0);
+    app.world_mut().spawn((Stockpile::new(3), source));
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            site,
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 2,
+                work: Duration::ZERO,
+            },
+        ))
+        .id();
+    let haul = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Haul {
+                destination: site,
+                amount: 2,
+            },
+            source,
+        ))
+        .id();
+
+    run_until(&mut app, |world| {
+        world
+            .get::<TaskWorker>(pawn)
+            .is_some_and(|worker| worker.carrying() > 0)
+    });
+    assert_eq!(app.world().get::<TaskWorker>(pawn).unwrap().carrying(), 2);
+    assert_eq!(stockpile_amount(&mut app, source), 1);
+    assert_eq!(
+        app.world().get::<PawnPath>(pawn).unwrap().target(),
+        Some(site.east())
+    );
+
+    run_until(&mut app, |world| world.get_entity(haul).is_err());
+    assert_eq!(
+        app.world().get::<Blueprint>(blueprint).unwrap().delivered(),
+        2
+    );
+
+    run_until(&mut app, |world| world.get_entity(blueprint).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+    assert_eq!(app.world().resource::<BuildingMaterials>().amount, 0);
+    assert_eq!(stockpile_amount(&mut app, source), 1);
+}
+
+#[test]
+fn haul_task_fills_stockpile() {
+    let (mut app, layer) = make_app();
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let source = TilePosition::new(layer, 3, 3);
+    let destination = TilePosition::new(layer, -3, 0);
+    app.world_mut().spawn((Stockpile::new(2), source));
+    let haul = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Haul {
+                destination,
+                amount: 5,
+            },
+            source,
+        ))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(haul).is_err());
+
+    assert_eq!(stockpile_amount(&mut app, source), 0);
+    assert_eq!(stockpile_amount(&mut app, destination), 2);
+}
+
+#[test]
+fn haul_task_fails_without_materials() {
+    let (mut app, layer) = make_app();
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let source = TilePosition::new(layer, 3, 0);
+    let haul = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Haul {
+                destination: TilePosition::new(layer, -3, 0),
+                amount: 1,
+            },
+            source,
+        ))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(haul).is_err());
+
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    assert_eq!(app.world().get::<PawnPath>(pawn).unwrap().target(), None);
+}
+
+#[test]
+fn cancel_haul_drops_materials() {
+    let (mut app, layer) = make_app();
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let source = TilePosition::new(layer, 2, 0);
+    app.world_mut().spawn((Stockpile::new(4), source));
+    let haul = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Haul {
+                destination: TilePosition::new(layer, -8, 0),
+                amount: 3,
+            },
+            source,
+        ))
+        .id();
+
+    run_until(&mut app, |world| {
+        world
+            .get::<TaskWorker>(pawn)
+            .is_some_and(|worker| worker.carrying() > 0)
+    });
+    app.world_mut().entity_mut(haul).despawn();
+    app.update();
+
+    let position = *app.world().get::<TilePosition>(pawn).unwrap();
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    assert_eq!(stockpile_amount(&mut app, source), 1);
+    assert_eq!(stockpile_amount(&mut app, position), 3);
+}
+
+#[test]
+fn assign_nearest_pawn_by_priority() {
+    let (mut app, layer) = make_app();
+    let near = spawn_pawn(&mut app, layer, Vec2::new(5.5, 0.5));
+    let far = spawn_pawn(&mut app, layer, Vec2::new(-5.5, 0.5));
+    let low = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Remove, TilePosition::new(layer, 8, 8)))
+        .id();
+    let high = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Remove, TilePosition::new(layer, 6, 0)).with_priority(1))
+        .id();
+
+    app.update();
+
+    assert_eq!(
+        *app.world().get::<TaskStatus>(high).unwrap(),
+        TaskStatus::Assigned { worker: near }
+    );
+    assert_eq!(
+        *app.world().get::<TaskStatus>(low).unwrap(),
+        TaskStatus::Assigned { worker: far }
+    );
+    assert_eq!(app.world().get::<TaskWorker>(near).unwrap().task(), high);
+    assert_eq!(app.world().get::<TaskWorker>(far).unwrap().task(), low);
+}
+
+#[test]
+fn cancel_task() {
+    let (mut app, layer) = make_app();
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 10, 0);
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Build(TileMaterial::WALL), site))
+        .id();
+
+    app.update();
+    assert!(app.world().get::<TaskWorker>(pawn).is_some());
+    assert_eq!(
+        app.world().get::<PawnPath>(pawn).unwrap().target(),
+        Some(site.west())
+    );
+
+    app.world_mut().despawn(task);
+    app.update();
+
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    assert_eq!(app.world().get::<PawnPath>(pawn).unwrap().target(), None);
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+}
+
+#[test]
+fn unreachable_task_fails() {
+    let (mut app, layer) = make_app();
+    for x in 4..=8 {
+        for y in 4..=8 {
+            if x == 4 || x == 8 || y == 4 || y == 8 {
+                set_material(&mut app, TilePosition::new(layer, x, y), TileMaterial::WALL);
+            }
+        }
+    }
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let task = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Build(TileMaterial::WALL),
+            TilePosition::new(layer, 6, 6),
+        ))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    let failed = app
+        .world_mut()
+        .run_system_once(|mut reader: MessageReader<TaskFailed>| {
+            reader
+                .read()
+                .map(|failed| (failed.task, failed.worker))
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    assert_eq!(failed, vec![(task, pawn)]);
+    assert_eq!(
+        get_kind(&mut app, TilePosition::new(layer, 6, 6)),
+        TileKind::Empty
+    );
+}
+
+#[test]
+fn no_tasks_pawn_ignored() {
+    let (mut app, layer) = make_app();
+    let pawn = app
+        .world_mut()
+        .spawn((
+            NoTasks,
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(Vec2::new(0.5, 0.5), Rot2::IDENTITY),
+        ))
+        .id();
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Remove, TilePosition::new(layer, 2, 0)))
+        .id();
+
+    app.update();
+
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+    assert_eq!(
+        *app.world().get::<TaskStatus>(task).unwrap(),
+        TaskStatus::Queued
+    );
+}
+
+#[test]
+fn blueprint_build_progress() {
+    let (mut app, layer) = make_app();
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 2, 0);
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            site,
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 0,
+                work: Duration::from_secs(1),
+            },
+        ))
+        .id();
+
+    app.update();
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+
+    run_until(&mut app, |world| {
+        world
+            .get::<Task>(blueprint)
+            .is_some_and(|task| task.progress() > 0.0)
+    });
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+    assert!(app.world().get::<Task>(blueprint).unwrap().progress() < 1.0);
+
+    run_until(&mut app, |world| world.get_entity(blueprint).is_err());
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+}
+
+#[test]
+fn blueprint_door() {
+    let (mut app, layer) = make_app();
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 2, 0);
+    set_material(&mut app, site, TileMaterial::WALL);
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            site,
+            TileMaterial::DOOR,
+            BlueprintCost {
+                materials: 0,
+                work: Duration::from_millis(200),
+            },
+        ))
+        .id();
+
+    run_until(&mut app, |world| world.get_entity(blueprint).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Door);
+    let doors: Vec<TilePosition> = app
+        .world_mut()
+        .query_filtered::<&TilePosition, With<Door>>()
+        .iter(app.world())
+        .copied()
+        .collect();
+    assert_eq!(doors, vec![site]);
+}
+
+#[test]
+fn blueprint_material_cost() {
+    let (mut app, layer) = make_app();
+    app.insert_resource(BuildingMaterials { amount: 1 });
+    spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 2, 0);
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            site,
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 2,
+                work: Duration::ZERO,
+            },
+        ))
+        .id();
+
+    for _ in 0..200 {
+        app.update();
+    }
+    assert!(!app.world().get::<Blueprint>(blueprint).unwrap().is_paid());
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+
+    app.world_mut().resource_mut::<BuildingMaterials>().amount = 5;
+    run_until(&mut app, |world| world.get_entity(blueprint).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+    assert_eq!(app.world().resource::<BuildingMaterials>().amount, 3);
+}
+
+#[test]
+fn blueprint_insufficient_materials() {
+    let (mut app, layer) = make_app();
+    app.insert_resource(BuildingMaterials { amount: 2 });
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let site = TilePosition::new(layer, 6, 0);
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            site,
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 2,
+                work: Duration::ZERO,
+            },
+        ))
+        .id();
+
+    run_until(&mut app, |world| world.get::<TaskWorker>(pawn).is_some());
+    app.world_mut().resource_mut::<BuildingMaterials>().amount = 0;
+    run_until(&mut app, |world| world.get::<TaskWorker>(pawn).is_none());
+
+    assert!(!app.world().get::<Blueprint>(blueprint).unwrap().is_paid());
+    assert_eq!(
+        *app.world().get::<TaskStatus>(blueprint).unwrap(),
+        TaskStatus::Queued
+    );
+    assert_eq!(app.world().resource::<TaskQueue>().len(), 1);
+
+    let other_site = TilePosition::new(layer, 0, 3);
+    let task = app
+        .world_mut()
+        .spawn(Task::new(TaskKind::Build(TileMaterial::WALL), other_site))
+        .id();
+    run_until(&mut app, |world| world.get_entity(task).is_err());
+
+    assert_eq!(get_kind(&mut app, other_site), TileKind::Wall);
+    assert_eq!(get_kind(&mut app, site), TileKind::Empty);
+    assert!(app.world().get::<TaskWorker>(pawn).is_none());
+
+    app.world_mut().resource_mut::<BuildingMaterials>().amount = 2;
+    run_until(&mut app, |world| world.get_entity(blueprint).is_err());
+
+    assert_eq!(get_kind(&mut app, site), TileKind::Wall);
+    assert_eq!(app.world().resource::<BuildingMaterials>().amount, 0);
+}
+
+#[test]
+fn blueprint_requeue_keeps_order() {
+    let (mut app, layer) = make_app();
+    app.insert_resource(BuildingMaterials { amount: 2 });
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let blueprint = app
+        .world_mut()
+        .spawn(Blueprint::bundle(
+            TilePosition::new(layer, 4, 0),
+            TileMaterial::WALL,
+            BlueprintCost {
+                materials: 2,
+                work: Duration::ZERO,
+            },
+        ))
+        .id();
+    let first = app
+        .world_mut()
+        .spawn(Task::new(
+            TaskKind::Build(TileMaterial::WALL),
+            TilePosition::new(layer, 0, 4),
+        ))
+        .id();
+    app.world_mut().spawn(Task::new(
+        TaskKind::Build(TileMaterial::WALL),
+        TilePosition::new(layer, -4, 0),
+    ));
+
+    let working_on = |task: Entity| {
+        move |world: &World| {
+            world
+                .get::<TaskWorker>(pawn)
+                .is_some_and(|worker| worker.task() == task)
+        }
+    };
+    run_until(&mut app, working_on(blueprint));
+    app.world_mut().resource_mut::<BuildingMaterials>().amount = 0;
+    run_until(&mut app, working_on(first));
+    app.world_mut().resource_mut::<BuildingMaterials>().amount = 2;
+
+    // The blueprint was queued before the last task, so it comes first once it's affordable.
+    run_until(&mut app, |world| world.get_entity(first).is_err());
+    run_until(&mut app, |world| world.get::<TaskWorker>(pawn).is_some());
+    assert_eq!(
+        app.world().get::<TaskWorker>(pawn).unwrap().task(),
+        blueprint
+    );
+}
+
+fn make_app() -> (App, Entity) {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        PhysicsPlugin,
+        WorldPlugin,
+        TasksPlugin,
+    ));
+
+    let timestep = Duration::from_millis(50);
+    app.insert_resource(Time::<Fixed>::from_duration(timestep));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    for x in [-1, 0] {
+        for y in [-1, 0] {
+            set_material(
+                &mut app,
+                TilePosition::new(layer, x * 32, y * 32),
+                TileMaterial::EMPTY,
+            );
+        }
+    }
+
+    (app, layer)
+}
+
+fn spawn_pawn(app: &mut App, layer: Entity, position: Vec2) -> Entity {
+    app.world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(position, Rot2::IDENTITY),
+        ))
+        .id()
+}
+
+fn set_material(app: &mut App, position: TilePosition, material: TileMaterial) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, material);
+        })
+        .unwrap();
+}
+
+fn get_kind(app: &mut App, position: TilePosition) -> TileKind {
+    app.world_mut()
+        .run_system_once(move |storage: TileStorage| storage.get_kind(position))
+        .unwrap()
+}
+
+fn stockpile_amount(app: &mut App, position: TilePosition) -> u32 {
+    app.world_mut()
+        .query::<(&Stockpile, &TilePosition)>()
+        .iter(app.world())
+        .filter(|&(_, &tile_position)| tile_position == position)
+        .map(|(stockpile, _)| stockpile.amount)
+        .sum()
+}
+
+fn run_until(app: &mut App, mut condition: impl FnMut(&World) -> bool) {
+    for _ in 0..1000 {
+        app.update();
+        if condition(app.world()) {
+            return;
+        }
+    }
+
+    panic!("condition not reached");
+}
@@ -0,0 +1,326 @@
+use bevy_camera::Camera;
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_gizmos::prelude::*;
+use bevy_input::prelude::*;
+use bevy_math::prelude::*;
+use bevy_transform::components::GlobalTransform;
+use bevy_ui::Interaction;
+use bevy_window::Window;
+use wdn_physics::{
+    layer::Layer,
+    tile::{
+        material::{TileKind, TileMaterial},
+        position::TilePosition,
+        storage::TileStorage,
+    },
+};
+use wdn_render::layer::LayerView;
+use wdn_tasks::{blueprint::Blueprint, task::Task};
+use wdn_world::command::PlayerCommand;
+
+#[derive(Resource, Debug)]
+pub struct BuildTool {
+    pub tool: Tool,
+    pub shape: ToolShape,
+    pub plan: bool,
+    drag_start: Option<TilePosition>,
+    hover: Option<TilePosition>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    Inspect,
+    #[default]
+    Wall,
+    Door,
+    SlowFloor,
+    FastFloor,
+    Stairs,
+    Erase,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolShape {
+    #[default]
+    Rectangle,
+    Line,
+}
+
+#[expect(clippy::too_many_arguments)]
+pub fn handle_build_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    layer_view: Option<Res<LayerView>>,
+    layers: Query<(Entity, &Layer, &ChildOf)>,
+    interactions: Query<&Interaction>,
+    mut build_tool: ResMut<BuildTool>,
+    mut player_commands: MessageWriter<PlayerCommand>,
+    storage: TileStorage,
+) {
+    let (camera, camera_transform) = camera.into_inner();
+
+    let layer = layer_view.and_then(|view| {
+        layers
+            .iter()
+            .find(|(_, layer, parent)| {
+                parent.parent() == view.stack && layer.height() == view.height
+            })
+            .map(|(id, ..)| id)
+    });
+
+    let hover = match (layer, window.cursor_position()) {
+        (Some(layer), Some(cursor_pos)) => camera
+            .viewport_to_world_2d(camera_transform, cursor_pos)
+            .ok()
+            .map(|world_pos| TilePosition::floor(layer, world_pos)),
+        _ => None,
+    };
+    build_tool.hover = hover;
+
+    let over_ui = interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+
+    if mouse.just_pressed(MouseButton::Left) && !over_ui && build_tool.tool != Tool::Inspect {
+        build_tool.drag_start = hover;
+    }
+
+    if mouse.just_released(MouseButton::Left)
+        && let Some(start) = build_tool.drag_start.take()
+        && let Some(end) = hover
+        && start.layer() == end.layer()
+    {
+        for position in shape_tiles(build_tool.shape, start.position(), end.position()) {
+            let position = TilePosition::from_vec(start.layer(), position);
+            if build_tool.plan
+                && matches!(build_tool.tool, Tool::Wall | Tool::Door)
+                && let Some(material) = build_tool.tool.material()
+            {
+                player_commands.write(PlayerCommand::PlaceBlueprint { position, material });
+            } else {
+                apply_tool(&mut player_commands, &storage, build_tool.tool, position);
+            }
+        }
+    }
+}
+
+pub fn draw_build_preview(
+    mut gizmos: Gizmos,
+    build_tool: Res<BuildTool>,
+    blueprints: Query<(&Task, &TilePosition), With<Blueprint>>,
+) {
+    blueprints.iter().for_each(|(task, position)| {
+        let center = position.center_position();
+        gizmos.rect_2d(center, Vec2::splat(0.9), Color::srgba(0.4, 0.7, 1.0, 0.6));
+        if task.progress() > 0.0 {
+            gizmos.rect_2d(
+                center,
+                Vec2::splat(0.9 * task.progress()),
+                Color::srgba(0.4, 0.7, 1.0, 0.9),
+            );
+        }
+    });
+
+    let Some(hover) = build_tool.hover else {
+        return;
+    };
+
+    let color = build_tool.tool.preview_color();
+    match build_tool.drag_start {
+        Some(start) if start.layer() == hover.layer() => {
+            for position in shape_tiles(build_tool.shape, start.position(), hover.position()) {
+                gizmos.rect_2d(position.as_vec2() + 0.5, Vec2::splat(0.95), color);
+            }
+        }
+        _ => {
+            gizmos.rect_2d(hover.center_position(), Vec2::splat(0.95), color);
+        }
+    }
+}
+
+pub fn shape_tiles(shape: ToolShape, start: IVec2, end: IVec2) -> Vec<IVec2> {
+    match shape {
+        ToolShape::Rectangle => {
+            let min = start.min(end);
+            let max = start.max(end);
+            (min.y..=max.y)
+                .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+                .collect()
+        }
+        ToolShape::Line => {
+            let delta = (end - start).abs();
+            let step = (end - start).signum();
+            let mut error = delta.x - delta.y;
+            let mut position = start;
+            let mut tiles = vec![position];
+
+            while position != end {
+                let error2 = error * 2;
+                if error2 > -delta.y {
+                    error -= delta.y;
+                    position.x += step.x;
+                }
+                if error2 < delta.x {
+                    error += delta.x;
+                    position.y += step.y;
+                }
+                tiles.push(position);
+            }
+
+            tiles
+        }
+    }
+}
+
+pub fn apply_tool(
+    player_commands: &mut MessageWriter<PlayerCommand>,
+    storage: &TileStorage,
+    tool: Tool,
+    position: TilePosition,
+) {
+    if tool == Tool::Erase {
+        player_commands.write(PlayerCommand::RemoveBlueprint { position });
+    }
+
+    let Some(material) = tool.material() else {
+        return;
+    };
+    let current = storage
+        .get(position)
+        .map_or(TileMaterial::EMPTY, |tile| tile.material());
+    if current == material {
+        return;
+    }
+
+    if current.kind() == TileKind::Door {
+        player_commands.write(PlayerCommand::DespawnDoor { position });
+    }
+
+    player_commands.write(PlayerCommand::SetMaterial { position, material });
+    if tool == Tool::Door {
+        player_commands.write(PlayerCommand::SpawnDoor { position });
+    }
+}
+
+impl Default for BuildTool {
+    fn default() -> Self {
+        BuildTool {
+            tool: Tool::default(),
+            shape: ToolShape::default(),
+            plan: true,
+            drag_start: None,
+            hover: None,
+        }
+    }
+}
+
+impl BuildTool {
+    pub fn hover(&self) -> Option<TilePosition> {
+        self.hover
+    }
+}
+
+impl Tool {
+    pub const ALL: [Tool; 7] = [
+        Tool::Inspect,
+        Tool::Wall,
+        Tool::Door,
+        Tool::SlowFloor,
+        Tool::FastFloor,
+        Tool::Stairs,
+        Tool::Erase,
+    ];
+
+    pub fn material(&self) -> Option<TileMaterial> {
+        match self {
+            Tool::Inspect => None,
+            Tool::Wall => Some(TileMaterial::WALL),
+            Tool::Door => Some(TileMaterial::DOOR),
+            Tool::SlowFloor => Some(TileMaterial::SLOW),
+            Tool::FastFloor => Some(TileMaterial::FAST),
+            Tool::Stairs => Some(TileMaterial::STAIRS),
+            Tool::Erase => Some(TileMaterial::EMPTY),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::Inspect => "Inspect",
+            Tool::Wall => "Wall",
+            Tool::Door => "Door",
+            Tool::SlowFloor => "Slow",
+            Tool::FastFloor => "Fast",
+            Tool::Stairs => "Stairs",
+            Tool::Erase => "Erase",
+        }
+    }
+
+    fn preview_color(&self) -> Color {
+        match self {
+            Tool::Inspect => Color::srgba(1.0, 0.85, 0.3, 0.8),
+            Tool::Erase => Color::srgba(1.0, 0.3, 0.3, 0.8),
+            _ => Color::srgba(0.9, 0.9, 0.9, 0.8),
+        }
+    }
+}
+
+impl ToolShape {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ToolShape::Rectangle => "Rect",
+            ToolShape::Line => "Line",
+        }
+    }
+}
+
+#[test]
+fn test_shape_tiles_rectangle() {
+    let tiles = shape_tiles(ToolShape::Rectangle, IVec2::new(1, 2), IVec2::new(-1, 3));
+    assert_eq!(
+        tiles,
+        vec![
+            IVec2::new(-1, 2),
+            IVec2::new(0, 2),
+            IVec2::new(1, 2),
+            IVec2::new(-1, 3),
+            IVec2::new(0, 3),
+            IVec2::new(1, 3),
+        ]
+    );
+
+    let tiles = shape_tiles(ToolShape::Rectangle, IVec2::new(4, 4), IVec2::new(4, 4));
+    assert_eq!(tiles, vec![IVec2::new(4, 4)]);
+}
+
+#[test]
+fn test_shape_tiles_line() {
+    let tiles = shape_tiles(ToolShape::Line, IVec2::new(0, 0), IVec2::new(4, 0));
+    assert_eq!(
+        tiles,
+        vec![
+            IVec2::new(0, 0),
+            IVec2::new(1, 0),
+            IVec2::new(2, 0),
+            IVec2::new(3, 0),
+            IVec2::new(4, 0),
+        ]
+    );
+
+    let tiles = shape_tiles(ToolShape::Line, IVec2::new(0, 0), IVec2::new(-3, -3));
+    assert_eq!(
+        tiles,
+        vec![
+            IVec2::new(0, 0),
+            IVec2::new(-1, -1),
+            IVec2::new(-2, -2),
+            IVec2::new(-3, -3),
+        ]
+    );
+
+    let tiles = shape_tiles(ToolShape::Line, IVec2::new(0, 0), IVec2::new(4, 2));
+    assert_eq!(tiles.len(), 5);
+    assert_eq!(tiles.first(), Some(&IVec2::new(0, 0)));
+    assert_eq!(tiles.last(), Some(&IVec2::new(4, 2)));
+}
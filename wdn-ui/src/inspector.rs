@@ -0,0 +1,203 @@
+use std::fmt::Write;
+
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_input::prelude::*;
+use bevy_text::{FontSize, TextFont};
+use bevy_ui::prelude::*;
+use wdn_physics::tile::{index::TileIndex, position::TilePosition, storage::TileMap};
+use wdn_world::{
+    combat::Health,
+    door::Door,
+    path::{
+        door::DoorRegions,
+        region::{Region, RegionTiles},
+        section::TileChunkSections,
+    },
+    pawn::{
+        Pawn,
+        action::PawnAction,
+        path::{PathState, PawnPath},
+    },
+};
+
+use crate::build::{BuildTool, Tool};
+
+#[derive(Resource, Debug, Default)]
+pub struct Inspector {
+    pub selected: Option<Inspected>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Inspected {
+    Pawn(Entity),
+    Door(Entity),
+    Region(TilePosition),
+}
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InspectorPanel;
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InspectorText;
+
+pub fn spawn_inspector(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Inspector"),
+        InspectorPanel,
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            right: Val::Px(8.0),
+            top: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.12, 0.85)),
+        children![(
+            InspectorText,
+            Text::default(),
+            TextFont {
+                font_size: FontSize::Px(14.0),
+                ..Default::default()
+            },
+        )],
+    ));
+}
+
+pub fn select_inspected(
+    mouse: Res<ButtonInput<MouseButton>>,
+    build_tool: Res<BuildTool>,
+    interactions: Query<&Interaction>,
+    index: Res<TileIndex>,
+    pawns: Query<(), With<Pawn>>,
+    doors: Query<(), With<Door>>,
+    mut inspector: ResMut<Inspector>,
+) {
+    if build_tool.tool != Tool::Inspect || !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    if interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    inspector.selected = build_tool.hover().map(|position| {
+        if let Some(&pawn) = index
+            .get_objects(position)
+            .iter()
+            .find(|&&id| pawns.contains(id))
+        {
+            Inspected::Pawn(pawn)
+        } else if let Some(door) = index.get_tile(position).filter(|&id| doors.contains(id)) {
+            Inspected::Door(door)
+        } else {
+            Inspected::Region(position)
+        }
+    });
+}
+
+#[expect(clippy::too_many_arguments)]
+pub fn update_inspector(
+    inspector: Res<Inspector>,
+    panel: Single<&mut Node, With<InspectorPanel>>,
+    text: Single<&mut Text, With<InspectorText>>,
+    pawns: Query<(&Health, &PawnAction, &PawnPath)>,
+    doors: Query<(&Door, &DoorRegions)>,
+    regions: Query<(&Region, &RegionTiles)>,
+    map: Res<TileMap>,
+    chunks: Query<&TileChunkSections>,
+) {
+    let description = match inspector.selected {
+        Some(Inspected::Pawn(id)) => pawns
+            .get(id)
+            .ok()
+            .map(|(health, action, path)| describe_pawn(id, health, action, path)),
+        Some(Inspected::Door(id)) => doors
+            .get(id)
+            .ok()
+            .map(|(door, door_regions)| describe_door(id, door, door_regions)),
+        Some(Inspected::Region(position)) => Some(
+            map.get(position.chunk_position())
+                .and_then(|chunk_id| chunks.get(chunk_id).ok())
+                .and_then(|sections| sections.region(position.chunk_offset()))
+                .and_then(|id| Some((id, regions.get(id).ok()?)))
+                .map_or_else(
+                    || format!("No region at ({}, {})", position.x(), position.y()),
+                    |(id, (region, tiles))| describe_region(id, region, tiles),
+                ),
+        ),
+        None => None,
+    };
+
+    let mut panel = panel.into_inner();
+    let mut text = text.into_inner();
+    match description {
+        Some(description) => {
+            if panel.display == Display::None {
+                panel.display = Display::Flex;
+            }
+            if text.0 != description {
+                text.0 = description;
+            }
+        }
+        None => {
+            if panel.display != Display::None {
+                panel.display = Display::None;
+            }
+        }
+    }
+}
+
+fn describe_pawn(id: Entity, health: &Health, action: &PawnAction, path: &PawnPath) -> String {
+    let mut text = format!("Pawn {id}\n");
+    writeln!(text, "Health: {}/{}", health.current, health.max).unwrap();
+    writeln!(text, "Action: {action:?}").unwrap();
+    match path.target() {
+        Some(target) => writeln!(text, "Target: ({}, {})", target.x(), target.y()).unwrap(),
+        None => writeln!(text, "Target: none").unwrap(),
+    }
+    let state = match path.state() {
+        PathState::Pending => "pending",
+        PathState::Searching(_) => "searching",
+        PathState::Active(_) => "active",
+        PathState::Finished => "finished",
+        PathState::Failed => "failed",
+    };
+    writeln!(text, "Path: {state}").unwrap();
+    match path.path() {
+        Some(path) => write!(text, "Cost: {}", path.cost()).unwrap(),
+        None => write!(text, "Cost: -").unwrap(),
+    }
+    text
+}
+
+fn describe_door(id: Entity, door: &Door, door_regions: &DoorRegions) -> String {
+    let mut text = format!("Door {id}\n");
+    write!(text, "State: {:?}", door.state()).unwrap();
+    for door_region in door_regions.iter() {
+        write!(
+            text,
+            "\nRegion {} {:?}",
+            door_region.region(),
+            door_region.adjacency()
+        )
+        .unwrap();
+        if door_region.dead_end() {
+            write!(text, " (dead end)").unwrap();
+        }
+    }
+    text
+}
+
+fn describe_region(id: Entity, region: &Region, tiles: &RegionTiles) -> String {
+    format!(
+        "Region {id}\nSize: {}\nDoors: {}\nOutside: {}",
+        tiles.len(),
+        tiles.door_count(),
+        region.outside()
+    )
+}
@@ -1,7 +1,41 @@
+pub mod build;
+pub mod inspector;
+pub mod toolbar;
+
 use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use wdn_render::RenderSystems;
+
+use crate::{
+    build::{BuildTool, draw_build_preview, handle_build_input},
+    inspector::{Inspector, select_inspected, spawn_inspector, update_inspector},
+    toolbar::{spawn_toolbar, update_toolbar},
+};
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
-    fn build(&self, _: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildTool>()
+            .init_resource::<Inspector>();
+
+        app.add_systems(Startup, (spawn_toolbar, spawn_inspector));
+        app.add_systems(
+            Update,
+            (
+                (update_toolbar, handle_build_input)
+                    .chain()
+                    .before(RenderSystems::RenderDoors)
+                    .before(RenderSystems::RenderTiles)
+                    .before(RenderSystems::RenderDev),
+                draw_build_preview
+                    .after(handle_build_input)
+                    .after(RenderSystems::RenderDev),
+                (select_inspected, update_inspector)
+                    .chain()
+                    .after(handle_build_input)
+                    .after(RenderSystems::RenderDamage),
+            ),
+        );
+    }
 }
@@ -0,0 +1,99 @@
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_text::{FontSize, TextFont};
+use bevy_ui::prelude::*;
+
+use crate::build::{BuildTool, Tool, ToolShape};
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolButton {
+    Tool(Tool),
+    Shape(ToolShape),
+    Plan,
+}
+
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.18);
+const HOVERED_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+const SELECTED_COLOR: Color = Color::srgb(0.3, 0.45, 0.7);
+
+pub fn spawn_toolbar(mut commands: Commands) {
+    let buttons = Tool::ALL.into_iter().map(ToolButton::Tool).chain([
+        ToolButton::Shape(ToolShape::Rectangle),
+        ToolButton::Shape(ToolShape::Line),
+        ToolButton::Plan,
+    ]);
+
+    commands
+        .spawn((
+            Name::new("Toolbar"),
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(8.0),
+                bottom: Val::Px(8.0),
+                column_gap: Val::Px(4.0),
+                ..Default::default()
+            },
+        ))
+        .with_children(|toolbar| {
+            for button in buttons {
+                toolbar.spawn((
+                    button,
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    BackgroundColor(BUTTON_COLOR),
+                    children![(
+                        Text::new(button.label()),
+                        TextFont {
+                            font_size: FontSize::Px(14.0),
+                            ..Default::default()
+                        },
+                    )],
+                ));
+            }
+        });
+}
+
+pub fn update_toolbar(
+    mut build_tool: ResMut<BuildTool>,
+    mut buttons: Query<(&ToolButton, &Interaction, &mut BackgroundColor)>,
+) {
+    for (button, interaction, _) in &buttons {
+        if *interaction == Interaction::Pressed {
+            match *button {
+                ToolButton::Tool(tool) => build_tool.tool = tool,
+                ToolButton::Shape(shape) => build_tool.shape = shape,
+                ToolButton::Plan => build_tool.plan = !build_tool.plan,
+            }
+        }
+    }
+
+    for (button, interaction, mut color) in &mut buttons {
+        let selected = match *button {
+            ToolButton::Tool(tool) => build_tool.tool == tool,
+            ToolButton::Shape(shape) => build_tool.shape == shape,
+            ToolButton::Plan => build_tool.plan,
+        };
+
+        let new_color = if selected {
+            SELECTED_COLOR
+        } else if *interaction == Interaction::Hovered {
+            HOVERED_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        color.set_if_neq(BackgroundColor(new_color));
+    }
+}
+
+impl ToolButton {
+    fn label(&self) -> &'static str {
+        match self {
+            ToolButton::Tool(tool) => tool.label(),
+            ToolButton::Shape(shape) => shape.label(),
+            ToolButton::Plan => "Plan",
+        }
+    }
+}
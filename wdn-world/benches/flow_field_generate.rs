@@ -1,6 +1,6 @@
 use bevy_app::{App, FixedUpdate, TaskPoolPlugin};
 use bevy_ecs::{prelude::*, system::RunSystemOnce};
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use std::hint::black_box;
 use wdn_physics::{
     layer::Layer,
@@ -25,14 +25,12 @@ fn make_app() -> (App, Entity) {
     (app, layer)
 }
 
-fn bench_flow_field_generate(c: &mut Criterion) {
-    let (mut app, layer) = make_app();
-
+fn seed_tiles(app: &mut App, layer: Entity, width: i32, height: i32) {
     app.world_mut()
         .run_system_once(move |mut commands: Commands, mut storage: TileStorageMut| {
-            for x in 0..WIDTH {
-                for y in 0..HEIGHT {
-                    if y > HEIGHT / 4 && y < HEIGHT / 4 * 3 && x % 16 == 0 {
+            for x in 0..width {
+                for y in 0..height {
+                    if y > height / 4 && y < height / 4 * 3 && x % 16 == 0 {
                         storage.set_material(TilePosition::new(layer, x, y), TileMaterial::WALL);
                     } else {
                         storage.set_material(TilePosition::new(layer, x, y), TileMaterial::EMPTY);
@@ -40,18 +38,37 @@ fn bench_flow_field_generate(c: &mut Criterion) {
                 }
             }
 
-            let door = TilePosition::new(layer, WIDTH / 2, HEIGHT / 2);
+            let door = TilePosition::new(layer, width / 2, height / 2);
             storage.set_material(door, TileMaterial::DOOR);
             commands.spawn((ChildOf(layer), Door::default(), door));
         })
         .expect("failed to seed benchmark tiles");
+}
 
-    app.world_mut().run_schedule(FixedUpdate);
+fn set_material(app: &mut App, position: TilePosition, material: TileMaterial) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, material);
+        })
+        .expect("failed to set benchmark tile");
+}
 
+fn door_region_tiles(app: &mut App) -> RegionTiles {
     let mut regions_query = app.world_mut().query::<&RegionTiles>();
-    let query = regions_query.query(app.world());
-    let region_tiles = query.iter().find(|region| region.door_count() > 0).unwrap();
+    regions_query
+        .iter(app.world())
+        .find(|region| region.door_count() > 0)
+        .unwrap()
+        .clone()
+}
+
+fn bench_flow_field_generate(c: &mut Criterion) {
+    let (mut app, layer) = make_app();
+    seed_tiles(&mut app, layer, WIDTH, HEIGHT);
 
+    app.world_mut().run_schedule(FixedUpdate);
+
+    let region_tiles = &door_region_tiles(&mut app);
     let door = region_tiles.doors()[0];
 
     c.bench_function("CostField::generate", |b| {
@@ -94,5 +111,36 @@ fn bench_flow_field_generate(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_flow_field_generate);
+fn bench_region_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_regions (toggle wall)");
+
+    // The edit is the same on every map. The path snapshot shares the region's tile blocks and
+    // index chunks, so only those lists grow with the region size.
+    for size in [128, 256, 512] {
+        let (mut app, layer) = make_app();
+        seed_tiles(&mut app, layer, size, size);
+
+        app.world_mut().run_schedule(FixedUpdate);
+
+        let wall = TilePosition::new(layer, size / 4 + 8, size / 8);
+        let mut wall_material = TileMaterial::WALL;
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                set_material(&mut app, wall, wall_material);
+                app.world_mut().run_schedule(FixedUpdate);
+
+                wall_material = if wall_material == TileMaterial::WALL {
+                    TileMaterial::EMPTY
+                } else {
+                    TileMaterial::WALL
+                };
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_flow_field_generate, bench_region_update);
 criterion_main!(benches);
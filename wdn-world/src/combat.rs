@@ -10,7 +10,10 @@ use wdn_physics::{
     kinematics::Velocity,
 };
 
-use crate::WorldSystems;
+use crate::{
+    WorldSystems,
+    path::cost::{DangerZones, update_danger_zones},
+};
 
 pub struct CombatPlugin;
 
@@ -77,6 +80,7 @@ pub fn apply_projectiles(
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<Damaged>();
+        app.init_resource::<DangerZones>();
 
         app.configure_sets(
             FixedUpdate,
@@ -85,7 +89,9 @@ impl Plugin for CombatPlugin {
 
         app.add_systems(
             FixedUpdate,
-            apply_projectiles.in_set(WorldSystems::ApplyProjectiles),
+            (apply_projectiles, update_danger_zones)
+                .chain_ignore_deferred()
+                .in_set(WorldSystems::ApplyProjectiles),
         );
     }
 }
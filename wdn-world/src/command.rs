@@ -0,0 +1,132 @@
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use wdn_physics::{
+    PhysicsSystems,
+    tile::{material::TileMaterial, position::TilePosition, storage::TileStorageMut},
+};
+
+use crate::{
+    WorldSystems,
+    door::Door,
+    pawn::{action::PawnAction, path::PawnPath},
+};
+
+pub struct CommandPlugin;
+
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub enum PlayerCommand {
+    SetTarget {
+        pawn: Entity,
+        target: TilePosition,
+    },
+    AttackLeft {
+        pawn: Entity,
+    },
+    AttackRight {
+        pawn: Entity,
+    },
+    SetMaterial {
+        position: TilePosition,
+        material: TileMaterial,
+    },
+    SpawnDoor {
+        position: TilePosition,
+    },
+    DespawnDoor {
+        position: TilePosition,
+    },
+    PlaceBlueprint {
+        position: TilePosition,
+        material: TileMaterial,
+    },
+    RemoveBlueprint {
+        position: TilePosition,
+    },
+}
+
+// Pawns that attacked on command, which go back to standing on the next tick.
+#[derive(Resource, Default, Debug)]
+pub struct CommandedAttacks {
+    pawns: Vec<Entity>,
+}
+
+pub fn apply_player_commands(
+    mut commands: Commands,
+    mut messages: MessageReader<PlayerCommand>,
+    mut storage: TileStorageMut,
+    mut pawns: Query<(&mut PawnPath, &mut PawnAction)>,
+    doors: Query<(), With<Door>>,
+    mut attacking: ResMut<CommandedAttacks>,
+) {
+    for pawn in attacking.pawns.drain(..) {
+        if let Ok((_, mut action)) = pawns.get_mut(pawn)
+            && matches!(*action, PawnAction::AttackLeft | PawnAction::AttackRight)
+        {
+            *action = PawnAction::Stand;
+        }
+    }
+
+    for &command in messages.read() {
+        match command {
+            PlayerCommand::SetTarget { pawn, target } => {
+                if let Ok((mut path, _)) = pawns.get_mut(pawn) {
+                    path.set_target(target);
+                }
+            }
+            PlayerCommand::AttackLeft { pawn } | PlayerCommand::AttackRight { pawn } => {
+                if let Ok((_, mut action)) = pawns.get_mut(pawn) {
+                    *action = match command {
+                        PlayerCommand::AttackLeft { .. } => PawnAction::AttackLeft,
+                        _ => PawnAction::AttackRight,
+                    };
+                    attacking.insert(pawn);
+                }
+            }
+            PlayerCommand::SetMaterial { position, material } => {
+                storage.set_material(position, material);
+            }
+            PlayerCommand::SpawnDoor { position } => {
+                commands.spawn((Door::default(), position));
+            }
+            PlayerCommand::DespawnDoor { position } => {
+                if let Some(door) = storage.index.get_tile(position)
+                    && doors.contains(door)
+                {
+                    commands.entity(door).despawn();
+                }
+            }
+            // Blueprints live in wdn-tasks, which applies these itself.
+            PlayerCommand::PlaceBlueprint { .. } | PlayerCommand::RemoveBlueprint { .. } => {}
+        }
+    }
+}
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlayerCommand>();
+        app.init_resource::<CommandedAttacks>();
+
+        app.configure_sets(
+            FixedUpdate,
+            WorldSystems::ApplyCommands
+                .before(WorldSystems::UpdateRegions)
+                .before(WorldSystems::UpdateDoors)
+                .before(PhysicsSystems::Collisions),
+        );
+
+        app.add_systems(
+            FixedUpdate,
+            apply_player_commands.in_set(WorldSystems::ApplyCommands),
+        );
+    }
+}
+
+impl CommandedAttacks {
+    pub fn contains(&self, pawn: Entity) -> bool {
+        self.pawns.contains(&pawn)
+    }
+
+    pub fn insert(&mut self, pawn: Entity) {
+        self.pawns.push(pawn);
+    }
+}
@@ -4,7 +4,7 @@ mod tests;
 use std::time::Duration;
 
 use bevy_app::prelude::*;
-use bevy_ecs::prelude::*;
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
 use bevy_time::prelude::*;
 
 use wdn_physics::{PhysicsSystems, collision::TileCollider, tile::material::TileMaterial};
@@ -19,7 +19,22 @@ pub struct Door {
     state: DoorState,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Component, Clone, Debug, Default)]
+pub struct DoorAccess {
+    locked: bool,
+    staff_only: bool,
+    owners: EntityHashSet,
+    schedule: Option<DoorSchedule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DoorSchedule {
+    period: Duration,
+    start: Duration,
+    end: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum DoorState {
     #[default]
     Closed,
@@ -57,6 +72,14 @@ impl Door {
     const OPEN_SPEED: f32 = 1.0;
     const OPEN_DURATION: Duration = Duration::from_secs(3);
 
+    pub fn from_state(state: DoorState) -> Self {
+        Door { state }
+    }
+
+    pub fn state(&self) -> DoorState {
+        self.state
+    }
+
     pub fn is_open(&self) -> bool {
         match self.state {
             DoorState::Closed => false,
@@ -145,3 +168,116 @@ impl Door {
         }
     }
 }
+
+impl DoorAccess {
+    pub fn locked() -> Self {
+        DoorAccess {
+            locked: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn staff_only() -> Self {
+        DoorAccess {
+            staff_only: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn owned(owners: impl IntoIterator<Item = Entity>) -> Self {
+        DoorAccess {
+            owners: owners.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_schedule(mut self, schedule: DoorSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    pub fn is_staff_only(&self) -> bool {
+        self.staff_only
+    }
+
+    pub fn set_staff_only(&mut self, staff_only: bool) {
+        self.staff_only = staff_only;
+    }
+
+    pub fn owners(&self) -> &EntityHashSet {
+        &self.owners
+    }
+
+    pub fn add_owner(&mut self, owner: Entity) {
+        self.owners.insert(owner);
+    }
+
+    pub fn remove_owner(&mut self, owner: Entity) {
+        self.owners.remove(&owner);
+    }
+
+    pub fn schedule(&self) -> Option<DoorSchedule> {
+        self.schedule
+    }
+
+    pub fn set_schedule(&mut self, schedule: Option<DoorSchedule>) {
+        self.schedule = schedule;
+    }
+
+    pub fn allows(&self, pawn: Entity, staff: bool, elapsed: Duration) -> bool {
+        if self.locked {
+            return false;
+        }
+
+        if staff {
+            return true;
+        }
+
+        if self.staff_only {
+            return false;
+        }
+
+        if !self.owners.is_empty() && !self.owners.contains(&pawn) {
+            return false;
+        }
+
+        self.schedule
+            .is_none_or(|schedule| schedule.is_open(elapsed))
+    }
+}
+
+impl DoorSchedule {
+    pub fn new(period: Duration, start: Duration, end: Duration) -> Self {
+        debug_assert!(!period.is_zero());
+        DoorSchedule { period, start, end }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    pub fn end(&self) -> Duration {
+        self.end
+    }
+
+    pub fn is_open(&self, elapsed: Duration) -> bool {
+        let time = Duration::from_nanos((elapsed.as_nanos() % self.period.as_nanos()) as u64);
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
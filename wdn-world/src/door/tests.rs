@@ -2,10 +2,11 @@ use std::time::Duration;
 
 use approx::assert_relative_eq;
 use bevy_app::prelude::*;
+use bevy_ecs::entity::Entity;
 use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
 use wdn_physics::collision::TileCollider;
 
-use super::{Door, DoorPlugin, DoorState};
+use super::{Door, DoorAccess, DoorPlugin, DoorSchedule, DoorState};
 
 #[test]
 fn door_closed() {
@@ -155,6 +156,60 @@ fn door_open_autoclose() {
     );
 }
 
+#[test]
+fn door_access() {
+    let owner = Entity::from_raw_u32(1).unwrap();
+    let other = Entity::from_raw_u32(2).unwrap();
+    let staff = true;
+    let prisoner = false;
+
+    let access = DoorAccess::default();
+    assert!(access.allows(other, prisoner, Duration::ZERO));
+    assert!(access.allows(other, staff, Duration::ZERO));
+
+    let mut access = DoorAccess::locked();
+    assert!(!access.allows(other, prisoner, Duration::ZERO));
+    assert!(!access.allows(other, staff, Duration::ZERO));
+    access.set_locked(false);
+    assert!(access.allows(other, prisoner, Duration::ZERO));
+
+    let access = DoorAccess::staff_only();
+    assert!(!access.allows(other, prisoner, Duration::ZERO));
+    assert!(access.allows(other, staff, Duration::ZERO));
+
+    let mut access = DoorAccess::owned([owner]);
+    assert!(access.allows(owner, prisoner, Duration::ZERO));
+    assert!(!access.allows(other, prisoner, Duration::ZERO));
+    assert!(access.allows(other, staff, Duration::ZERO));
+    access.remove_owner(owner);
+    assert!(access.allows(other, prisoner, Duration::ZERO));
+}
+
+#[test]
+fn door_access_schedule() {
+    let pawn = Entity::from_raw_u32(1).unwrap();
+    let access = DoorAccess::owned([pawn]).with_schedule(DoorSchedule::new(
+        Duration::from_secs(100),
+        Duration::from_secs(20),
+        Duration::from_secs(40),
+    ));
+
+    assert!(!access.allows(pawn, false, Duration::from_secs(10)));
+    assert!(access.allows(pawn, false, Duration::from_secs(20)));
+    assert!(!access.allows(pawn, false, Duration::from_secs(40)));
+    assert!(access.allows(pawn, false, Duration::from_secs(230)));
+    assert!(access.allows(pawn, true, Duration::from_secs(10)));
+
+    let overnight = DoorSchedule::new(
+        Duration::from_secs(100),
+        Duration::from_secs(80),
+        Duration::from_secs(10),
+    );
+    assert!(overnight.is_open(Duration::from_secs(90)));
+    assert!(overnight.is_open(Duration::from_secs(105)));
+    assert!(!overnight.is_open(Duration::from_secs(50)));
+}
+
 fn make_app(timestep: Duration) -> App {
     let mut app = App::new();
     app.add_plugins((TaskPoolPlugin::default(), TimePlugin, DoorPlugin));
@@ -1,28 +1,41 @@
 pub mod combat;
+pub mod command;
 pub mod door;
 pub mod path;
 pub mod pawn;
+pub mod trigger;
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 
 use crate::combat::CombatPlugin;
+use crate::command::CommandPlugin;
 use crate::door::DoorPlugin;
 use crate::path::PathPlugin;
 use crate::pawn::PawnPlugin;
+use crate::trigger::TriggerPlugin;
 
 pub struct WorldPlugin;
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash, SystemSet)]
 pub enum WorldSystems {
+    ApplyCommands,
     ApplyPawnActions,
     ApplyProjectiles,
     UpdateRegions,
     UpdateDoors,
+    UpdateTriggers,
 }
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((CombatPlugin, DoorPlugin, PawnPlugin, PathPlugin));
+        app.add_plugins((
+            CombatPlugin,
+            CommandPlugin,
+            DoorPlugin,
+            PawnPlugin,
+            PathPlugin,
+            TriggerPlugin,
+        ));
     }
 }
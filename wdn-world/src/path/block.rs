@@ -0,0 +1,149 @@
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+    sync::Arc,
+};
+
+const BLOCK_SIZE: usize = 1024;
+
+// A vector stored in shared blocks, so cloning it for a path snapshot is cheap and a later
+// write only copies the block it touches.
+#[derive(Clone)]
+pub struct BlockVec<T> {
+    blocks: Vec<Arc<Vec<T>>>,
+    len: usize,
+}
+
+// Every block unshared up front, for bulk writes that would otherwise check each block's
+// reference count on every index.
+pub struct BlockVecMut<'a, T> {
+    blocks: Vec<&'a mut [T]>,
+}
+
+impl<T: Clone> BlockVec<T> {
+    pub fn new() -> Self {
+        BlockVec {
+            blocks: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn from_elem(value: T, len: usize) -> Self {
+        let mut vec = BlockVec::new();
+        vec.resize(len, value);
+        vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.blocks
+            .get(index / BLOCK_SIZE)
+            .and_then(|block| block.get(index % BLOCK_SIZE))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.blocks.iter().flat_map(|block| block.iter())
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let blocks = (self.len + additional).div_ceil(BLOCK_SIZE);
+        self.blocks
+            .reserve(blocks.saturating_sub(self.blocks.len()));
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len.is_multiple_of(BLOCK_SIZE) {
+            self.blocks.push(Arc::new(Vec::with_capacity(BLOCK_SIZE)));
+        }
+
+        let block = self.blocks.last_mut().expect("missing block");
+        Arc::make_mut(block).push(value);
+        self.len += 1;
+    }
+
+    pub fn resize(&mut self, len: usize, value: T) {
+        if len <= self.len {
+            self.blocks.truncate(len.div_ceil(BLOCK_SIZE));
+            if let Some(block) = self.blocks.last_mut() {
+                Arc::make_mut(block).truncate(len - (len - 1) / BLOCK_SIZE * BLOCK_SIZE);
+            }
+            self.len = len;
+            return;
+        }
+
+        while self.len < len {
+            if self.len.is_multiple_of(BLOCK_SIZE) {
+                self.blocks.push(Arc::new(Vec::with_capacity(BLOCK_SIZE)));
+            }
+
+            let block = Arc::make_mut(self.blocks.last_mut().expect("missing block"));
+            let count = (len - self.len).min(BLOCK_SIZE - block.len());
+            block.resize(block.len() + count, value.clone());
+            self.len += count;
+        }
+    }
+
+    pub fn as_mut(&mut self) -> BlockVecMut<'_, T> {
+        BlockVecMut {
+            blocks: self
+                .blocks
+                .iter_mut()
+                .map(|block| Arc::make_mut(block).as_mut_slice())
+                .collect(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.len = 0;
+    }
+}
+
+impl<T: Clone> Default for BlockVec<T> {
+    fn default() -> Self {
+        BlockVec::new()
+    }
+}
+
+impl<T> Index<usize> for BlockVec<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.blocks[index / BLOCK_SIZE][index % BLOCK_SIZE]
+    }
+}
+
+impl<T: Clone> IndexMut<usize> for BlockVec<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut Arc::make_mut(&mut self.blocks[index / BLOCK_SIZE])[index % BLOCK_SIZE]
+    }
+}
+
+impl<T> Index<usize> for BlockVecMut<'_, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.blocks[index / BLOCK_SIZE][index % BLOCK_SIZE]
+    }
+}
+
+impl<T> IndexMut<usize> for BlockVecMut<'_, T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.blocks[index / BLOCK_SIZE][index % BLOCK_SIZE]
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BlockVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.blocks.iter().flat_map(|block| block.iter()))
+            .finish()
+    }
+}
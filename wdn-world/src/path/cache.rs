@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use bevy_ecs::prelude::*;
+use bevy_platform::collections::HashMap;
+use wdn_physics::tile::position::TilePosition;
+
+use crate::path::{
+    find::PathGraph,
+    flow::{CostField, FlowPolicy},
+    region::{AddedRegions, RegionTiles},
+};
+
+#[derive(Resource, Debug)]
+pub struct PathCache {
+    goals: HashMap<TilePosition, GoalField>,
+    order: VecDeque<TilePosition>,
+    capacity: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct GoalField {
+    goal: TilePosition,
+    region: Entity,
+    cost_field: Arc<CostField>,
+}
+
+pub fn update_path_cache(mut cache: ResMut<PathCache>, added_regions: Res<AddedRegions>) {
+    for &region in added_regions.iter() {
+        cache.invalidate(region);
+    }
+}
+
+pub fn on_remove_cache_region(trigger: On<Remove, RegionTiles>, mut cache: ResMut<PathCache>) {
+    cache.invalidate(trigger.entity);
+}
+
+impl PathCache {
+    pub const CAPACITY: usize = 128;
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        PathCache {
+            goals: HashMap::default(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, goal: TilePosition) -> Option<&GoalField> {
+        self.goals.get(&goal)
+    }
+
+    pub fn get_or_generate(
+        &mut self,
+        goal: TilePosition,
+        graph: &impl PathGraph,
+    ) -> Option<GoalField> {
+        if let Some(goal_field) = self.goals.get(&goal) {
+            return Some(goal_field.clone());
+        }
+
+        let goal_field = GoalField::generate(goal, graph)?;
+        self.insert(goal_field.clone());
+        Some(goal_field)
+    }
+
+    pub fn insert(&mut self, goal_field: GoalField) {
+        if self.goals.contains_key(&goal_field.goal) {
+            return;
+        }
+
+        if self.goals.len() >= self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.goals.remove(&oldest);
+        }
+
+        self.order.push_back(goal_field.goal);
+        self.goals.insert(goal_field.goal, goal_field);
+    }
+
+    pub fn invalidate(&mut self, region: Entity) {
+        self.goals
+            .retain(|_, goal_field| goal_field.region != region);
+        self.order.retain(|goal| self.goals.contains_key(goal));
+    }
+
+    pub fn clear(&mut self) {
+        self.goals.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.goals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+    }
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        PathCache::with_capacity(PathCache::CAPACITY)
+    }
+}
+
+impl GoalField {
+    pub fn generate(goal: TilePosition, graph: &impl PathGraph) -> Option<Self> {
+        let region = graph.tile_region(goal)?;
+        let region_tiles = graph.region_tiles(region).ok()?;
+        let goal_index = region_tiles.get_tile_index(goal.layer_offset())?;
+
+        let mut cost_field = CostField::new(region_tiles.size());
+        cost_field.generate::<FlowPolicy>(
+            &FlowPolicy,
+            region_tiles,
+            goal_index,
+            goal.layer_offset(),
+            graph.tile_adjacency(region, goal),
+        );
+
+        Some(GoalField {
+            goal,
+            region,
+            cost_field: Arc::new(cost_field),
+        })
+    }
+
+    pub fn goal(&self) -> TilePosition {
+        self.goal
+    }
+
+    pub fn region(&self) -> Entity {
+        self.region
+    }
+
+    pub fn cost_field(&self) -> &Arc<CostField> {
+        &self.cost_field
+    }
+}
@@ -0,0 +1,265 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy_ecs::prelude::*;
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_time::prelude::*;
+use wdn_physics::{collision::filter::CollisionGroups, tile::position::TilePosition};
+
+use crate::combat::Damaged;
+
+pub trait CostModifier: Send + Sync {
+    fn cost(&self, position: TilePosition) -> u32;
+
+    // Every tile that may have a non-zero cost, so searches can find the affected regions
+    // without testing every tile in them.
+    fn tiles(&self) -> Box<dyn Iterator<Item = TilePosition> + '_>;
+
+    // Crowds gather at shared destinations, so a modifier can leave the goal's region alone to
+    // keep the cached goal field usable there.
+    fn in_goal_region(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct CostModifiers<'a> {
+    modifiers: &'a [&'a dyn CostModifier],
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct AvoidZone {
+    tiles: HashSet<TilePosition>,
+    groups: CollisionGroups,
+    cost: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Congestion {
+    occupancy: Arc<HashMap<TilePosition, u32>>,
+    origin: TilePosition,
+    cost: u32,
+}
+
+#[derive(Resource, Clone, Default, Debug)]
+pub struct DangerZones {
+    zones: Vec<DangerZone>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DangerZone {
+    position: TilePosition,
+    remaining: Duration,
+}
+
+pub fn update_danger_zones(
+    mut zones: ResMut<DangerZones>,
+    mut damaged: MessageReader<Damaged>,
+    positions: Query<&TilePosition>,
+    time: Res<Time>,
+) {
+    zones.tick(time.delta());
+
+    for message in damaged.read() {
+        if let Ok(&position) = positions.get(message.target) {
+            zones.insert(position);
+        }
+    }
+}
+
+impl<'a> CostModifiers<'a> {
+    pub fn new(modifiers: &'a [&'a dyn CostModifier]) -> Self {
+        CostModifiers { modifiers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modifiers.is_empty()
+    }
+
+    pub fn cost(&self, position: TilePosition) -> u32 {
+        self.modifiers.iter().fold(0, |cost, modifier| {
+            cost.saturating_add(modifier.cost(position))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a dyn CostModifier> + use<'a> {
+        self.modifiers.iter().copied()
+    }
+}
+
+impl AvoidZone {
+    pub fn new(
+        tiles: impl IntoIterator<Item = TilePosition>,
+        groups: CollisionGroups,
+        cost: u32,
+    ) -> Self {
+        AvoidZone {
+            tiles: tiles.into_iter().collect(),
+            groups,
+            cost,
+        }
+    }
+
+    pub fn tiles(&self) -> &HashSet<TilePosition> {
+        &self.tiles
+    }
+
+    pub fn groups(&self) -> CollisionGroups {
+        self.groups
+    }
+
+    pub fn tile_cost(&self) -> u32 {
+        self.cost
+    }
+
+    pub fn applies_to(&self, groups: CollisionGroups) -> bool {
+        self.groups.intersects(groups)
+    }
+}
+
+impl CostModifier for AvoidZone {
+    fn cost(&self, position: TilePosition) -> u32 {
+        if self.tiles.contains(&position) {
+            self.cost
+        } else {
+            0
+        }
+    }
+
+    fn tiles(&self) -> Box<dyn Iterator<Item = TilePosition> + '_> {
+        Box::new(self.tiles.iter().copied())
+    }
+}
+
+impl Congestion {
+    pub const RADIUS: i32 = 8;
+    pub const COST: u32 = 5;
+
+    // Only the crowd near the origin matters; pawns further away will have moved on.
+    pub fn new(occupancy: Arc<HashMap<TilePosition, u32>>, origin: TilePosition) -> Self {
+        Congestion {
+            occupancy,
+            origin,
+            cost: Self::COST,
+        }
+    }
+
+    pub fn occupancy(
+        positions: impl IntoIterator<Item = TilePosition>,
+    ) -> Arc<HashMap<TilePosition, u32>> {
+        let mut occupancy = HashMap::default();
+        for position in positions {
+            *occupancy.entry(position).or_default() += 1;
+        }
+        Arc::new(occupancy)
+    }
+
+    pub fn with_cost(mut self, cost: u32) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles().next().is_none()
+    }
+}
+
+impl CostModifier for Congestion {
+    fn cost(&self, position: TilePosition) -> u32 {
+        if position.layer() != self.origin.layer()
+            || position.x().abs_diff(self.origin.x()) > Self::RADIUS as u32
+            || position.y().abs_diff(self.origin.y()) > Self::RADIUS as u32
+        {
+            return 0;
+        }
+
+        let mut count = self.occupancy.get(&position).copied().unwrap_or(0);
+        if position == self.origin {
+            count = count.saturating_sub(1);
+        }
+        count.saturating_mul(self.cost)
+    }
+
+    fn tiles(&self) -> Box<dyn Iterator<Item = TilePosition> + '_> {
+        Box::new(
+            self.occupancy
+                .keys()
+                .copied()
+                .filter(|&position| self.cost(position) > 0),
+        )
+    }
+
+    fn in_goal_region(&self) -> bool {
+        false
+    }
+}
+
+impl DangerZones {
+    pub const RADIUS: i32 = 2;
+    pub const COST: u32 = 20;
+    pub const DURATION: Duration = Duration::from_secs(30);
+
+    pub fn insert(&mut self, position: TilePosition) {
+        self.insert_for(position, Self::DURATION);
+    }
+
+    pub fn insert_for(&mut self, position: TilePosition, remaining: Duration) {
+        match self.zones.iter_mut().find(|zone| zone.position == position) {
+            Some(zone) => zone.remaining = remaining,
+            None => self.zones.push(DangerZone {
+                position,
+                remaining,
+            }),
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.zones.retain_mut(|zone| {
+            zone.remaining = zone.remaining.saturating_sub(delta);
+            !zone.remaining.is_zero()
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DangerZone> {
+        self.zones.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+}
+
+impl CostModifier for DangerZones {
+    fn cost(&self, position: TilePosition) -> u32 {
+        self.zones
+            .iter()
+            .filter(|zone| zone.contains(position))
+            .fold(0, |cost, _| cost.saturating_add(Self::COST))
+    }
+
+    fn tiles(&self) -> Box<dyn Iterator<Item = TilePosition> + '_> {
+        Box::new(self.zones.iter().flat_map(|zone| zone.tiles()))
+    }
+}
+
+impl DangerZone {
+    pub fn position(&self) -> TilePosition {
+        self.position
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    pub fn contains(&self, position: TilePosition) -> bool {
+        position.layer() == self.position.layer()
+            && position.x().abs_diff(self.position.x()) <= DangerZones::RADIUS as u32
+            && position.y().abs_diff(self.position.y()) <= DangerZones::RADIUS as u32
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = TilePosition> + use<> {
+        let position = self.position;
+        (-DangerZones::RADIUS..=DangerZones::RADIUS).flat_map(move |y| {
+            (-DangerZones::RADIUS..=DangerZones::RADIUS).map(move |x| position.with_offset(x, y))
+        })
+    }
+}
@@ -2,9 +2,9 @@ use arrayvec::ArrayVec;
 use bevy_ecs::prelude::*;
 use wdn_physics::tile::adjacency::Adjacency;
 
-use crate::path::region::{AddedRegions, RegionTiles};
+use crate::path::region::{AddedRegions, RegionChanges, RegionTiles};
 
-#[derive(Component, Default)]
+#[derive(Component, Clone, Default)]
 pub struct DoorRegions {
     regions: ArrayVec<DoorRegion, 4>,
 }
@@ -19,13 +19,29 @@ pub struct DoorRegion {
 
 pub fn update_door_regions(
     mut doors: Query<&mut DoorRegions>,
-    regions: Query<(Entity, &RegionTiles)>,
+    regions: Query<(Entity, &RegionTiles, Option<&RegionChanges>)>,
     added_regions: Res<AddedRegions>,
 ) {
-    regions
-        .iter_many_unique(added_regions.iter())
-        .for_each(|(region_id, region_tiles)| {
-            let dead_end = region_tiles.door_count() == 1;
+    for (region_id, region_tiles, changes) in regions.iter_many(added_regions.iter()) {
+        let Some(changes) = changes.filter(|changes| !changes.is_empty()) else {
+            continue;
+        };
+
+        let region_doors = region_tiles.doors().iter().map(|door| door.door());
+        for door in changes.removed_doors().iter().copied().chain(region_doors) {
+            if let Ok(mut door_regions) = doors.get_mut(door) {
+                door_regions.remove(region_id);
+            }
+        }
+    }
+
+    regions.iter_many_unique(added_regions.iter()).for_each(
+        |(region_id, region_tiles, changes)| {
+            if changes.is_some_and(|changes| changes.is_empty()) {
+                return;
+            }
+
+            let dead_end = region_tiles.door_count() == 1 && region_tiles.stairs().is_empty();
             for region_door in region_tiles.doors() {
                 let mut door_regions = doors.get_mut(region_door.door()).expect("invalid door");
                 door_regions.insert(
@@ -35,7 +51,8 @@ pub fn update_door_regions(
                     dead_end,
                 );
             }
-        });
+        },
+    );
 }
 
 pub fn on_remove_region(
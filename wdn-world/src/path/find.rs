@@ -1,20 +1,29 @@
-use std::{collections::BinaryHeap, hash::Hash};
+use std::{collections::BinaryHeap, hash::Hash, sync::Arc, time::Duration};
 
-use bevy_ecs::{prelude::*, system::SystemParam};
+use bevy_ecs::{entity::EntityHashSet, prelude::*, system::SystemParam};
 use bevy_math::prelude::*;
 use bevy_platform::collections::{HashMap, hash_map};
-use wdn_physics::tile::{
-    index::TileIndex,
-    material::TileMoveSpeed,
-    position::{TileLayerOffset, TilePosition},
-    storage::TileStorage,
+use wdn_physics::{
+    layer::Layer,
+    tile::{
+        adjacency::Adjacency,
+        index::TileIndex,
+        material::{TileKind, TileMoveSpeed},
+        position::{TileLayerOffset, TilePosition},
+        storage::TileStorage,
+    },
 };
 
-use crate::path::{
-    door::DoorRegions,
-    flow::{CostField, FlowField, PathPolicy, octile_cost},
-    region::RegionTiles,
-    section::TileChunkSections,
+use crate::{
+    door::DoorAccess,
+    path::{
+        cache::GoalField,
+        cost::{CostModifier, CostModifiers},
+        door::DoorRegions,
+        flow::{CostField, FlowField, ModifiedFlowPolicy, PathPolicy, STAIRS_COST, octile_cost},
+        region::RegionTiles,
+        section::TileChunkSections,
+    },
 };
 
 #[derive(Debug)]
@@ -30,10 +39,64 @@ pub enum PathStep {
         flow_field: Entity,
         goal: TilePosition,
     },
+    StairsFlowField {
+        region: Entity,
+        flow_field: Entity,
+        goal: TilePosition,
+    },
     RegionCostField {
         region: Entity,
+        version: u32,
+        cost_field: Arc<CostField>,
+    },
+    WaypointCostField {
+        region: Entity,
+        version: u32,
         cost_field: CostField,
+        goal: TilePosition,
     },
+    Stairs {
+        from: TilePosition,
+        to: TilePosition,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathMove {
+    Walk(Dir2),
+    Climb(TilePosition),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PathAgent {
+    entity: Entity,
+    staff: bool,
+    elapsed: Duration,
+}
+
+pub trait PathGraph {
+    fn region_tiles(&self, region: Entity) -> Result<&RegionTiles>;
+
+    fn flow_field(&self, flow_field: Entity) -> Result<&FlowField>;
+
+    fn door_regions(&self, door: Entity) -> Result<&DoorRegions>;
+
+    fn door_access(&self, door: Entity) -> Option<&DoorAccess>;
+
+    fn tile_region(&self, position: TilePosition) -> Option<Entity>;
+
+    fn tile_door(&self, position: TilePosition) -> Option<Entity>;
+
+    fn tile_adjacency(&self, region: Entity, position: TilePosition) -> Adjacency;
+
+    fn is_stairs(&self, position: TilePosition) -> bool;
+
+    fn linked_layer(&self, layer: Entity, delta: i32) -> Option<Entity>;
+}
+
+pub struct PathSearch<'a, G> {
+    graph: &'a G,
+    goal_field: Option<&'a GoalField>,
 }
 
 #[derive(SystemParam)]
@@ -43,13 +106,16 @@ pub struct PathParam<'w, 's> {
     pub chunks: Query<'w, 's, &'static TileChunkSections>,
     pub flow_fields: Query<'w, 's, &'static FlowField>,
     pub doors: Query<'w, 's, &'static DoorRegions>,
+    pub access: Query<'w, 's, &'static DoorAccess>,
     pub regions: Query<'w, 's, &'static RegionTiles>,
+    pub layers: Query<'w, 's, (Entity, &'static Layer, &'static ChildOf)>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum SearchNodeId {
     Door(Entity),
     Position(Entity, TilePosition),
+    Stairs(Entity, TilePosition),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -60,6 +126,13 @@ struct SearchNode {
     estimated_cost: u32,
 }
 
+struct SearchModifiers<'m> {
+    modifiers: CostModifiers<'m>,
+    goal_modifiers: CostModifiers<'m>,
+    goal_region: Option<Entity>,
+    modified: EntityHashSet,
+}
+
 #[derive(Debug)]
 struct SearchEntry {
     parent: SearchNodeId,
@@ -74,19 +147,233 @@ enum SearchEntryPath {
         region: Entity,
         flow_field: Entity,
     },
+    StairsFlowField {
+        region: Entity,
+        flow_field: Entity,
+    },
+    Stairs {
+        from: TilePosition,
+    },
     CostField {
         region: Entity,
         start: TilePosition,
-        cost_field: Option<CostField>,
+        cost_field: Option<Arc<CostField>>,
     },
 }
 
 impl PathParam<'_, '_> {
-    pub fn find_path(&self, start: TilePosition, goal: TilePosition) -> Result<Option<Path>> {
-        if start.layer() != goal.layer() {
-            return Ok(None);
+    pub fn find_path(
+        &self,
+        start: TilePosition,
+        goal: TilePosition,
+        agent: &PathAgent,
+        modifiers: CostModifiers,
+    ) -> Result<Option<Path>> {
+        PathSearch::new(self).find_path(start, goal, agent, modifiers)
+    }
+
+    pub fn next_move(
+        &self,
+        path: &mut Path,
+        position: TilePosition,
+        agent: &PathAgent,
+    ) -> Result<Option<PathMove>> {
+        if let Some(door) = self.next_door(path, position)
+            && !PathSearch::new(self).door_allowed(door, agent)
+        {
+            return Err("door access denied".into());
+        }
+
+        loop {
+            match path.steps.last_mut() {
+                Some(&mut PathStep::DoorFlowField {
+                    region,
+                    flow_field,
+                    goal,
+                })
+                | Some(&mut PathStep::StairsFlowField {
+                    region,
+                    flow_field,
+                    goal,
+                }) => {
+                    if position == goal {
+                        path.steps.pop();
+                    } else {
+                        let region_tiles = self.regions.get(region)?;
+                        let position_index = region_tiles
+                            .get_tile_index(position.layer_offset())
+                            .ok_or("position not in region")?;
+
+                        let flow_field_data = self.flow_fields.get(flow_field)?;
+                        let entry = flow_field_data
+                            .get(position_index)
+                            .ok_or("position not in flow field")?;
+                        return Ok(Some(PathMove::Walk(entry.dir())));
+                    }
+                }
+                Some(PathStep::RegionCostField {
+                    cost_field,
+                    region,
+                    version,
+                }) => {
+                    let region_tiles = self.regions.get(*region)?;
+                    if region_tiles.version() != *version {
+                        return Err("region changed".into());
+                    }
+
+                    let position_index = region_tiles
+                        .get_tile_index(position.layer_offset())
+                        .ok_or("position not in region")?;
+                    if !cost_field.contains(position_index) {
+                        return Ok(None);
+                    }
+
+                    let dir = cost_field.flow_vector(position_index, &region_tiles[position_index]);
+
+                    return Ok(Some(PathMove::Walk(dir)));
+                }
+                Some(PathStep::WaypointCostField {
+                    region,
+                    version,
+                    cost_field,
+                    goal,
+                }) => {
+                    if position == *goal {
+                        path.steps.pop();
+                    } else {
+                        let region_tiles = self.regions.get(*region)?;
+                        if region_tiles.version() != *version {
+                            return Err("region changed".into());
+                        }
+
+                        let position_index = region_tiles
+                            .get_tile_index(position.layer_offset())
+                            .ok_or("position not in region")?;
+                        if !cost_field.contains(position_index) {
+                            return Ok(None);
+                        }
+
+                        let dir =
+                            cost_field.flow_vector(position_index, &region_tiles[position_index]);
+
+                        return Ok(Some(PathMove::Walk(dir)));
+                    }
+                }
+                Some(&mut PathStep::Stairs { from, to }) => {
+                    if position.layer() == to.layer() {
+                        path.steps.pop();
+                    } else if position == from {
+                        return Ok(Some(PathMove::Climb(to)));
+                    } else {
+                        return Err("position not on stairs".into());
+                    }
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    pub fn next_door(&self, path: &Path, position: TilePosition) -> Option<Entity> {
+        path.steps.iter().rev().find_map(|step| match *step {
+            PathStep::DoorFlowField { goal, .. } | PathStep::WaypointCostField { goal, .. }
+                if goal != position =>
+            {
+                self.index.get_tile(goal)
+            }
+            _ => None,
+        })
+    }
+
+    pub fn is_valid(&self, path: &Path) -> bool {
+        path.steps.iter().all(|step| match *step {
+            PathStep::DoorFlowField {
+                region, flow_field, ..
+            }
+            | PathStep::StairsFlowField {
+                region, flow_field, ..
+            } => self.regions.contains(region) && self.flow_fields.contains(flow_field),
+            PathStep::RegionCostField {
+                region, version, ..
+            }
+            | PathStep::WaypointCostField {
+                region, version, ..
+            } => self
+                .regions
+                .get(region)
+                .is_ok_and(|region_tiles| region_tiles.version() == version),
+            PathStep::Stairs { from, to } => self.is_stairs(from) && self.is_stairs(to),
+        })
+    }
+}
+
+impl PathGraph for PathParam<'_, '_> {
+    fn region_tiles(&self, region: Entity) -> Result<&RegionTiles> {
+        Ok(self.regions.get(region)?)
+    }
+
+    fn flow_field(&self, flow_field: Entity) -> Result<&FlowField> {
+        Ok(self.flow_fields.get(flow_field)?)
+    }
+
+    fn door_regions(&self, door: Entity) -> Result<&DoorRegions> {
+        Ok(self.doors.get(door)?)
+    }
+
+    fn door_access(&self, door: Entity) -> Option<&DoorAccess> {
+        self.access.get(door).ok()
+    }
+
+    fn tile_region(&self, position: TilePosition) -> Option<Entity> {
+        let chunk_id = self.storage.chunk_id(position.chunk_position())?;
+        let chunk_sections = self.chunks.get(chunk_id).ok()?;
+        chunk_sections.region(position.chunk_offset())
+    }
+
+    fn tile_door(&self, position: TilePosition) -> Option<Entity> {
+        self.index.get_tile(position)
+    }
+
+    fn tile_adjacency(&self, _region: Entity, position: TilePosition) -> Adjacency {
+        self.storage.get_adjacency(position).walls().complement()
+    }
+
+    fn is_stairs(&self, position: TilePosition) -> bool {
+        self.storage.get_kind(position) == TileKind::Stairs
+    }
+
+    fn linked_layer(&self, layer: Entity, delta: i32) -> Option<Entity> {
+        let (_, layer, parent) = self.layers.get(layer).ok()?;
+        let (linked_layer, _, _) =
+            self.layers
+                .iter()
+                .find(|(_, linked_layer, linked_parent)| {
+                    linked_parent.parent() == parent.parent()
+                        && linked_layer.height() == layer.height() + delta
+                })?;
+        Some(linked_layer)
+    }
+}
+
+impl<'a, G: PathGraph> PathSearch<'a, G> {
+    pub fn new(graph: &'a G) -> Self {
+        PathSearch {
+            graph,
+            goal_field: None,
         }
+    }
+
+    pub fn with_goal_field(mut self, goal_field: &'a GoalField) -> Self {
+        self.goal_field = Some(goal_field);
+        self
+    }
 
+    pub fn find_path(
+        &self,
+        start: TilePosition,
+        goal: TilePosition,
+        agent: &PathAgent,
+        modifiers: CostModifiers,
+    ) -> Result<Option<Path>> {
         if start == goal {
             return Ok(Some(Path {
                 steps: vec![],
@@ -100,9 +387,24 @@ impl PathParam<'_, '_> {
         let Some(goal_id) = self.position_id(goal) else {
             return Ok(None);
         };
+        if let SearchNodeId::Door(door) = goal_id
+            && !self.door_allowed(door, agent)
+        {
+            return Ok(None);
+        }
 
         let mut open: BinaryHeap<SearchNode> = BinaryHeap::new();
         let mut map: HashMap<SearchNodeId, SearchEntry> = HashMap::default();
+        let goal_modifiers: Vec<&dyn CostModifier> = modifiers
+            .iter()
+            .filter(|modifier| modifier.in_goal_region())
+            .collect();
+        let modifiers = self.search_modifiers(
+            modifiers,
+            CostModifiers::new(&goal_modifiers),
+            start_id,
+            goal_id,
+        );
 
         open.push(SearchNode {
             id: start_id,
@@ -112,8 +414,8 @@ impl PathParam<'_, '_> {
         });
 
         while let Some(node) = open.pop() {
-            if node.id == goal_id {
-                return Ok(Some(self.collect_path(map, goal_id)?));
+            if node.id == goal_id || node.position == goal {
+                return Ok(Some(self.collect_path(map, node.id, &modifiers)?));
             }
 
             if let Some(entry) = map.get(&node.id) {
@@ -122,76 +424,47 @@ impl PathParam<'_, '_> {
                 }
             }
 
-            self.visit_neighbors(&node, goal, goal_id, |id, position, path, cost| {
-                let new_cost = node.cost + cost;
-
-                match map.entry(id) {
-                    hash_map::Entry::Occupied(entry) if new_cost >= entry.get().cost => {
+            self.visit_neighbors(
+                &node,
+                goal,
+                goal_id,
+                &modifiers,
+                |id, position, path, cost| {
+                    if let SearchNodeId::Door(door) = id
+                        && !self.door_allowed(door, agent)
+                    {
                         return;
                     }
-                    entry => {
-                        entry.insert(SearchEntry {
-                            parent: node.id,
-                            path,
-                            cost: new_cost,
-                            position,
-                        });
-                    }
-                }
 
-                let estimated_cost =
-                    new_cost + octile_cost_heuristic(position.layer_offset(), goal.layer_offset());
-                open.push(SearchNode {
-                    id,
-                    position,
-                    cost: new_cost,
-                    estimated_cost,
-                });
-            })?;
-        }
+                    let new_cost = node.cost + cost;
 
-        Ok(None)
-    }
-
-    pub fn path_dir(&self, path: &mut Path, position: TilePosition) -> Result<Option<Dir2>> {
-        loop {
-            match path.steps.last_mut() {
-                Some(&mut PathStep::DoorFlowField {
-                    region,
-                    flow_field,
-                    goal,
-                }) => {
-                    if position == goal {
-                        path.steps.pop();
-                    } else {
-                        let region_tiles = self.regions.get(region)?;
-                        let position_index = region_tiles
-                            .get_tile_index(position.layer_offset())
-                            .ok_or("position not in region")?;
-
-                        let flow_field_data = self.flow_fields.get(flow_field)?;
-                        let entry = flow_field_data
-                            .get(position_index)
-                            .ok_or("position not in flow field")?;
-                        return Ok(Some(entry.dir()));
-                    }
-                }
-                Some(PathStep::RegionCostField { cost_field, region }) => {
-                    let region_tiles = self.regions.get(*region)?;
-                    let position_index = region_tiles
-                        .get_tile_index(position.layer_offset())
-                        .ok_or("position not in region")?;
-                    if !cost_field.contains(position_index) {
-                        return Ok(None);
+                    match map.entry(id) {
+                        hash_map::Entry::Occupied(entry) if new_cost >= entry.get().cost => {
+                            return;
+                        }
+                        entry => {
+                            entry.insert(SearchEntry {
+                                parent: node.id,
+                                path,
+                                cost: new_cost,
+                                position,
+                            });
+                        }
                     }
 
-                    let dir = cost_field.flow_vector(position_index, &region_tiles[position_index]);
-
-                    return Ok(Some(dir));
-                }
-                None => return Ok(None),
-            }
+                    let estimated_cost = new_cost
+                        + octile_cost_heuristic(position.layer_offset(), goal.layer_offset());
+                    open.push(SearchNode {
+                        id,
+                        position,
+                        cost: new_cost,
+                        estimated_cost,
+                    });
+                },
+            )?;
         }
+
+        Ok(None)
     }
 
     fn generate_cost_field_path(
@@ -199,8 +472,9 @@ impl PathParam<'_, '_> {
         region: Entity,
         start: TilePosition,
         goal: TilePosition,
+        modifiers: CostModifiers,
     ) -> Result<(CostField, u32)> {
-        let region_tiles = self.regions.get(region)?;
+        let region_tiles = self.graph.region_tiles(region)?;
 
         let start_position = start.layer_offset();
         let start_index = region_tiles
@@ -210,10 +484,11 @@ impl PathParam<'_, '_> {
         let goal_index = region_tiles
             .get_tile_index(goal_position)
             .ok_or("goal position not in region")?;
-        let goal_adjacency = self.storage.get_adjacency(goal).walls().complement();
+        let goal_adjacency = self.graph.tile_adjacency(region, goal);
 
         let mut cost_field = CostField::new(region_tiles.size());
-        let policy = PathPolicy::new(start_position, start_index);
+        let policy =
+            PathPolicy::new(start_position, start_index).with_modifiers(goal.layer(), modifiers);
 
         cost_field.generate::<PathPolicy>(
             &policy,
@@ -233,35 +508,74 @@ impl PathParam<'_, '_> {
         Ok((cost_field, cost))
     }
 
+    fn goal_field(&self, region: Entity, goal: TilePosition) -> Option<&'a Arc<CostField>> {
+        self.goal_field
+            .filter(|goal_field| goal_field.goal() == goal && goal_field.region() == region)
+            .map(|goal_field| goal_field.cost_field())
+    }
+
     fn position_id(&self, position: TilePosition) -> Option<SearchNodeId> {
-        let chunk_id = self.storage.chunk_id(position.chunk_position())?;
-        let chunk_sections = self.chunks.get(chunk_id).ok()?;
+        self.graph
+            .tile_region(position)
+            .map(|region| SearchNodeId::Position(region, position))
+            .or_else(|| self.graph.tile_door(position).map(SearchNodeId::Door))
+    }
 
-        if let Some(region) = chunk_sections.region(position.chunk_offset()) {
-            Some(SearchNodeId::Position(region, position))
-        } else if let Some(door) = self.index.get_tile(position) {
-            Some(SearchNodeId::Door(door))
-        } else {
-            None
+    fn door_allowed(&self, door: Entity, agent: &PathAgent) -> bool {
+        match self.graph.door_access(door) {
+            Some(access) => access.allows(agent.entity, agent.staff, agent.elapsed),
+            None => true,
         }
     }
 
+    fn stairs_id(&self, position: TilePosition) -> Option<SearchNodeId> {
+        if !self.graph.is_stairs(position) {
+            return None;
+        }
+
+        match self.position_id(position)? {
+            SearchNodeId::Position(region, position) => {
+                Some(SearchNodeId::Stairs(region, position))
+            }
+            _ => None,
+        }
+    }
+
+    fn linked_stairs(&self, position: TilePosition) -> impl Iterator<Item = TilePosition> + '_ {
+        [1, -1].into_iter().filter_map(move |delta| {
+            let linked_layer = self.graph.linked_layer(position.layer(), delta)?;
+
+            let linked = TilePosition::from((linked_layer, position.layer_offset()));
+            if self.graph.is_stairs(linked) {
+                Some(linked)
+            } else {
+                None
+            }
+        })
+    }
+
     fn visit_neighbors(
         &self,
         node: &SearchNode,
         goal: TilePosition,
         goal_id: SearchNodeId,
+        modifiers: &SearchModifiers,
         mut f: impl FnMut(SearchNodeId, TilePosition, SearchEntryPath, u32),
     ) -> Result<()> {
         match node.id {
             SearchNodeId::Position(region, position) => {
-                let region_tiles = self.regions.get(region)?;
+                if let Some(modifiers) = modifiers.modified(region) {
+                    return self
+                        .visit_modified_region(region, position, goal, goal_id, modifiers, &mut f);
+                }
+
+                let region_tiles = self.graph.region_tiles(region)?;
                 let node_position_index = region_tiles
                     .get_tile_index(position.layer_offset())
                     .ok_or("position not in region")?;
 
                 for region_door in region_tiles.doors() {
-                    let flow_field = self.flow_fields.get(region_door.flow_field())?;
+                    let flow_field = self.graph.flow_field(region_door.flow_field())?;
                     let cost = flow_field
                         .get(node_position_index)
                         .ok_or("position not in flow field")?
@@ -277,9 +591,44 @@ impl PathParam<'_, '_> {
                     );
                 }
 
+                for region_stairs in region_tiles.stairs() {
+                    let stairs_position =
+                        TilePosition::from((node.position.layer(), region_stairs.position()));
+                    let cost = if region_stairs.index() == node_position_index {
+                        0
+                    } else {
+                        self.graph
+                            .flow_field(region_stairs.flow_field())?
+                            .get(node_position_index)
+                            .ok_or("position not in flow field")?
+                            .cost()
+                    };
+                    f(
+                        SearchNodeId::Stairs(region, stairs_position),
+                        stairs_position,
+                        SearchEntryPath::StairsFlowField {
+                            region,
+                            flow_field: region_stairs.flow_field(),
+                        },
+                        cost,
+                    );
+                }
+
                 if goal_id.in_region(region) {
-                    let (cost_field, cost) =
-                        self.generate_cost_field_path(region, position, goal)?;
+                    let (cost_field, cost) = match self.goal_field(region, goal) {
+                        Some(goal_field) if goal_field.contains(node_position_index) => {
+                            (goal_field.clone(), goal_field[node_position_index])
+                        }
+                        _ => {
+                            let (cost_field, cost) = self.generate_cost_field_path(
+                                region,
+                                position,
+                                goal,
+                                modifiers.region(region),
+                            )?;
+                            (Arc::new(cost_field), cost)
+                        }
+                    };
                     f(
                         goal_id,
                         goal,
@@ -292,15 +641,103 @@ impl PathParam<'_, '_> {
                     );
                 }
             }
+            SearchNodeId::Stairs(region, position) => {
+                if let Some(modifiers) = modifiers.modified(region) {
+                    self.visit_modified_region(region, position, goal, goal_id, modifiers, &mut f)?;
+                    self.visit_linked_stairs(position, &mut f);
+                    return Ok(());
+                }
+
+                let region_tiles = self.graph.region_tiles(region)?;
+                let stairs = region_tiles
+                    .stairs()
+                    .iter()
+                    .find(|region_stairs| region_stairs.position() == position.layer_offset())
+                    .ok_or("stairs not in region")?;
+                let flow_field = self.graph.flow_field(stairs.flow_field())?;
+
+                for region_door in region_tiles.doors() {
+                    let cost = flow_field
+                        .get(region_door.index())
+                        .ok_or("position not in flow field")?
+                        .cost();
+                    f(
+                        SearchNodeId::Door(region_door.door()),
+                        TilePosition::from((position.layer(), region_door.position())),
+                        SearchEntryPath::FlowField {
+                            region,
+                            flow_field: region_door.flow_field(),
+                        },
+                        cost,
+                    );
+                }
+
+                for region_stairs in region_tiles.stairs() {
+                    if region_stairs.index() == stairs.index() {
+                        continue;
+                    }
+
+                    let stairs_position =
+                        TilePosition::from((position.layer(), region_stairs.position()));
+                    let cost = flow_field
+                        .get(region_stairs.index())
+                        .ok_or("position not in flow field")?
+                        .cost();
+                    f(
+                        SearchNodeId::Stairs(region, stairs_position),
+                        stairs_position,
+                        SearchEntryPath::StairsFlowField {
+                            region,
+                            flow_field: region_stairs.flow_field(),
+                        },
+                        cost,
+                    );
+                }
+
+                if goal_id.in_region(region) {
+                    let goal_index = region_tiles
+                        .get_tile_index(goal.layer_offset())
+                        .ok_or("goal not in region")?;
+
+                    let cost = flow_field
+                        .get(goal_index)
+                        .ok_or("goal not in flow field")?
+                        .cost();
+                    f(
+                        goal_id,
+                        goal,
+                        SearchEntryPath::CostField {
+                            region,
+                            start: position,
+                            cost_field: None,
+                        },
+                        cost,
+                    );
+                }
+
+                self.visit_linked_stairs(position, &mut f);
+            }
             SearchNodeId::Door(door) => {
-                let door_regions = self.doors.get(door)?;
+                let door_regions = self.graph.door_regions(door)?;
                 for door_region in door_regions.iter() {
                     if door_region.dead_end() && !goal_id.in_region(door_region.region()) {
                         continue;
                     }
 
-                    let region_tiles = self.regions.get(door_region.region())?;
-                    let flow_field = self.flow_fields.get(door_region.flow_field())?;
+                    if let Some(modifiers) = modifiers.modified(door_region.region()) {
+                        self.visit_modified_region(
+                            door_region.region(),
+                            node.position,
+                            goal,
+                            goal_id,
+                            modifiers,
+                            &mut f,
+                        )?;
+                        continue;
+                    }
+
+                    let region_tiles = self.graph.region_tiles(door_region.region())?;
+                    let flow_field = self.graph.flow_field(door_region.flow_field())?;
 
                     for region_door in region_tiles.doors() {
                         if region_door.door() == door {
@@ -322,6 +759,24 @@ impl PathParam<'_, '_> {
                         );
                     }
 
+                    for region_stairs in region_tiles.stairs() {
+                        let stairs_position =
+                            TilePosition::from((node.position.layer(), region_stairs.position()));
+                        let cost = flow_field
+                            .get(region_stairs.index())
+                            .ok_or("position not in flow field")?
+                            .cost();
+                        f(
+                            SearchNodeId::Stairs(door_region.region(), stairs_position),
+                            stairs_position,
+                            SearchEntryPath::StairsFlowField {
+                                region: door_region.region(),
+                                flow_field: region_stairs.flow_field(),
+                            },
+                            cost,
+                        );
+                    }
+
                     if goal_id.in_region(door_region.region()) {
                         let goal_index = region_tiles
                             .get_tile_index(goal.layer_offset())
@@ -348,10 +803,150 @@ impl PathParam<'_, '_> {
         Ok(())
     }
 
+    fn visit_linked_stairs(
+        &self,
+        position: TilePosition,
+        f: &mut impl FnMut(SearchNodeId, TilePosition, SearchEntryPath, u32),
+    ) {
+        for linked in self.linked_stairs(position) {
+            if let Some(linked_id) = self.stairs_id(linked) {
+                f(
+                    linked_id,
+                    linked,
+                    SearchEntryPath::Stairs { from: position },
+                    STAIRS_COST,
+                );
+            }
+        }
+    }
+
+    fn search_modifiers<'m>(
+        &self,
+        modifiers: CostModifiers<'m>,
+        goal_modifiers: CostModifiers<'m>,
+        start_id: SearchNodeId,
+        goal_id: SearchNodeId,
+    ) -> SearchModifiers<'m> {
+        // A pawn already in the goal's region still routes around everything in it.
+        let goal_region = match goal_id {
+            SearchNodeId::Position(region, _) if !start_id.in_region(region) => Some(region),
+            _ => None,
+        };
+
+        let mut modified = EntityHashSet::default();
+        for modifier in modifiers.iter() {
+            let mut insert = |region: Entity| {
+                if goal_region != Some(region) || modifier.in_goal_region() {
+                    modified.insert(region);
+                }
+            };
+
+            for position in modifier.tiles() {
+                if modifier.cost(position) == 0 {
+                    continue;
+                }
+
+                if let Some(region) = self.graph.tile_region(position) {
+                    insert(region);
+                } else if let Some(door) = self.graph.tile_door(position)
+                    && let Ok(door_regions) = self.graph.door_regions(door)
+                {
+                    for door_region in door_regions.iter() {
+                        insert(door_region.region());
+                    }
+                }
+            }
+        }
+
+        SearchModifiers {
+            modifiers,
+            goal_modifiers,
+            goal_region,
+            modified,
+        }
+    }
+
+    fn visit_modified_region(
+        &self,
+        region: Entity,
+        start: TilePosition,
+        goal: TilePosition,
+        goal_id: SearchNodeId,
+        modifiers: CostModifiers,
+        f: &mut impl FnMut(SearchNodeId, TilePosition, SearchEntryPath, u32),
+    ) -> Result<()> {
+        let region_tiles = self.graph.region_tiles(region)?;
+        let start_index = region_tiles
+            .get_tile_index(start.layer_offset())
+            .ok_or("position not in region")?;
+        let start_adjacency = match region_tiles
+            .doors()
+            .iter()
+            .find(|region_door| region_door.index() == start_index)
+        {
+            Some(region_door) => region_door.adjacency(),
+            None => self.graph.tile_adjacency(region, start),
+        };
+
+        let mut cost_field = CostField::new(region_tiles.size());
+        cost_field.generate::<ModifiedFlowPolicy>(
+            &ModifiedFlowPolicy::new(start.layer(), modifiers),
+            region_tiles,
+            start_index,
+            start.layer_offset(),
+            start_adjacency,
+        );
+
+        let path = || SearchEntryPath::CostField {
+            region,
+            start,
+            cost_field: None,
+        };
+
+        for region_door in region_tiles.doors() {
+            if region_door.index() == start_index || !cost_field.contains(region_door.index()) {
+                continue;
+            }
+
+            f(
+                SearchNodeId::Door(region_door.door()),
+                TilePosition::from((start.layer(), region_door.position())),
+                path(),
+                cost_field[region_door.index()],
+            );
+        }
+
+        for region_stairs in region_tiles.stairs() {
+            if !cost_field.contains(region_stairs.index()) {
+                continue;
+            }
+
+            let stairs_position = TilePosition::from((start.layer(), region_stairs.position()));
+            f(
+                SearchNodeId::Stairs(region, stairs_position),
+                stairs_position,
+                path(),
+                cost_field[region_stairs.index()],
+            );
+        }
+
+        if goal_id.in_region(region) {
+            let goal_index = region_tiles
+                .get_tile_index(goal.layer_offset())
+                .ok_or("goal not in region")?;
+            if cost_field.contains(goal_index) {
+                f(goal_id, goal, path(), cost_field[goal_index]);
+            }
+        }
+
+        Ok(())
+    }
+
     fn collect_path(
         &self,
         mut map: HashMap<SearchNodeId, SearchEntry>,
         goal: SearchNodeId,
+        modifiers: &SearchModifiers,
     ) -> Result<Path> {
         let mut steps = Vec::new();
 
@@ -365,21 +960,64 @@ impl PathParam<'_, '_> {
                     flow_field,
                     goal: entry.position,
                 },
+                SearchEntryPath::StairsFlowField { region, flow_field } => {
+                    PathStep::StairsFlowField {
+                        region,
+                        flow_field,
+                        goal: entry.position,
+                    }
+                }
+                SearchEntryPath::Stairs { from } => PathStep::Stairs {
+                    from,
+                    to: entry.position,
+                },
                 SearchEntryPath::CostField {
                     region,
                     start: _,
                     cost_field: Some(cost_field),
-                } => PathStep::RegionCostField { region, cost_field },
+                } => PathStep::RegionCostField {
+                    region,
+                    version: self.graph.region_tiles(region)?.version(),
+                    cost_field,
+                },
                 SearchEntryPath::CostField {
                     region,
                     start,
                     cost_field: None,
-                } => PathStep::RegionCostField {
-                    region: region,
-                    cost_field: self
-                        .generate_cost_field_path(region, start, entry.position)?
-                        .0,
-                },
+                } => {
+                    let version = self.graph.region_tiles(region)?.version();
+                    if current == goal
+                        && modifiers.modified(region).is_none()
+                        && let Some(cost_field) = self.goal_field(region, entry.position)
+                    {
+                        PathStep::RegionCostField {
+                            region,
+                            version,
+                            cost_field: cost_field.clone(),
+                        }
+                    } else {
+                        let (cost_field, _) = self.generate_cost_field_path(
+                            region,
+                            start,
+                            entry.position,
+                            modifiers.region(region),
+                        )?;
+                        if current == goal {
+                            PathStep::RegionCostField {
+                                region,
+                                version,
+                                cost_field: Arc::new(cost_field),
+                            }
+                        } else {
+                            PathStep::WaypointCostField {
+                                region,
+                                version,
+                                cost_field,
+                                goal: entry.position,
+                            }
+                        }
+                    }
+                }
             };
 
             steps.push(path_entry);
@@ -390,6 +1028,33 @@ impl PathParam<'_, '_> {
     }
 }
 
+impl PathAgent {
+    pub fn new(entity: Entity, elapsed: Duration) -> Self {
+        PathAgent {
+            entity,
+            staff: false,
+            elapsed,
+        }
+    }
+
+    pub fn with_staff(mut self, staff: bool) -> Self {
+        self.staff = staff;
+        self
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn is_staff(&self) -> bool {
+        self.staff
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
 fn octile_cost_heuristic(start: TileLayerOffset, goal: TileLayerOffset) -> u32 {
     octile_cost(start, goal, TileMoveSpeed::Slow)
 }
@@ -432,11 +1097,25 @@ impl Ord for SearchNode {
     }
 }
 
+impl<'m> SearchModifiers<'m> {
+    fn region(&self, region: Entity) -> CostModifiers<'m> {
+        if self.goal_region == Some(region) {
+            self.goal_modifiers
+        } else {
+            self.modifiers
+        }
+    }
+
+    fn modified(&self, region: Entity) -> Option<CostModifiers<'m>> {
+        self.modified.contains(&region).then(|| self.region(region))
+    }
+}
+
 impl SearchNodeId {
     fn in_region(&self, region: Entity) -> bool {
         match self {
             SearchNodeId::Position(r, _) => *r == region,
-            SearchNodeId::Door(_) => false,
+            SearchNodeId::Door(_) | SearchNodeId::Stairs(..) => false,
         }
     }
 }
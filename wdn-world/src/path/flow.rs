@@ -1,6 +1,6 @@
 use std::{
     array,
-    cmp::Ordering,
+    cmp::{Ordering, Reverse},
     collections::{BinaryHeap, VecDeque},
     ops::Index,
 };
@@ -11,13 +11,18 @@ use bevy_ecs::{
 };
 use bevy_log::warn;
 use bevy_math::prelude::*;
+use bevy_platform::collections::{HashMap, HashSet};
 use wdn_physics::tile::{
     adjacency::Adjacency,
     material::TileMoveSpeed,
     position::{TileLayerOffset, TilePosition},
 };
 
-use crate::path::region::{RegionTile, RegionTileIndex, RegionTiles};
+use crate::path::{
+    block::{BlockVec, BlockVecMut},
+    cost::CostModifiers,
+    region::{RegionChanges, RegionTile, RegionTileIndex, RegionTiles},
+};
 
 pub const SLOW_DIAGONAL_COST: u32 = 10;
 pub const SLOW_CARDINAL_COST: u32 = 7;
@@ -25,13 +30,14 @@ pub const MEDIUM_DIAGONAL_COST: u32 = 7;
 pub const MEDIUM_CARDINAL_COST: u32 = 5;
 pub const FAST_DIAGONAL_COST: u32 = 4;
 pub const FAST_CARDINAL_COST: u32 = 3;
+pub const STAIRS_COST: u32 = 10;
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug)]
 pub struct FlowField {
     door_position: TilePosition,
     door_index: RegionTileIndex,
     door_adjacency: Adjacency,
-    flow: Vec<Dir2>,
+    flow: BlockVec<Dir2>,
     costs: CostField,
 }
 
@@ -48,7 +54,13 @@ pub struct FlowFieldEntry {
 
 #[derive(Debug, Clone)]
 pub struct CostField {
-    costs: Vec<u32>,
+    costs: BlockVec<u32>,
+    reachable: usize,
+}
+
+struct CostFieldMut<'a> {
+    costs: BlockVecMut<'a, u32>,
+    reachable: &'a mut usize,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -71,6 +83,10 @@ pub trait CostPolicy {
     fn priority(&self, position: TileLayerOffset, cost: u32) -> u32;
 
     fn finished(&self, index: RegionTileIndex) -> bool;
+
+    fn tile_cost(&self, _position: TileLayerOffset) -> u32 {
+        0
+    }
 }
 
 pub trait CostPolicyQueue {
@@ -81,13 +97,26 @@ pub trait CostPolicyQueue {
 
 pub struct FlowPolicy;
 
-pub struct PathPolicy {
+pub struct PathPolicy<'a> {
     goal: TileLayerOffset,
     goal_index: RegionTileIndex,
+    layer: Entity,
+    modifiers: CostModifiers<'a>,
+}
+
+pub struct ModifiedFlowPolicy<'a> {
+    layer: Entity,
+    modifiers: CostModifiers<'a>,
+}
+
+// Region tiles as they were before the latest region update.
+struct PreviousTiles<'a> {
+    tiles: &'a RegionTiles,
+    previous_tiles: &'a HashMap<RegionTileIndex, RegionTile>,
 }
 
 pub fn update_flow_fields(
-    regions: Query<&RegionTiles>,
+    regions: Query<(&RegionTiles, Option<&RegionChanges>)>,
     mut flow_fields: Query<(&ChildOf, &mut FlowField)>,
     added_flow_fields: Res<AddedFlowFields>,
 ) {
@@ -96,8 +125,11 @@ pub fn update_flow_fields(
     flow_fields
         .par_iter_many_unique_mut(added_flow_fields.iter())
         .for_each(|(parent, mut flow)| {
-            let tiles = regions.get(parent.parent()).expect("invalid region");
-            flow.populate_flow(tiles);
+            let (tiles, changes) = regions.get(parent.parent()).expect("invalid region");
+            match changes {
+                Some(changes) => flow.repair(tiles, changes),
+                None => flow.populate_flow(tiles),
+            }
         });
 
     let elapsed = start.elapsed();
@@ -147,7 +179,7 @@ impl FlowField {
             door_position,
             door_index,
             door_adjacency,
-            flow: Vec::with_capacity(size),
+            flow: BlockVec::new(),
             costs: CostField::new(size),
         }
     }
@@ -162,7 +194,7 @@ impl FlowField {
             door_position,
             door_index,
             door_adjacency,
-            flow: Vec::default(),
+            flow: BlockVec::default(),
             costs,
         }
     }
@@ -174,7 +206,7 @@ impl FlowField {
     pub fn iter(&self) -> impl Iterator<Item = (RegionTileIndex, FlowFieldEntry)> {
         (0..self.costs.len()).filter_map(move |index| {
             let index = index as RegionTileIndex;
-            if index == self.door_index {
+            if index == self.door_index || !self.costs.contains(index) {
                 return None;
             }
 
@@ -197,7 +229,7 @@ impl FlowField {
     }
 
     pub fn len(&self) -> usize {
-        self.costs.len() - 1
+        self.costs.reachable().saturating_sub(1)
     }
 
     pub fn populate_flow(&mut self, tiles: &RegionTiles) {
@@ -209,25 +241,79 @@ impl FlowField {
             self.door_adjacency,
         );
 
+        self.populate_directions(tiles);
+    }
+
+    pub fn repair(&mut self, tiles: &RegionTiles, changes: &RegionChanges) {
+        let door_index = tiles
+            .get_tile_index(self.door_position.layer_offset())
+            .expect("door not in region");
+        let door_adjacency = tiles[door_index].adjacency();
+
+        if self.flow.is_empty()
+            || door_index != self.door_index
+            || door_adjacency != self.door_adjacency
+        {
+            self.door_index = door_index;
+            self.door_adjacency = door_adjacency;
+            self.costs = CostField::new(tiles.size());
+            self.populate_flow(tiles);
+            return;
+        }
+
+        let modified = self.costs.repair(
+            tiles,
+            changes.previous_tiles(),
+            changes.added_tiles(),
+            door_index,
+            door_adjacency,
+        );
+
+        self.flow.resize(tiles.size(), Dir2::NORTH);
+        for &index in changes.previous_tiles().keys() {
+            self.update_direction(tiles, index);
+        }
+
+        for index in modified {
+            self.update_direction(tiles, index);
+            if let Some(tile) = tiles.get(index) {
+                for neighbor in [tile.north(), tile.east(), tile.south(), tile.west()]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.update_direction(tiles, neighbor);
+                }
+            }
+        }
+    }
+
+    fn populate_directions(&mut self, tiles: &RegionTiles) {
+        self.flow.clear();
+        self.flow.resize(tiles.size(), Dir2::NORTH);
+
         for (index, tile) in tiles.tiles() {
-            let position = tile.position();
-            if position == self.door_position.layer_offset() {
-                self.flow.push(Dir2::NORTH);
+            if index == self.door_index {
                 continue;
             }
 
             debug_assert!(
                 self.costs.contains(index),
                 "{:?} is unreachable from door {:?}",
-                position,
+                tile.position(),
                 self.door_position
             );
 
-            let dir = self.costs.flow_vector(index, tile);
-            self.flow.push(dir);
+            self.flow[index as usize] = self.costs.flow_vector(index, tile);
         }
+    }
 
-        debug_assert_eq!(self.flow.len(), tiles.size());
+    fn update_direction(&mut self, tiles: &RegionTiles, index: RegionTileIndex) {
+        self.flow[index as usize] = match tiles.get(index) {
+            Some(tile) if index != self.door_index && self.costs.contains(index) => {
+                self.costs.flow_vector(index, tile)
+            }
+            _ => Dir2::NORTH,
+        };
     }
 }
 
@@ -266,7 +352,8 @@ impl FlowFieldEntry {
 impl CostField {
     pub fn new(size: usize) -> Self {
         Self {
-            costs: vec![u32::MAX; size],
+            costs: BlockVec::from_elem(u32::MAX, size),
+            reachable: 0,
         }
     }
 
@@ -280,25 +367,33 @@ impl CostField {
     ) {
         debug_assert_eq!(tiles.size(), self.costs.len());
 
+        let mut costs = CostFieldMut {
+            costs: self.costs.as_mut(),
+            reachable: &mut self.reachable,
+        };
         let mut open = S::Queue::default();
 
         let start_priority = policy.priority(start_position, 0);
-        self.insert(start, 0);
+        costs.insert(start, 0);
         open.push(CostNode::new(start, start_adjacency, 0, start_priority));
 
         while let Some(node) = open.pop() {
-            debug_assert!(self.contains(node.index));
+            debug_assert!(costs[node.index] != u32::MAX);
 
             if policy.finished(node.index) {
                 break;
             }
 
-            if self[node.index] < node.cost {
+            if costs[node.index] < node.cost {
                 continue;
             }
 
-            node.visit_neighbors(tiles, |neighbor, cost| {
+            node.visit_neighbors(tiles, |neighbor, cost, corners| {
                 let neighbor_data = &tiles[neighbor];
+                let tile_cost = corners.into_iter().flatten().fold(
+                    policy.tile_cost(neighbor_data.position()),
+                    |tile_cost, corner| tile_cost.max(policy.tile_cost(tiles[corner].position())),
+                );
 
                 let adjacency = if neighbor_data.is_door() {
                     Adjacency::NONE
@@ -306,16 +401,119 @@ impl CostField {
                     neighbor_data.adjacency()
                 };
 
-                let new_cost = self[node.index] + cost;
+                let new_cost = costs[node.index] + cost + tile_cost;
                 let priority = policy.priority(neighbor_data.position(), new_cost);
 
-                if self.insert(neighbor, new_cost) {
+                if costs.insert(neighbor, new_cost) {
                     open.push(CostNode::new(neighbor, adjacency, new_cost, priority));
                 }
             });
         }
     }
 
+    pub fn repair(
+        &mut self,
+        tiles: &RegionTiles,
+        previous_tiles: &HashMap<RegionTileIndex, RegionTile>,
+        added_tiles: &[RegionTileIndex],
+        start: RegionTileIndex,
+        start_adjacency: Adjacency,
+    ) -> Vec<RegionTileIndex> {
+        let removed = (tiles.size()..self.costs.len())
+            .filter(|&index| self.costs[index] != u32::MAX)
+            .count();
+        self.reachable -= removed;
+        self.costs.resize(tiles.size(), u32::MAX);
+
+        // A cost only needs rebuilding if every shortest path to it went through a changed tile.
+        // Candidates are checked in cost order, so the tiles they could be reached from have
+        // already been decided.
+        let previous = PreviousTiles {
+            tiles,
+            previous_tiles,
+        };
+        let added: HashSet<RegionTileIndex> = added_tiles.iter().copied().collect();
+        let mut affected = HashSet::new();
+        let mut candidates = BinaryHeap::new();
+        for &index in previous_tiles.keys() {
+            if self.contains(index) {
+                candidates.push(Reverse((self[index], index)));
+                self.visit_tight_neighbors(&previous, index, start, start_adjacency, |neighbor| {
+                    candidates.push(Reverse((self[neighbor], neighbor)));
+                });
+            }
+        }
+
+        let mut decided = HashSet::new();
+        while let Some(Reverse((_, index))) = candidates.pop() {
+            if !decided.insert(index) || index == start {
+                continue;
+            }
+
+            let supported = tiles.get(index).is_some()
+                && !added.contains(&index)
+                && self.is_supported(tiles, &affected, index, start, start_adjacency);
+            if !supported {
+                affected.insert(index);
+                self.visit_tight_neighbors(&previous, index, start, start_adjacency, |neighbor| {
+                    candidates.push(Reverse((self[neighbor], neighbor)));
+                });
+            }
+        }
+
+        for &index in &affected {
+            if self.contains(index) {
+                self.costs[index as usize] = u32::MAX;
+                self.reachable -= 1;
+            }
+        }
+
+        // Affected tiles are rebuilt from the costs around them, and changed tiles are
+        // expanded again in case they opened a shorter path.
+        let mut open = BinaryHeap::new();
+        let mut seeded = HashSet::new();
+        let mut seed = |costs: &Self, index: RegionTileIndex| {
+            if tiles.get(index).is_some() && costs.contains(index) && seeded.insert(index) {
+                let adjacency = expand_adjacency(tiles, index, start, start_adjacency);
+                open.push(CostNode::new(index, adjacency, costs[index], costs[index]));
+            }
+        };
+
+        for &index in previous_tiles.keys().chain(added_tiles) {
+            seed(self, index);
+        }
+
+        for &index in &affected {
+            let Some(tile) = tiles.get(index) else {
+                continue;
+            };
+
+            for neighbor in neighbor_positions(tile.position()) {
+                if let Some(neighbor) = tiles.get_tile_index(neighbor) {
+                    seed(self, neighbor);
+                }
+            }
+        }
+
+        let mut modified: Vec<_> = affected.into_iter().collect();
+        while let Some(node) = open.pop() {
+            if self[node.index] < node.cost {
+                continue;
+            }
+
+            node.visit_neighbors(tiles, |neighbor, cost, _| {
+                let new_cost = node.cost + cost;
+                if self.insert(neighbor, new_cost) {
+                    modified.push(neighbor);
+                    let adjacency = expand_adjacency(tiles, neighbor, start, start_adjacency);
+                    open.push(CostNode::new(neighbor, adjacency, new_cost, new_cost));
+                }
+            });
+        }
+
+        modified
+    }
+
     pub fn flow_vector(&self, index: RegionTileIndex, tile: &RegionTile) -> Dir2 {
         let cost = self[index];
 
@@ -395,9 +593,12 @@ impl CostField {
     }
 
     fn insert(&mut self, index: RegionTileIndex, cost: u32) -> bool {
-        let entry = &mut self.costs[index as usize];
-        if cost < *entry {
-            *entry = cost;
+        let previous = self.costs[index as usize];
+        if cost < previous {
+            self.costs[index as usize] = cost;
+            if previous == u32::MAX {
+                self.reachable += 1;
+            }
             true
         } else {
             false
@@ -408,9 +609,59 @@ impl CostField {
         self.costs.len()
     }
 
+    pub fn reachable(&self) -> usize {
+        self.reachable
+    }
+
     fn flow_delta(&self, neighbor: RegionTileIndex, cost: u32) -> Option<u32> {
         cost.checked_sub(self[neighbor])
     }
+
+    fn visit_tight_neighbors<T, F>(
+        &self,
+        tiles: &T,
+        index: RegionTileIndex,
+        start: RegionTileIndex,
+        start_adjacency: Adjacency,
+        mut f: F,
+    ) where
+        T: Index<RegionTileIndex, Output = RegionTile>,
+        F: FnMut(RegionTileIndex),
+    {
+        let adjacency = expand_adjacency(tiles, index, start, start_adjacency);
+        CostNode::new(index, adjacency, self[index], self[index]).visit_neighbors(
+            tiles,
+            |neighbor, cost, _| {
+                if self[neighbor] != u32::MAX && self[neighbor] == self[index] + cost {
+                    f(neighbor);
+                }
+            },
+        );
+    }
+
+    fn is_supported(
+        &self,
+        tiles: &RegionTiles,
+        affected: &HashSet<RegionTileIndex>,
+        index: RegionTileIndex,
+        start: RegionTileIndex,
+        start_adjacency: Adjacency,
+    ) -> bool {
+        neighbor_positions(tiles[index].position()).any(|neighbor| {
+            let Some(neighbor) = tiles.get_tile_index(neighbor) else {
+                return false;
+            };
+            if !self.contains(neighbor) || affected.contains(&neighbor) {
+                return false;
+            }
+
+            let mut supported = false;
+            self.visit_tight_neighbors(tiles, neighbor, start, start_adjacency, |tight| {
+                supported |= tight == index;
+            });
+            supported
+        })
+    }
 }
 
 impl Index<RegionTileIndex> for CostField {
@@ -421,6 +672,39 @@ impl Index<RegionTileIndex> for CostField {
     }
 }
 
+impl CostFieldMut<'_> {
+    fn insert(&mut self, index: RegionTileIndex, cost: u32) -> bool {
+        let entry = &mut self.costs[index as usize];
+        if cost < *entry {
+            if *entry == u32::MAX {
+                *self.reachable += 1;
+            }
+            *entry = cost;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Index<RegionTileIndex> for CostFieldMut<'_> {
+    type Output = u32;
+
+    fn index(&self, index: RegionTileIndex) -> &Self::Output {
+        &self.costs[index as usize]
+    }
+}
+
+impl Index<RegionTileIndex> for PreviousTiles<'_> {
+    type Output = RegionTile;
+
+    fn index(&self, index: RegionTileIndex) -> &Self::Output {
+        self.previous_tiles
+            .get(&index)
+            .unwrap_or(&self.tiles[index])
+    }
+}
+
 impl CostNode {
     fn new(index: RegionTileIndex, adjacency: Adjacency, cost: u32, priority: u32) -> Self {
         CostNode {
@@ -431,9 +715,10 @@ impl CostNode {
         }
     }
 
-    fn visit_neighbors<F>(&self, tiles: &RegionTiles, mut f: F)
+    fn visit_neighbors<T, F>(&self, tiles: &T, mut f: F)
     where
-        F: FnMut(RegionTileIndex, u32),
+        T: Index<RegionTileIndex, Output = RegionTile>,
+        F: FnMut(RegionTileIndex, u32, [Option<RegionTileIndex>; 2]),
     {
         let tile = &tiles[self.index];
         let (cardinal_cost, diagonal_cost) = move_cost(tile.move_speed());
@@ -441,25 +726,25 @@ impl CostNode {
         if self.adjacency.contains(Adjacency::NORTH)
             && let Some(north) = tile.north()
         {
-            f(north, cardinal_cost);
+            f(north, cardinal_cost, [None, None]);
         }
 
         if self.adjacency.contains(Adjacency::SOUTH)
             && let Some(south) = tile.south()
         {
-            f(south, cardinal_cost);
+            f(south, cardinal_cost, [None, None]);
         }
 
         if self.adjacency.contains(Adjacency::EAST)
             && let Some(east) = tile.east()
         {
-            f(east, cardinal_cost);
+            f(east, cardinal_cost, [None, None]);
         }
 
         if self.adjacency.contains(Adjacency::WEST)
             && let Some(west) = tile.west()
         {
-            f(west, cardinal_cost);
+            f(west, cardinal_cost, [None, None]);
         }
 
         if self
@@ -468,7 +753,7 @@ impl CostNode {
             && let Some(north) = tile.north()
             && let Some(north_east) = tiles[north].east()
         {
-            f(north_east, diagonal_cost);
+            f(north_east, diagonal_cost, [Some(north), tile.east()]);
         }
 
         if self
@@ -477,7 +762,7 @@ impl CostNode {
             && let Some(east) = tile.east()
             && let Some(south_east) = tiles[east].south()
         {
-            f(south_east, diagonal_cost);
+            f(south_east, diagonal_cost, [Some(east), tile.south()]);
         }
 
         if self
@@ -486,7 +771,7 @@ impl CostNode {
             && let Some(south) = tile.south()
             && let Some(south_west) = tiles[south].west()
         {
-            f(south_west, diagonal_cost);
+            f(south_west, diagonal_cost, [Some(south), tile.west()]);
         }
 
         if self
@@ -495,7 +780,7 @@ impl CostNode {
             && let Some(west) = tile.west()
             && let Some(north_west) = tiles[west].north()
         {
-            f(north_west, diagonal_cost);
+            f(north_west, diagonal_cost, [Some(west), tile.north()]);
         }
     }
 }
@@ -583,13 +868,24 @@ impl CostPolicy for FlowPolicy {
     }
 }
 
-impl PathPolicy {
+impl<'a> PathPolicy<'a> {
     pub fn new(goal: TileLayerOffset, goal_index: RegionTileIndex) -> Self {
-        Self { goal, goal_index }
+        Self {
+            goal,
+            goal_index,
+            layer: Entity::PLACEHOLDER,
+            modifiers: CostModifiers::default(),
+        }
+    }
+
+    pub fn with_modifiers(mut self, layer: Entity, modifiers: CostModifiers<'a>) -> Self {
+        self.layer = layer;
+        self.modifiers = modifiers;
+        self
     }
 }
 
-impl CostPolicy for PathPolicy {
+impl CostPolicy for PathPolicy<'_> {
     type Queue = BinaryHeap<CostNode>;
 
     fn priority(&self, position: TileLayerOffset, cost: u32) -> u32 {
@@ -599,6 +895,38 @@ impl CostPolicy for PathPolicy {
     fn finished(&self, index: RegionTileIndex) -> bool {
         index == self.goal_index
     }
+
+    fn tile_cost(&self, position: TileLayerOffset) -> u32 {
+        if self.modifiers.is_empty() {
+            0
+        } else {
+            self.modifiers
+                .cost(TilePosition::from((self.layer, position)))
+        }
+    }
+}
+
+impl<'a> ModifiedFlowPolicy<'a> {
+    pub fn new(layer: Entity, modifiers: CostModifiers<'a>) -> Self {
+        Self { layer, modifiers }
+    }
+}
+
+impl CostPolicy for ModifiedFlowPolicy<'_> {
+    type Queue = BinaryHeap<CostNode>;
+
+    fn priority(&self, _position: TileLayerOffset, cost: u32) -> u32 {
+        cost
+    }
+
+    fn finished(&self, _index: RegionTileIndex) -> bool {
+        false
+    }
+
+    fn tile_cost(&self, position: TileLayerOffset) -> u32 {
+        self.modifiers
+            .cost(TilePosition::from((self.layer, position)))
+    }
 }
 
 impl CostPolicyQueue for BinaryHeap<CostNode> {
@@ -611,6 +939,37 @@ impl CostPolicyQueue for BinaryHeap<CostNode> {
     }
 }
 
+fn expand_adjacency<T: Index<RegionTileIndex, Output = RegionTile>>(
+    tiles: &T,
+    index: RegionTileIndex,
+    start: RegionTileIndex,
+    start_adjacency: Adjacency,
+) -> Adjacency {
+    if index == start {
+        start_adjacency
+    } else if tiles[index].is_door() {
+        Adjacency::NONE
+    } else {
+        tiles[index].adjacency()
+    }
+}
+
+fn neighbor_positions(position: TileLayerOffset) -> impl Iterator<Item = TileLayerOffset> {
+    let north = position.north();
+    let south = position.south();
+    [
+        north,
+        north.east(),
+        position.east(),
+        south.east(),
+        south,
+        south.west(),
+        position.west(),
+        north.west(),
+    ]
+    .into_iter()
+}
+
 fn flow_tiebreak(a_flow: &mut Option<u32>, b_flow: &mut Option<u32>) {
     if let (Some(a), Some(b)) = (*a_flow, *b_flow) {
         if b > a {
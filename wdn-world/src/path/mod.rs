@@ -1,8 +1,13 @@
+pub mod block;
+pub mod cache;
+pub mod cost;
 pub mod door;
 pub mod find;
 pub mod flow;
 pub mod region;
+pub mod request;
 pub mod section;
+pub mod snapshot;
 #[cfg(test)]
 pub mod tests;
 
@@ -15,16 +20,23 @@ use wdn_physics::tile::storage::TileChunk;
 use crate::{
     WorldSystems,
     path::{
+        cache::{PathCache, on_remove_cache_region, update_path_cache},
         door::{on_remove_region, update_door_regions},
         flow::{AddedFlowFields, clear_added_flow_fields, flow_fields_added, update_flow_fields},
         region::{
-            AddedRegions, clear_added_regions, on_add_region, regions_added, update_region_doors,
-            update_region_tiles, update_regions,
+            AddedRegions, clear_added_regions, clear_region_changes, on_add_region, regions_added,
+            update_region_doors, update_region_tiles, update_regions,
         },
+        request::PathBudget,
         section::{
             TileChunkSectionChanges, TileChunkSections, chunk_sections_changed,
             update_chunk_sections,
         },
+        snapshot::{
+            PathSnapshot, on_remove_snapshot_chunk, on_remove_snapshot_door,
+            on_remove_snapshot_door_access, on_remove_snapshot_flow_field,
+            on_remove_snapshot_layer, on_remove_snapshot_region, update_path_snapshot,
+        },
     },
 };
 
@@ -36,7 +48,10 @@ impl Plugin for PathPlugin {
 
         app.init_resource::<TileChunkSectionChanges>()
             .init_resource::<AddedRegions>()
-            .init_resource::<AddedFlowFields>();
+            .init_resource::<AddedFlowFields>()
+            .init_resource::<PathSnapshot>()
+            .init_resource::<PathBudget>()
+            .init_resource::<PathCache>();
 
         app.add_systems(
             FixedUpdate,
@@ -50,11 +65,13 @@ impl Plugin for PathPlugin {
                         (update_flow_fields, clear_added_flow_fields)
                             .chain()
                             .run_if(flow_fields_added),
-                        (update_door_regions, clear_added_regions).chain(),
+                        (update_door_regions, update_path_cache, clear_added_regions).chain(),
                     ),
+                    clear_region_changes,
                 )
                     .chain()
                     .run_if(regions_added),
+                update_path_snapshot,
             )
                 .chain()
                 .in_set(WorldSystems::UpdateRegions),
@@ -72,5 +89,47 @@ impl Plugin for PathPlugin {
                 "Observer({})",
                 type_name_of_val(&on_remove_region)
             )));
+        app.world_mut()
+            .add_observer(on_remove_cache_region)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_cache_region)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_region)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_region)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_chunk)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_chunk)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_flow_field)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_flow_field)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_door)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_door)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_door_access)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_door_access)
+            )));
+        app.world_mut()
+            .add_observer(on_remove_snapshot_layer)
+            .insert(Name::new(format!(
+                "Observer({})",
+                type_name_of_val(&on_remove_snapshot_layer)
+            )));
     }
 }
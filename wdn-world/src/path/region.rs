@@ -1,14 +1,16 @@
-use std::{collections::VecDeque, ops::Index, u32};
+use std::{collections::VecDeque, mem, ops::Index, sync::Arc};
 
 use bevy_ecs::{
-    entity::{EntityHashSet, hash_set},
+    entity::{EntityHashMap, EntityHashSet, hash_set},
     prelude::*,
 };
 use bevy_log::error;
-use bevy_platform::collections::{HashMap, HashSet};
+use bevy_math::IVec2;
+use bevy_platform::collections::HashMap;
 use smallvec::SmallVec;
 use tracing::info;
 use wdn_physics::tile::{
+    CHUNK_SIZE, CHUNK_SIZE_SQUARED,
     adjacency::Adjacency,
     index::TileIndex,
     material::{TileKind, TileMoveSpeed},
@@ -17,6 +19,7 @@ use wdn_physics::tile::{
 };
 
 use crate::path::{
+    block::BlockVec,
     flow::{AddedFlowFields, FlowField},
     section::{TileChunkSectionChanges, TileChunkSections},
 };
@@ -25,20 +28,46 @@ use crate::path::{
 #[require(RegionTiles)]
 pub struct Region {
     layer: Entity,
-    sections: SmallVec<[(Entity, TileLayerOffset); 2]>,
+    sections: HashMap<TileLayerOffset, Entity>,
     outside: bool,
 }
 
 #[derive(Component, Debug, Default)]
+pub struct RegionChanges {
+    removed_tiles: Vec<TileLayerOffset>,
+    added_sections: Vec<(Entity, TileLayerOffset)>,
+    changed_sections: Vec<(Entity, TileLayerOffset)>,
+    previous_tiles: HashMap<RegionTileIndex, RegionTile>,
+    added_tiles: Vec<RegionTileIndex>,
+    removed_doors: Vec<Entity>,
+    removed_flow_fields: Vec<Entity>,
+}
+
+#[derive(Component, Clone, Debug, Default)]
 pub struct RegionTiles {
-    tiles: Vec<RegionTile>,
-    tile_index: HashMap<TileLayerOffset, RegionTileIndex>,
+    tiles: BlockVec<RegionTile>,
+    tile_index: RegionTileMap,
+    free: Vec<RegionTileIndex>,
     doors: Vec<RegionDoor>,
     stairs: Vec<RegionStairs>,
+    version: u32,
 }
 
 pub type RegionTileIndex = u32;
 
+// Tile indices stored by chunk, so a region cloned into the path snapshot shares every chunk
+// that a later edit doesn't touch.
+#[derive(Clone, Debug, Default)]
+struct RegionTileMap {
+    chunks: HashMap<IVec2, Arc<RegionTileChunk>>,
+}
+
+#[derive(Clone, Debug)]
+struct RegionTileChunk {
+    len: usize,
+    indices: [RegionTileIndex; CHUNK_SIZE_SQUARED],
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RegionTile {
     position: TileLayerOffset,
@@ -64,6 +93,7 @@ pub struct RegionDoor {
 pub struct RegionStairs {
     index: RegionTileIndex,
     position: TileLayerOffset,
+    flow_field: Entity,
 }
 
 #[derive(Default, Resource)]
@@ -71,105 +101,262 @@ pub struct AddedRegions {
     added_regions: EntityHashSet,
 }
 
+type RegionChunkQuery<'w, 's> = Query<'w, 's, (&'static TileChunk, &'static mut TileChunkSections)>;
+
+// Grows one front from each changed section in lockstep, stopping once at most one front is
+// still growing. Fronts that run out of sections have been cut off and become new regions,
+// so a split only visits the part that was cut off rather than the whole region.
+struct RegionSearch<'a, 'w, 's> {
+    chunks: &'a RegionChunkQuery<'w, 's>,
+    map: &'a TileMap,
+    invalid_sections: &'a HashMap<TilePosition, Entity>,
+    visited: &'a mut HashMap<TilePosition, usize>,
+    fronts: Vec<RegionFront>,
+    live: usize,
+    pending: usize,
+}
+
+#[derive(Default)]
+struct RegionFront {
+    root: usize,
+    queue: VecDeque<(Entity, TilePosition)>,
+    sections: Vec<(Entity, TilePosition)>,
+    regions: SmallVec<[Entity; 2]>,
+    outside: bool,
+    finished: bool,
+}
+
 pub fn update_regions(
     mut commands: Commands,
-    regions: Query<&Region>,
-    chunks: Query<(&TileChunk, &TileChunkSections)>,
+    mut regions: Query<(&mut Region, &RegionTiles)>,
+    mut chunks: RegionChunkQuery,
     mut changes: ResMut<TileChunkSectionChanges>,
     map: Res<TileMap>,
-    mut queue: Local<VecDeque<(Entity, TilePosition)>>,
     mut added_regions: ResMut<AddedRegions>,
-    mut visited_sections: Local<HashSet<TilePosition>>,
+    mut visited_sections: Local<HashMap<TilePosition, usize>>,
 ) -> Result {
     let TileChunkSectionChanges {
         ref mut removed_sections,
         ref mut invalid_sections,
+        ref mut changed_sections,
         ref mut invalid_regions,
     } = *changes;
 
     info!(
         "updating regions for {} changed or invalidated sections",
-        removed_sections.len() + invalid_sections.len()
+        removed_sections.len() + invalid_sections.len() + changed_sections.len()
     );
 
-    for &region in invalid_regions.iter() {
-        let region = regions.get(region)?;
-        for (chunk_id, section) in region.sections() {
-            if !removed_sections.contains(&section) {
-                invalid_sections.insert(section, chunk_id);
+    let mut search = RegionSearch {
+        chunks: &chunks,
+        map: &map,
+        invalid_sections,
+        visited: &mut visited_sections,
+        fronts: Vec::new(),
+        live: 0,
+        pending: invalid_sections.len(),
+    };
+
+    for (&section, &chunk_id) in invalid_sections.iter() {
+        search.seed(chunk_id, section)?;
+    }
+
+    // Sections on the other side of a removed section may no longer be connected.
+    for (&section, removed) in removed_sections.iter() {
+        for neighbor in removed.border_neighbors(section.chunk_position()) {
+            if let Some(neighbor_chunk_id) = map.get(neighbor.chunk_position())
+                && let Ok((_, neighbor_chunk_sections)) = chunks.get(neighbor_chunk_id)
+                && let Some(neighbor_section) =
+                    neighbor_chunk_sections.section_id(neighbor.chunk_offset())
+            {
+                search.seed(
+                    neighbor_chunk_id,
+                    TilePosition::from((neighbor.chunk_position(), neighbor_section)),
+                )?;
             }
         }
     }
 
-    visited_sections.reserve(invalid_sections.len());
+    while search.live > 1 || (search.live == 1 && search.pending > 0) {
+        for front in 0..search.fronts.len() {
+            if search.is_live(front) {
+                search.step(front)?;
+            }
+        }
+    }
 
-    for (&section, &chunk_id) in invalid_sections.iter() {
-        if !visited_sections.insert(section) {
-            continue;
+    let live_front = (0..search.fronts.len()).find(|&front| search.is_live(front));
+    if let Some(front) = live_front
+        && search.fronts[front].regions.is_empty()
+    {
+        while search.is_live(front) {
+            search.step(front)?;
         }
+    }
+
+    let mut despawned: EntityHashSet = invalid_regions.drain().collect();
+    let mut merged = EntityHashSet::default();
+    let mut region_changes = EntityHashMap::<RegionChanges>::default();
+    let mut keeper_sections = Vec::new();
 
-        debug_assert!(queue.is_empty());
-        queue.push_back((chunk_id, section));
+    for front in search.roots() {
+        despawned.extend(search.fronts[front].regions.iter().copied());
+    }
 
-        let mut region_sections = SmallVec::new();
-        let mut region_outside = false;
+    let keeper = match live_front.filter(|&front| search.is_live(front)) {
+        Some(front) => {
+            // The largest region reached by the remaining front absorbs the others.
+            let keeper = search.fronts[front]
+                .regions
+                .iter()
+                .copied()
+                .max_by_key(|&region| {
+                    let size = regions.get(region).map_or(0, |(_, tiles)| tiles.len());
+                    (size, region)
+                })
+                .ok_or("remaining region front has no regions")?;
+
+            despawned.remove(&keeper);
+            merged.extend(
+                search.fronts[front]
+                    .regions
+                    .iter()
+                    .copied()
+                    .filter(|&region| region != keeper),
+            );
+
+            let mut keeper_changes = RegionChanges::default();
+            let mut removed = Vec::new();
+            let mut outside = search.fronts[front].outside;
+
+            for (&section, removed_section) in removed_sections.iter() {
+                if removed_section.region() == keeper {
+                    removed.push(section.layer_offset());
+                    keeper_changes.removed_tiles.extend(
+                        removed_section.tiles().iter().map(|&offset| {
+                            TileLayerOffset::from((section.chunk_position(), offset))
+                        }),
+                    );
+                }
+            }
 
-        while let Some((current_chunk_id, current_section)) = queue.pop_front() {
-            let (current_chunk, current_chunk_sections) = chunks.get(current_chunk_id)?;
-            let current_section_data =
-                current_chunk_sections.section(current_section.chunk_offset());
+            for finished in search.roots().filter(|&root| search.fronts[root].finished) {
+                for &(chunk_id, section) in &search.fronts[finished].sections {
+                    let (_, chunk_sections) = chunks.get(chunk_id)?;
+                    let section_data = chunk_sections.section(section.chunk_offset());
+                    if section_data.region() == keeper {
+                        removed.push(section.layer_offset());
+                        keeper_changes
+                            .removed_tiles
+                            .extend(section_data.tiles().iter().map(|&offset| {
+                                TileLayerOffset::from((section.chunk_position(), offset))
+                            }));
+                    }
+                }
+            }
 
-            if !invalid_sections.contains_key(&current_section) {
-                invalid_regions.insert(current_section_data.region());
+            for &(chunk_id, section) in &search.fronts[front].sections {
+                if invalid_sections.contains_key(&section) {
+                    keeper_sections.push((chunk_id, section));
+                }
             }
 
-            region_sections.push((current_chunk_id, current_section.layer_offset()));
-
-            current_section_data.visit_neighbors(current_chunk, |neighbor| {
-                if let Some(neighbor_chunk_id) = map.get(neighbor.chunk_position()) {
-                    let (_, neighbor_chunk_sections) = chunks.get(neighbor_chunk_id)?;
-                    let neighbor_section_offset = neighbor_chunk_sections
-                        .section_id(neighbor.chunk_offset())
-                        .ok_or("neighbor section not found")?;
-                    let neighbor_section =
-                        TilePosition::from((neighbor.chunk_position(), neighbor_section_offset));
-                    if visited_sections.insert(neighbor_section) {
-                        queue.push_back((neighbor_chunk_id, neighbor_section));
+            for &region in &merged {
+                let (region, _) = regions.get(region)?;
+                outside |= region.outside;
+                for (chunk_id, section) in region.sections() {
+                    if !removed_sections.contains_key(&section)
+                        && !invalid_sections.contains_key(&section)
+                        && !search.is_finished(section)
+                    {
+                        keeper_sections.push((chunk_id, section));
                     }
-                } else {
-                    region_outside = true;
                 }
+            }
 
-                Ok(())
-            })?;
+            let (mut keeper_region, _) = regions.get_mut(keeper)?;
+            keeper_region.outside |= outside;
+            for section in removed {
+                keeper_region.sections.remove(&section);
+            }
+            for &(chunk_id, section) in &keeper_sections {
+                keeper_region
+                    .sections
+                    .insert(section.layer_offset(), chunk_id);
+                keeper_changes
+                    .added_sections
+                    .push((chunk_id, section.layer_offset()));
+            }
+
+            region_changes.insert(keeper, keeper_changes);
+            Some(keeper)
+        }
+        None => None,
+    };
+
+    for (&section, &chunk_id) in changed_sections.iter() {
+        if search.is_finished(section) {
+            continue;
+        }
+
+        let (_, chunk_sections) = chunks.get(chunk_id)?;
+        let region = chunk_sections.section(section.chunk_offset()).region();
+        if region != Entity::PLACEHOLDER
+            && !merged.contains(&region)
+            && !despawned.contains(&region)
+        {
+            region_changes
+                .entry(region)
+                .or_default()
+                .changed_sections
+                .push((chunk_id, section.layer_offset()));
         }
+    }
+
+    for front in search.roots().filter(|&root| search.fronts[root].finished) {
+        let front = &search.fronts[front];
+        let layer = front.sections[0].1.layer();
+        let region = Region {
+            layer,
+            sections: front
+                .sections
+                .iter()
+                .map(|&(chunk_id, section)| (section.layer_offset(), chunk_id))
+                .collect(),
+            outside: front.outside,
+        };
+
+        added_regions.insert(commands.spawn((region, ChildOf(layer))).id());
+    }
 
-        let region = commands
-            .spawn((
-                Region {
-                    layer: section.layer(),
-                    sections: region_sections,
-                    outside: region_outside,
-                },
-                ChildOf(section.layer()),
-            ))
-            .id();
+    for (region, changes) in region_changes {
+        commands.entity(region).insert(changes);
         added_regions.insert(region);
     }
 
-    for &region in invalid_regions.iter() {
+    for region in despawned {
         commands.entity(region).try_despawn();
     }
 
-    invalid_regions.clear();
+    if let Some(keeper) = keeper {
+        for (chunk_id, section) in keeper_sections {
+            chunks
+                .get_mut(chunk_id)?
+                .1
+                .section_mut(section.chunk_offset())
+                .set_region(keeper);
+        }
+    }
+
     invalid_sections.clear();
     removed_sections.clear();
+    changed_sections.clear();
     visited_sections.clear();
     Ok(())
 }
 
 pub fn update_region_tiles(
-    mut regions: Query<(&Region, &mut RegionTiles)>,
+    mut regions: Query<(&Region, &mut RegionTiles, Option<&mut RegionChanges>)>,
     chunks: Query<(&TileChunk, &TileChunkSections)>,
     added_regions: Res<AddedRegions>,
 ) {
@@ -180,26 +367,40 @@ pub fn update_region_tiles(
 
     regions
         .par_iter_many_unique_mut(added_regions.iter())
-        .for_each(|(region, mut region_tiles)| {
-            for (chunk_id, section_offset) in region.sections() {
-                let (chunk, chunk_sections) = chunks.get(chunk_id).expect("invalid chunk");
-                let section = chunk_sections.section(section_offset.chunk_offset());
-
-                region_tiles.reserve(section.size(), 8);
-
-                for &tile_offset in section.tiles() {
-                    let tile = chunk.get(tile_offset);
-                    let position = TileLayerOffset::from((chunk.position(), tile_offset));
-
-                    let index = region_tiles.insert_tile(position, tile);
+        .for_each(|(region, mut region_tiles, changes)| match changes {
+            Some(mut changes) => {
+                let tiles = changes
+                    .added_sections
+                    .iter()
+                    .chain(&changes.changed_sections)
+                    .flat_map(|&(chunk_id, section_offset)| {
+                        let (chunk, chunk_sections) = chunks.get(chunk_id).expect("invalid chunk");
+                        let section = chunk_sections.section(section_offset.chunk_offset());
+                        section.tiles().iter().map(move |&tile_offset| {
+                            let position = TileLayerOffset::from((chunk.position(), tile_offset));
+                            (position, chunk.get(tile_offset))
+                        })
+                    })
+                    .collect();
+
+                region_tiles
+                    .bypass_change_detection()
+                    .update(&mut changes, tiles);
+                if !changes.is_empty() {
+                    region_tiles.set_changed();
+                }
+            }
+            None => {
+                for (chunk_id, section_offset) in region.sections() {
+                    let (chunk, chunk_sections) = chunks.get(chunk_id).expect("invalid chunk");
+                    let section = chunk_sections.section(section_offset.chunk_offset());
 
-                    let door_adjacency = tile.door_adjacency();
-                    if !door_adjacency.is_empty() {
-                        region_tiles.insert_doors(position, index, door_adjacency);
-                    }
+                    region_tiles.reserve(section.size(), 8);
 
-                    if tile.kind() == TileKind::Stairs {
-                        region_tiles.insert_stairs(position, index);
+                    for &tile_offset in section.tiles() {
+                        let tile = chunk.get(tile_offset);
+                        let position = TileLayerOffset::from((chunk.position(), tile_offset));
+                        region_tiles.insert(position, tile);
                     }
                 }
             }
@@ -208,18 +409,29 @@ pub fn update_region_tiles(
 
 pub fn update_region_doors(
     mut commands: Commands,
-    mut regions: Query<(Entity, &Region, &mut RegionTiles)>,
+    mut regions: Query<(Entity, &Region, &mut RegionTiles, Option<&RegionChanges>)>,
     index: Res<TileIndex>,
     added_regions: Res<AddedRegions>,
     mut added_flow_fields: ResMut<AddedFlowFields>,
 ) {
     regions.iter_many_unique_mut(added_regions.iter()).for_each(
-        |(region_id, region, mut region_tiles)| {
+        |(region_id, region, mut region_tiles, changes)| {
+            if let Some(changes) = changes {
+                if changes.is_empty() {
+                    return;
+                }
+
+                for &flow_field in &changes.removed_flow_fields {
+                    commands.entity(flow_field).try_despawn();
+                }
+            }
+
             let RegionTiles {
                 ref mut tiles,
                 ref mut doors,
+                ref mut stairs,
                 ..
-            } = *region_tiles;
+            } = *region_tiles.bypass_change_detection();
 
             for door in doors {
                 let door_position = tiles[door.index as usize].position();
@@ -234,20 +446,32 @@ pub fn update_region_doors(
 
                 door.adjacency = door_adjacency;
                 door.door = door_id;
-                door.flow_field = commands
-                    .spawn((
-                        ChildOf(region_id),
-                        FlowField::new(
-                            TilePosition::from((region.layer(), door_position)),
-                            door.index,
-                            door_adjacency,
-                            tiles.len(),
-                        ),
-                    ))
-                    .id();
+                if door.flow_field == Entity::PLACEHOLDER {
+                    let flow_field = FlowField::new(
+                        TilePosition::from((region.layer(), door_position)),
+                        door.index,
+                        door_adjacency,
+                        tiles.len(),
+                    );
+                    door.flow_field = commands.spawn((ChildOf(region_id), flow_field)).id();
+                }
 
                 added_flow_fields.insert(door.flow_field);
             }
+
+            for stairs in stairs {
+                if stairs.flow_field == Entity::PLACEHOLDER {
+                    let flow_field = FlowField::new(
+                        TilePosition::from((region.layer(), stairs.position)),
+                        stairs.index,
+                        tiles[stairs.index as usize].adjacency(),
+                        tiles.len(),
+                    );
+                    stairs.flow_field = commands.spawn((ChildOf(region_id), flow_field)).id();
+                }
+
+                added_flow_fields.insert(stairs.flow_field);
+            }
         },
     );
 }
@@ -260,13 +484,19 @@ pub fn clear_added_regions(mut changes: ResMut<AddedRegions>) {
     changes.clear();
 }
 
+pub fn clear_region_changes(mut commands: Commands, regions: Query<Entity, With<RegionChanges>>) {
+    for region in &regions {
+        commands.entity(region).remove::<RegionChanges>();
+    }
+}
+
 pub fn on_add_region(
     trigger: On<Add, Region>,
     regions: Query<&Region>,
     mut chunks: Query<&mut TileChunkSections>,
 ) -> Result {
     let region = regions.get(trigger.entity)?;
-    for &(chunk_id, section) in &region.sections {
+    for (&section, &chunk_id) in &region.sections {
         chunks
             .get_mut(chunk_id)?
             .section_mut(section.chunk_offset())
@@ -284,7 +514,7 @@ impl Region {
     pub fn sections(&self) -> impl Iterator<Item = (Entity, TilePosition)> {
         self.sections
             .iter()
-            .map(|&(chunk_id, position)| (chunk_id, TilePosition::from((self.layer, position))))
+            .map(|(&position, &chunk_id)| (chunk_id, TilePosition::from((self.layer, position))))
     }
 
     pub fn outside(&self) -> bool {
@@ -292,11 +522,164 @@ impl Region {
     }
 }
 
+impl RegionChanges {
+    pub fn previous_tiles(&self) -> &HashMap<RegionTileIndex, RegionTile> {
+        &self.previous_tiles
+    }
+
+    pub fn added_tiles(&self) -> &[RegionTileIndex] {
+        &self.added_tiles
+    }
+
+    pub fn removed_doors(&self) -> &[Entity] {
+        &self.removed_doors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous_tiles.is_empty()
+            && self.added_tiles.is_empty()
+            && self.removed_doors.is_empty()
+            && self.removed_flow_fields.is_empty()
+    }
+}
+
+impl RegionSearch<'_, '_, '_> {
+    fn seed(&mut self, chunk_id: Entity, section: TilePosition) -> Result {
+        if self.visited.contains_key(&section) {
+            return Ok(());
+        }
+
+        let front = self.fronts.len();
+        self.fronts.push(RegionFront {
+            root: front,
+            ..Default::default()
+        });
+        self.live += 1;
+        self.visit(front, chunk_id, section)
+    }
+
+    fn visit(&mut self, front: usize, chunk_id: Entity, section: TilePosition) -> Result {
+        let (_, chunk_sections) = self.chunks.get(chunk_id)?;
+        let region = chunk_sections.section(section.chunk_offset()).region();
+
+        self.visited.insert(section, front);
+        let front = &mut self.fronts[front];
+        front.sections.push((chunk_id, section));
+
+        // New sections are expanded first, so every front has claimed them before it stops.
+        if self.invalid_sections.contains_key(&section) {
+            front.queue.push_front((chunk_id, section));
+        } else {
+            if !front.regions.contains(&region) {
+                front.regions.push(region);
+            }
+            front.queue.push_back((chunk_id, section));
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, mut front: usize) -> Result {
+        let Some((chunk_id, section)) = self.fronts[front].queue.pop_front() else {
+            self.fronts[front].finished = true;
+            self.live -= 1;
+            return Ok(());
+        };
+
+        if self.invalid_sections.contains_key(&section) {
+            self.pending -= 1;
+        }
+
+        let chunks = self.chunks;
+        let map = self.map;
+        let (chunk, chunk_sections) = chunks.get(chunk_id)?;
+        chunk_sections
+            .section(section.chunk_offset())
+            .visit_neighbors(chunk, |neighbor| {
+                let Some(neighbor_chunk_id) = map.get(neighbor.chunk_position()) else {
+                    self.fronts[front].outside = true;
+                    return Ok(());
+                };
+
+                let (_, neighbor_chunk_sections) = chunks.get(neighbor_chunk_id)?;
+                let neighbor_section_offset = neighbor_chunk_sections
+                    .section_id(neighbor.chunk_offset())
+                    .ok_or("neighbor section not found")?;
+                let neighbor_section =
+                    TilePosition::from((neighbor.chunk_position(), neighbor_section_offset));
+
+                match self.visited.get(&neighbor_section) {
+                    Some(&other) => {
+                        let other = self.root(other);
+                        if other != front {
+                            front = self.merge(front, other);
+                        }
+                        Ok(())
+                    }
+                    None => self.visit(front, neighbor_chunk_id, neighbor_section),
+                }
+            })
+    }
+
+    fn merge(&mut self, front: usize, other: usize) -> usize {
+        let (root, other) =
+            if self.fronts[front].sections.len() >= self.fronts[other].sections.len() {
+                (front, other)
+            } else {
+                (other, front)
+            };
+
+        let other_front = mem::take(&mut self.fronts[other]);
+        self.fronts[other].root = root;
+        self.live -= 1;
+
+        let root_front = &mut self.fronts[root];
+        for (chunk_id, section) in other_front.queue {
+            if self.invalid_sections.contains_key(&section) {
+                root_front.queue.push_front((chunk_id, section));
+            } else {
+                root_front.queue.push_back((chunk_id, section));
+            }
+        }
+        root_front.sections.extend(other_front.sections);
+        for region in other_front.regions {
+            if !root_front.regions.contains(&region) {
+                root_front.regions.push(region);
+            }
+        }
+        root_front.outside |= other_front.outside;
+
+        root
+    }
+
+    fn root(&self, mut front: usize) -> usize {
+        while self.fronts[front].root != front {
+            front = self.fronts[front].root;
+        }
+        front
+    }
+
+    fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.fronts.len()).filter(|&front| self.fronts[front].root == front)
+    }
+
+    fn is_live(&self, front: usize) -> bool {
+        self.fronts[front].root == front && !self.fronts[front].finished
+    }
+
+    fn is_finished(&self, section: TilePosition) -> bool {
+        self.visited
+            .get(&section)
+            .is_some_and(|&front| self.fronts[self.root(front)].finished)
+    }
+}
+
 impl RegionTiles {
     pub fn tiles(&self) -> impl Iterator<Item = (RegionTileIndex, &RegionTile)> {
         self.tiles
             .iter()
             .enumerate()
+            .filter(|(_, tile)| !tile.is_vacant())
             .map(|(index, tile)| (index as RegionTileIndex, tile))
     }
 
@@ -312,29 +695,182 @@ impl RegionTiles {
         self.tile_index.get(&offset).copied()
     }
 
+    pub fn get(&self, index: RegionTileIndex) -> Option<&RegionTile> {
+        self.tiles
+            .get(index as usize)
+            .filter(|tile| !tile.is_vacant())
+    }
+
     pub fn size(&self) -> usize {
         self.tiles.len()
     }
 
+    pub fn len(&self) -> usize {
+        self.tiles.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn door_count(&self) -> usize {
         self.doors.len()
     }
 
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
     fn reserve(&mut self, empty: usize, doors: usize) {
         self.tiles.reserve(empty + doors);
-        self.tile_index.reserve(empty + doors);
         self.doors.reserve(doors);
     }
 
-    fn insert_tile(&mut self, position: TileLayerOffset, tile: &TileData) -> RegionTileIndex {
-        let index = self.tiles.len() as RegionTileIndex;
+    fn insert(&mut self, position: TileLayerOffset, tile: &TileData) -> RegionTileIndex {
+        let index = self.insert_tile(position, tile);
 
-        let adjacency = tile.adjacency().walls().complement();
+        let door_adjacency = tile.door_adjacency();
+        if !door_adjacency.is_empty() {
+            self.insert_doors(position, index, door_adjacency);
+        }
 
-        self.tile_index.insert(position, index);
-        self.tiles.push(RegionTile {
+        if tile.kind() == TileKind::Stairs {
+            self.insert_stairs(position, index);
+        }
+
+        index
+    }
+
+    fn update(
+        &mut self,
+        changes: &mut RegionChanges,
+        mut tiles: HashMap<TileLayerOffset, &TileData>,
+    ) {
+        let mut removed = Vec::new();
+        let mut replaced = Vec::new();
+        let mut inserted = Vec::new();
+
+        for position in mem::take(&mut changes.removed_tiles) {
+            let Some(index) = self.get_tile_index(position) else {
+                continue;
+            };
+
+            match tiles.remove(&position) {
+                Some(tile) if self.tiles[index as usize].is_door() => {
+                    removed.push(index);
+                    inserted.push((position, tile));
+                }
+                Some(tile) if self.is_same_tile(index, tile) => {}
+                Some(tile) => replaced.push((index, tile)),
+                None => removed.push(index),
+            }
+        }
+
+        for (position, tile) in tiles {
+            match self.get_tile_index(position) {
+                Some(index) if self.tiles[index as usize].is_door() => {
+                    removed.push(index);
+                    inserted.push((position, tile));
+                }
+                Some(index) if self.is_same_tile(index, tile) => {}
+                Some(index) => replaced.push((index, tile)),
+                None => inserted.push((position, tile)),
+            }
+        }
+
+        // Flow fields find the costs that depended on a change from the old copies of the
+        // changed tiles and their neighbors.
+        let positions: Vec<_> = removed
+            .iter()
+            .chain(replaced.iter().map(|(index, _)| index))
+            .map(|&index| self.tiles[index as usize].position)
+            .chain(inserted.iter().map(|&(position, _)| position))
+            .collect();
+        for position in positions {
+            for neighbor in [
+                position,
+                position.north(),
+                position.east(),
+                position.south(),
+                position.west(),
+            ] {
+                if let Some(index) = self.get_tile_index(neighbor) {
+                    changes
+                        .previous_tiles
+                        .entry(index)
+                        .or_insert(self.tiles[index as usize]);
+                }
+            }
+        }
+
+        for index in removed {
+            self.remove_tile(index, changes);
+        }
+
+        for (index, tile) in replaced {
+            self.replace_tile(index, tile, changes);
+        }
+
+        for (position, tile) in inserted {
+            let index = self.insert(position, tile);
+            changes.added_tiles.push(index);
+        }
+
+        let unattached_doors: Vec<_> = self
+            .doors
+            .iter()
+            .map(|door| door.index)
+            .filter(|&index| self.tiles[index as usize].adjacency.is_empty())
+            .collect();
+        for index in unattached_doors {
+            self.remove_tile(index, changes);
+        }
+
+        changes.added_tiles.extend(
+            self.doors
+                .iter()
+                .filter(|door| door.flow_field == Entity::PLACEHOLDER)
+                .map(|door| door.index),
+        );
+
+        if !changes.is_empty() {
+            self.version += 1;
+        }
+    }
+
+    fn is_same_tile(&self, index: RegionTileIndex, tile: &TileData) -> bool {
+        let region_tile = &self.tiles[index as usize];
+        let door_adjacency = tile.door_adjacency();
+        let is_door = |link: Option<RegionTileIndex>| {
+            link.is_some_and(|link| self.tiles[link as usize].is_door())
+        };
+
+        region_tile.kind == tile.kind()
+            && region_tile.move_speed == tile.move_speed()
+            && region_tile.adjacency == tile.adjacency().walls().complement()
+            && door_adjacency.contains(Adjacency::NORTH) == is_door(region_tile.north())
+            && door_adjacency.contains(Adjacency::EAST) == is_door(region_tile.east())
+            && door_adjacency.contains(Adjacency::SOUTH) == is_door(region_tile.south())
+            && door_adjacency.contains(Adjacency::WEST) == is_door(region_tile.west())
+    }
+
+    fn allocate(&mut self, tile: RegionTile) -> RegionTileIndex {
+        match self.free.pop() {
+            Some(index) => {
+                self.tiles[index as usize] = tile;
+                index
+            }
+            None => {
+                self.tiles.push(tile);
+                (self.tiles.len() - 1) as RegionTileIndex
+            }
+        }
+    }
+
+    fn insert_tile(&mut self, position: TileLayerOffset, tile: &TileData) -> RegionTileIndex {
+        let index = self.allocate(RegionTile {
             position,
-            adjacency,
+            adjacency: tile.adjacency().walls().complement(),
             kind: tile.kind(),
             move_speed: tile.move_speed(),
             north: u32::MAX,
@@ -343,6 +879,84 @@ impl RegionTiles {
             west: u32::MAX,
         });
 
+        self.tile_index.insert(position, index);
+        self.link_tile(index);
+        index
+    }
+
+    fn replace_tile(
+        &mut self,
+        index: RegionTileIndex,
+        tile: &TileData,
+        changes: &mut RegionChanges,
+    ) {
+        let previous = self.tiles[index as usize];
+        if previous.kind == TileKind::Stairs && tile.kind() != TileKind::Stairs {
+            self.remove_stairs(index, changes);
+        }
+
+        self.unlink_tile(index);
+
+        let region_tile = &mut self.tiles[index as usize];
+        region_tile.adjacency = tile.adjacency().walls().complement();
+        region_tile.kind = tile.kind();
+        region_tile.move_speed = tile.move_speed();
+
+        self.link_tile(index);
+
+        let door_adjacency = tile.door_adjacency();
+        if !door_adjacency.is_empty() {
+            self.insert_doors(previous.position, index, door_adjacency);
+        }
+
+        if tile.kind() == TileKind::Stairs && previous.kind != TileKind::Stairs {
+            self.insert_stairs(previous.position, index);
+        }
+    }
+
+    fn remove_tile(&mut self, index: RegionTileIndex, changes: &mut RegionChanges) {
+        let tile = self.tiles[index as usize];
+        if tile.is_door()
+            && let Some(door) = self.doors.iter().position(|door| door.index == index)
+        {
+            let door = self.doors.swap_remove(door);
+            if door.door != Entity::PLACEHOLDER {
+                changes.removed_doors.push(door.door);
+            }
+            if door.flow_field != Entity::PLACEHOLDER {
+                changes.removed_flow_fields.push(door.flow_field);
+            }
+        } else if tile.kind == TileKind::Stairs {
+            self.remove_stairs(index, changes);
+        }
+
+        self.unlink_tile(index);
+        self.tile_index.remove(&tile.position);
+
+        // The slot is left behind as a wall so indices held by flow fields stay valid until it
+        // is reused.
+        let vacant = &mut self.tiles[index as usize];
+        vacant.kind = TileKind::Wall;
+        vacant.adjacency = Adjacency::NONE;
+        self.free.push(index);
+    }
+
+    fn remove_stairs(&mut self, index: RegionTileIndex, changes: &mut RegionChanges) {
+        if let Some(stairs) = self.stairs.iter().position(|stairs| stairs.index == index) {
+            let stairs = self.stairs.swap_remove(stairs);
+            if stairs.flow_field != Entity::PLACEHOLDER {
+                changes.removed_flow_fields.push(stairs.flow_field);
+            }
+        }
+    }
+
+    fn link_tile(&mut self, index: RegionTileIndex) {
+        let RegionTile {
+            position,
+            adjacency,
+            ..
+        } = self.tiles[index as usize];
+
         if adjacency.contains(Adjacency::NORTH)
             && let Some(&north_index) = self.tile_index.get(&position.north())
         {
@@ -370,8 +984,56 @@ impl RegionTiles {
             self.tiles[index as usize].west = west_index;
             self.tiles[west_index as usize].east = index;
         }
+    }
 
-        index
+    fn unlink_tile(&mut self, index: RegionTileIndex) {
+        let position = self.tiles[index as usize].position;
+
+        if let Some(&north_index) = self.tile_index.get(&position.north()) {
+            let north = &mut self.tiles[north_index as usize];
+            if north.south == index {
+                north.south = u32::MAX;
+                if north.is_door() {
+                    north.adjacency.remove(Adjacency::SOUTH);
+                }
+            }
+        }
+
+        if let Some(&east_index) = self.tile_index.get(&position.east()) {
+            let east = &mut self.tiles[east_index as usize];
+            if east.west == index {
+                east.west = u32::MAX;
+                if east.is_door() {
+                    east.adjacency.remove(Adjacency::WEST);
+                }
+            }
+        }
+
+        if let Some(&south_index) = self.tile_index.get(&position.south()) {
+            let south = &mut self.tiles[south_index as usize];
+            if south.north == index {
+                south.north = u32::MAX;
+                if south.is_door() {
+                    south.adjacency.remove(Adjacency::NORTH);
+                }
+            }
+        }
+
+        if let Some(&west_index) = self.tile_index.get(&position.west()) {
+            let west = &mut self.tiles[west_index as usize];
+            if west.east == index {
+                west.east = u32::MAX;
+                if west.is_door() {
+                    west.adjacency.remove(Adjacency::EAST);
+                }
+            }
+        }
+
+        let tile = &mut self.tiles[index as usize];
+        tile.north = u32::MAX;
+        tile.east = u32::MAX;
+        tile.south = u32::MAX;
+        tile.west = u32::MAX;
     }
 
     fn insert_doors(
@@ -422,34 +1084,87 @@ impl RegionTiles {
     }
 
     fn insert_door(&mut self, position: TileLayerOffset) -> RegionTileIndex {
-        *self.tile_index.entry(position).or_insert_with(|| {
-            let index = self.tiles.len() as RegionTileIndex;
-            self.doors.push(RegionDoor {
-                index,
-                position,
-                door: Entity::PLACEHOLDER,
-                flow_field: Entity::PLACEHOLDER,
-                adjacency: Adjacency::NONE,
-            });
-            self.tiles.push(RegionTile {
-                position,
-                adjacency: Adjacency::NONE,
-                kind: TileKind::Door,
-                move_speed: TileMoveSpeed::Medium,
-                north: u32::MAX,
-                east: u32::MAX,
-                south: u32::MAX,
-                west: u32::MAX,
-            });
-            index
-        })
+        if let Some(&index) = self.tile_index.get(&position) {
+            return index;
+        }
+
+        let index = self.allocate(RegionTile {
+            position,
+            adjacency: Adjacency::NONE,
+            kind: TileKind::Door,
+            move_speed: TileMoveSpeed::Medium,
+            north: u32::MAX,
+            east: u32::MAX,
+            south: u32::MAX,
+            west: u32::MAX,
+        });
+        self.tile_index.insert(position, index);
+        self.doors.push(RegionDoor {
+            index,
+            position,
+            door: Entity::PLACEHOLDER,
+            flow_field: Entity::PLACEHOLDER,
+            adjacency: Adjacency::NONE,
+        });
+        index
     }
 
     fn insert_stairs(&mut self, position: TileLayerOffset, index: RegionTileIndex) {
-        self.stairs.push(RegionStairs { index, position });
+        self.stairs.push(RegionStairs {
+            index,
+            position,
+            flow_field: Entity::PLACEHOLDER,
+        });
+    }
+}
+
+impl RegionTileMap {
+    fn get(&self, position: &TileLayerOffset) -> Option<&RegionTileIndex> {
+        let chunk = self.chunks.get(&chunk_key(position))?;
+        Some(&chunk.indices[position.chunk_offset().index()]).filter(|&&index| index != u32::MAX)
+    }
+
+    fn insert(&mut self, position: TileLayerOffset, index: RegionTileIndex) {
+        let chunk = self.chunks.entry(chunk_key(&position)).or_insert_with(|| {
+            Arc::new(RegionTileChunk {
+                len: 0,
+                indices: [u32::MAX; CHUNK_SIZE_SQUARED],
+            })
+        });
+        let chunk = Arc::make_mut(chunk);
+        let entry = &mut chunk.indices[position.chunk_offset().index()];
+        if *entry == u32::MAX {
+            chunk.len += 1;
+        }
+        *entry = index;
+    }
+
+    fn remove(&mut self, position: &TileLayerOffset) {
+        let key = chunk_key(position);
+        let offset = position.chunk_offset().index();
+        let Some(chunk) = self.chunks.get_mut(&key) else {
+            return;
+        };
+        if chunk.indices[offset] == u32::MAX {
+            return;
+        }
+
+        let chunk = Arc::make_mut(chunk);
+        chunk.indices[offset] = u32::MAX;
+        chunk.len -= 1;
+        if chunk.len == 0 {
+            self.chunks.remove(&key);
+        }
     }
 }
 
+fn chunk_key(position: &TileLayerOffset) -> IVec2 {
+    IVec2::new(
+        position.x().div_euclid(CHUNK_SIZE as i32),
+        position.y().div_euclid(CHUNK_SIZE as i32),
+    )
+}
+
 impl Index<RegionTileIndex> for RegionTiles {
     type Output = RegionTile;
 
@@ -475,6 +1190,10 @@ impl RegionTile {
         self.kind == TileKind::Door
     }
 
+    fn is_vacant(&self) -> bool {
+        self.kind == TileKind::Wall
+    }
+
     pub fn move_speed(&self) -> TileMoveSpeed {
         self.move_speed
     }
@@ -542,6 +1261,10 @@ impl RegionStairs {
     pub fn position(&self) -> TileLayerOffset {
         self.position
     }
+
+    pub fn flow_field(&self) -> Entity {
+        self.flow_field
+    }
 }
 
 impl AddedRegions {
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use bevy_tasks::{AsyncComputeTaskPool, Task, block_on};
+use wdn_physics::tile::position::TilePosition;
+
+use crate::path::{
+    cache::GoalField,
+    cost::{CostModifier, CostModifiers},
+    find::{Path, PathAgent},
+    snapshot::PathSnapshot,
+};
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PathBudget {
+    pub requests: usize,
+    pub results: usize,
+}
+
+#[derive(Clone)]
+pub struct PathRequest {
+    start: TilePosition,
+    goal: TilePosition,
+    agent: PathAgent,
+    modifiers: Vec<Arc<dyn CostModifier>>,
+    goal_field: Option<GoalField>,
+    generate_goal_field: bool,
+}
+
+#[derive(Debug)]
+pub struct PathTask {
+    task: Task<PathResponse>,
+}
+
+#[derive(Debug)]
+pub struct PathResponse {
+    generation: u64,
+    path: Result<Option<Path>>,
+    goal_field: Option<GoalField>,
+}
+
+impl Default for PathBudget {
+    fn default() -> Self {
+        PathBudget {
+            requests: 64,
+            results: 64,
+        }
+    }
+}
+
+impl PathRequest {
+    pub fn new(start: TilePosition, goal: TilePosition, agent: PathAgent) -> Self {
+        PathRequest {
+            start,
+            goal,
+            agent,
+            modifiers: Vec::new(),
+            goal_field: None,
+            generate_goal_field: false,
+        }
+    }
+
+    pub fn with_modifier(mut self, modifier: Arc<dyn CostModifier>) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    pub fn with_goal_field(mut self, goal_field: GoalField) -> Self {
+        self.goal_field = Some(goal_field);
+        self
+    }
+
+    pub fn with_generated_goal_field(mut self) -> Self {
+        self.generate_goal_field = true;
+        self
+    }
+
+    pub fn start(&self) -> TilePosition {
+        self.start
+    }
+
+    pub fn goal(&self) -> TilePosition {
+        self.goal
+    }
+
+    pub fn agent(&self) -> &PathAgent {
+        &self.agent
+    }
+
+    pub fn find(&self, snapshot: &PathSnapshot) -> PathResponse {
+        let modifiers: Vec<&dyn CostModifier> = self
+            .modifiers
+            .iter()
+            .map(|modifier| modifier.as_ref())
+            .collect();
+
+        let generated = if self.goal_field.is_none() && self.generate_goal_field {
+            snapshot.goal_field(self.goal)
+        } else {
+            None
+        };
+
+        let mut search = snapshot.search();
+        if let Some(goal_field) = self.goal_field.as_ref().or(generated.as_ref()) {
+            search = search.with_goal_field(goal_field);
+        }
+
+        PathResponse {
+            generation: snapshot.generation(),
+            path: search.find_path(
+                self.start,
+                self.goal,
+                &self.agent,
+                CostModifiers::new(&modifiers),
+            ),
+            goal_field: generated,
+        }
+    }
+
+    pub fn spawn(self, snapshot: &PathSnapshot) -> PathTask {
+        let snapshot = snapshot.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move { self.find(&snapshot) });
+        PathTask { task }
+    }
+}
+
+impl PathTask {
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    pub fn poll(&mut self) -> Option<PathResponse> {
+        if self.task.is_finished() {
+            Some(block_on(&mut self.task))
+        } else {
+            None
+        }
+    }
+}
+
+impl PathResponse {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn take_goal_field(&mut self) -> Option<GoalField> {
+        self.goal_field.take()
+    }
+
+    pub fn into_path(self) -> Result<Option<Path>> {
+        self.path
+    }
+}
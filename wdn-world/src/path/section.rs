@@ -1,12 +1,12 @@
 use std::fmt;
 
 use bevy_ecs::{entity::EntityHashSet, prelude::*};
-use bevy_platform::collections::{HashMap, HashSet, hash_map};
+use bevy_platform::collections::{HashMap, hash_map};
 use wdn_physics::tile::{
     CHUNK_SIZE, CHUNK_SIZE_SQUARED,
     adjacency::Adjacency,
     material::{TileKind, TileMoveSpeed},
-    position::{TileChunkOffset, TilePosition},
+    position::{TileChunkOffset, TileChunkPosition, TilePosition},
     storage::TileChunk,
 };
 
@@ -25,8 +25,9 @@ pub struct TileChunkSection {
 
 #[derive(Default, Resource)]
 pub struct TileChunkSectionChanges {
-    pub(super) removed_sections: HashSet<TilePosition>,
+    pub(super) removed_sections: HashMap<TilePosition, TileChunkSection>,
     pub(super) invalid_sections: HashMap<TilePosition, Entity>,
+    pub(super) changed_sections: HashMap<TilePosition, Entity>,
     pub(super) invalid_regions: EntityHashSet,
 }
 
@@ -45,6 +46,7 @@ pub fn update_chunk_sections(
     let TileChunkSectionChanges {
         ref mut removed_sections,
         ref mut invalid_sections,
+        ref mut changed_sections,
         ref mut invalid_regions,
     } = *changes;
 
@@ -73,8 +75,9 @@ pub fn update_chunk_sections(
                 if prev_set_entry != set_entry {
                     for section_id in prev_set_entry.invalid_sections(set_entry) {
                         if let Some(section) = chunk_sections.sections.remove(&section_id) {
-                            removed_sections.insert(TilePosition::from((position, section_id)));
                             invalid_regions.insert(section.region);
+                            removed_sections
+                                .insert(TilePosition::from((position, section_id)), section);
                         }
                     }
                 }
@@ -103,6 +106,14 @@ pub fn update_chunk_sections(
                 }
             }
 
+            // Sections that kept their tiles may still have changed walls or neighbors.
+            for &section_id in chunk_sections.sections.keys() {
+                let section = TilePosition::from((position, section_id));
+                if !invalid_sections.contains_key(&section) {
+                    changed_sections.insert(section, chunk_id);
+                }
+            }
+
             *chunk_sections.set = set;
         });
 
@@ -110,7 +121,10 @@ pub fn update_chunk_sections(
 }
 
 pub fn chunk_sections_changed(changes: Res<TileChunkSectionChanges>) -> bool {
-    if changes.removed_sections.is_empty() && changes.invalid_sections.is_empty() {
+    if changes.removed_sections.is_empty()
+        && changes.invalid_sections.is_empty()
+        && changes.changed_sections.is_empty()
+    {
         debug_assert!(changes.invalid_regions.is_empty());
         false
     } else {
@@ -195,6 +209,24 @@ impl TileChunkSection {
         })
     }
 
+    pub fn border_neighbors(
+        &self,
+        chunk_position: TileChunkPosition,
+    ) -> impl Iterator<Item = TilePosition> + '_ {
+        let edge = CHUNK_SIZE as u16 - 1;
+        self.edges().iter().flat_map(move |&offset| {
+            let position = TilePosition::from((chunk_position, offset));
+            [
+                (offset.x() == 0).then(|| position.west()),
+                (offset.x() == edge).then(|| position.east()),
+                (offset.y() == 0).then(|| position.south()),
+                (offset.y() == edge).then(|| position.north()),
+            ]
+            .into_iter()
+            .flatten()
+        })
+    }
+
     fn insert(&mut self, offset: TileChunkOffset) {
         let index = self.tiles.len();
         self.tiles.push(offset);
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use bevy_ecs::{entity::EntityHashMap, prelude::*};
+use bevy_platform::collections::HashMap;
+use wdn_physics::{
+    layer::Layer,
+    tile::{
+        CHUNK_SIZE_SQUARED,
+        adjacency::Adjacency,
+        material::TileKind,
+        position::{TileChunkPosition, TilePosition},
+        storage::TileChunk,
+    },
+};
+
+use crate::{
+    door::{Door, DoorAccess},
+    path::{
+        cache::GoalField,
+        cost::CostModifiers,
+        door::DoorRegions,
+        find::{Path, PathAgent, PathGraph, PathSearch},
+        flow::FlowField,
+        region::{Region, RegionTiles},
+        section::TileChunkSections,
+    },
+};
+
+#[derive(Resource, Clone, Default)]
+pub struct PathSnapshot {
+    data: Arc<PathSnapshotData>,
+}
+
+#[derive(Clone, Default)]
+struct PathSnapshotData {
+    generation: u64,
+    regions: EntityHashMap<(Entity, Arc<RegionTiles>)>,
+    flow_fields: EntityHashMap<Arc<FlowField>>,
+    doors: EntityHashMap<DoorRegions>,
+    door_access: EntityHashMap<DoorAccess>,
+    door_tiles: HashMap<TilePosition, Entity>,
+    chunk_regions: HashMap<TileChunkPosition, Arc<[Entity; CHUNK_SIZE_SQUARED]>>,
+    layers: EntityHashMap<(Entity, i32)>,
+}
+
+type ChangedDoorFilter = (With<Door>, Changed<DoorRegions>);
+type ChangedLayerFilter = Or<(Changed<Layer>, Changed<ChildOf>)>;
+
+pub fn update_path_snapshot(
+    mut snapshot: ResMut<PathSnapshot>,
+    regions: Query<(Entity, &Region, &RegionTiles), Changed<RegionTiles>>,
+    chunks: Query<(&TileChunk, &TileChunkSections), Changed<TileChunkSections>>,
+    flow_fields: Query<(Entity, &FlowField), Changed<FlowField>>,
+    doors: Query<(Entity, &DoorRegions, &TilePosition), ChangedDoorFilter>,
+    access: Query<(Entity, &DoorAccess), Changed<DoorAccess>>,
+    layers: Query<(Entity, &Layer, &ChildOf), ChangedLayerFilter>,
+) {
+    if regions.is_empty()
+        && chunks.is_empty()
+        && flow_fields.is_empty()
+        && doors.is_empty()
+        && access.is_empty()
+        && layers.is_empty()
+    {
+        return;
+    }
+
+    let data = snapshot.data_mut();
+
+    if !regions.is_empty() || !chunks.is_empty() || !flow_fields.is_empty() || !doors.is_empty() {
+        data.generation += 1;
+    }
+
+    for (id, region, tiles) in &regions {
+        data.regions
+            .insert(id, (region.layer(), Arc::new(tiles.clone())));
+    }
+
+    for (chunk, chunk_sections) in &chunks {
+        data.insert_chunk(chunk.position(), chunk_sections);
+    }
+
+    for (id, flow_field) in &flow_fields {
+        data.flow_fields.insert(id, Arc::new(flow_field.clone()));
+    }
+
+    for (id, door_regions, &position) in &doors {
+        data.doors.insert(id, door_regions.clone());
+        data.door_tiles.insert(position, id);
+    }
+
+    for (id, door_access) in &access {
+        data.door_access.insert(id, door_access.clone());
+    }
+
+    for (id, layer, parent) in &layers {
+        data.layers.insert(id, (parent.parent(), layer.height()));
+    }
+}
+
+pub fn on_remove_snapshot_region(
+    trigger: On<Remove, RegionTiles>,
+    mut snapshot: ResMut<PathSnapshot>,
+) {
+    let data = snapshot.data_mut();
+    data.generation += 1;
+    data.regions.remove(&trigger.entity);
+}
+
+pub fn on_remove_snapshot_chunk(
+    trigger: On<Remove, TileChunkSections>,
+    chunks: Query<&TileChunk>,
+    mut snapshot: ResMut<PathSnapshot>,
+) {
+    if let Ok(chunk) = chunks.get(trigger.entity) {
+        snapshot.data_mut().chunk_regions.remove(&chunk.position());
+    }
+}
+
+pub fn on_remove_snapshot_flow_field(
+    trigger: On<Remove, FlowField>,
+    mut snapshot: ResMut<PathSnapshot>,
+) {
+    let data = snapshot.data_mut();
+    data.generation += 1;
+    data.flow_fields.remove(&trigger.entity);
+}
+
+pub fn on_remove_snapshot_door(
+    trigger: On<Remove, DoorRegions>,
+    mut snapshot: ResMut<PathSnapshot>,
+) {
+    let data = snapshot.data_mut();
+    data.generation += 1;
+    data.doors.remove(&trigger.entity);
+    data.door_tiles.retain(|_, door| *door != trigger.entity);
+}
+
+pub fn on_remove_snapshot_door_access(
+    trigger: On<Remove, DoorAccess>,
+    mut snapshot: ResMut<PathSnapshot>,
+) {
+    snapshot.data_mut().door_access.remove(&trigger.entity);
+}
+
+pub fn on_remove_snapshot_layer(trigger: On<Remove, Layer>, mut snapshot: ResMut<PathSnapshot>) {
+    snapshot.data_mut().layers.remove(&trigger.entity);
+}
+
+impl PathSnapshot {
+    pub fn generation(&self) -> u64 {
+        self.data.generation
+    }
+
+    pub fn find_path(
+        &self,
+        start: TilePosition,
+        goal: TilePosition,
+        agent: &PathAgent,
+        modifiers: CostModifiers,
+    ) -> Result<Option<Path>> {
+        self.search().find_path(start, goal, agent, modifiers)
+    }
+
+    pub fn search(&self) -> PathSearch<'_, impl PathGraph> {
+        PathSearch::new(&*self.data)
+    }
+
+    pub fn goal_field(&self, goal: TilePosition) -> Option<GoalField> {
+        GoalField::generate(goal, &*self.data)
+    }
+
+    fn data_mut(&mut self) -> &mut PathSnapshotData {
+        Arc::make_mut(&mut self.data)
+    }
+}
+
+impl PathSnapshotData {
+    fn insert_chunk(&mut self, position: TileChunkPosition, chunk_sections: &TileChunkSections) {
+        let mut regions = [Entity::PLACEHOLDER; CHUNK_SIZE_SQUARED];
+        for section_id in chunk_sections.sections() {
+            let section = chunk_sections.section(section_id);
+            for &offset in section.tiles() {
+                regions[offset.index()] = section.region();
+            }
+        }
+
+        self.chunk_regions.insert(position, Arc::new(regions));
+    }
+}
+
+impl PathGraph for PathSnapshotData {
+    fn region_tiles(&self, region: Entity) -> Result<&RegionTiles> {
+        let (_, tiles) = self.regions.get(&region).ok_or("region not in snapshot")?;
+        Ok(tiles.as_ref())
+    }
+
+    fn flow_field(&self, flow_field: Entity) -> Result<&FlowField> {
+        let flow_field = self
+            .flow_fields
+            .get(&flow_field)
+            .ok_or("flow field not in snapshot")?;
+        Ok(flow_field.as_ref())
+    }
+
+    fn door_regions(&self, door: Entity) -> Result<&DoorRegions> {
+        Ok(self.doors.get(&door).ok_or("door not in snapshot")?)
+    }
+
+    fn door_access(&self, door: Entity) -> Option<&DoorAccess> {
+        self.door_access.get(&door)
+    }
+
+    fn tile_region(&self, position: TilePosition) -> Option<Entity> {
+        let regions = self.chunk_regions.get(&position.chunk_position())?;
+        let region = regions[position.chunk_offset().index()];
+        (region != Entity::PLACEHOLDER).then_some(region)
+    }
+
+    fn tile_door(&self, position: TilePosition) -> Option<Entity> {
+        self.door_tiles.get(&position).copied()
+    }
+
+    fn tile_adjacency(&self, region: Entity, position: TilePosition) -> Adjacency {
+        self.regions
+            .get(&region)
+            .and_then(|(_, tiles)| {
+                let index = tiles.get_tile_index(position.layer_offset())?;
+                Some(tiles[index].adjacency())
+            })
+            .unwrap_or(Adjacency::NONE)
+    }
+
+    fn is_stairs(&self, position: TilePosition) -> bool {
+        self.tile_region(position)
+            .and_then(|region| {
+                let (_, tiles) = self.regions.get(&region)?;
+                let index = tiles.get_tile_index(position.layer_offset())?;
+                Some(tiles[index].kind() == TileKind::Stairs)
+            })
+            .unwrap_or(false)
+    }
+
+    fn linked_layer(&self, layer: Entity, delta: i32) -> Option<Entity> {
+        let &(parent, height) = self.layers.get(&layer)?;
+        self.layers
+            .iter()
+            .find(|&(_, &(linked_parent, linked_height))| {
+                linked_parent == parent && linked_height == height + delta
+            })
+            .map(|(&linked_layer, _)| linked_layer)
+    }
+}
@@ -1,11 +1,17 @@
+use std::{sync::Arc, time::Duration};
+
 use approx::{AbsDiffEq, RelativeEq, assert_relative_eq};
 use bevy_app::prelude::*;
 use bevy_ecs::entity::EntityHashSet;
-use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_ecs::{
+    prelude::*,
+    system::{RunSystemOnce, SystemState},
+};
 use bevy_math::Dir2;
 
 use bevy_platform::collections::HashSet;
-use wdn_physics::layer::Layer;
+use wdn_physics::collision::filter::CollisionGroups;
+use wdn_physics::layer::{Layer, LayerStack};
 use wdn_physics::tile::CHUNK_SIZE;
 use wdn_physics::tile::adjacency::Adjacency;
 use wdn_physics::tile::index::TileIndex;
@@ -18,12 +24,17 @@ use wdn_physics::tile::{
     storage::{TileMap, TileStorage, TileStorageMut},
 };
 
-use crate::door::Door;
+use crate::door::{Door, DoorAccess, DoorSchedule};
+use crate::path::block::BlockVec;
+use crate::path::cache::{GoalField, PathCache};
+use crate::path::cost::{AvoidZone, Congestion, CostModifier, CostModifiers, DangerZones};
 use crate::path::door::DoorRegions;
-use crate::path::find::{Path, PathParam, PathStep};
-use crate::path::flow::{FlowField, FlowFieldEntry};
+use crate::path::find::{Path, PathAgent, PathMove, PathParam, PathStep};
+use crate::path::flow::{FlowField, FlowFieldEntry, MEDIUM_DIAGONAL_COST};
 use crate::path::region::RegionTiles;
+use crate::path::request::PathRequest;
 use crate::path::section::TileChunkSections;
+use crate::path::snapshot::PathSnapshot;
 
 use super::{PathPlugin, region::Region};
 
@@ -231,10 +242,11 @@ fn region_door_update2() {
     assert_eq!(new_regions.len(), 2);
 
     assert!(!new_regions.contains(&inside));
-    assert!(!new_regions.contains(&outside));
+    assert!(new_regions.contains(&outside));
 
     let new_inside = tile_region(&mut app, center).unwrap();
     let new_outside = tile_region(&mut app, TilePosition::new(layer, 5, 5)).unwrap();
+    assert_eq!(new_outside, outside);
 
     assert!(new_regions.contains(&new_inside));
     assert!(new_regions.contains(&new_outside));
@@ -1066,8 +1078,7 @@ fn region_door_merge() {
     assert!(new_regions.contains(&outside));
     assert!(new_regions.contains(&inside));
     assert_ne!(outside, inside);
-    assert_ne!(inside, west);
-    assert_ne!(inside, east);
+    assert!(inside == west || inside == east);
 
     assert_eq!(region_size(&mut app, outside), 4071);
     assert_eq!(region_size(&mut app, inside), 7);
@@ -1312,8 +1323,7 @@ fn region_merge2() {
     assert!(new_regions.contains(&outside));
     assert!(new_regions.contains(&combined));
     assert_ne!(combined, outside);
-    assert!(!new_regions.contains(&west));
-    assert!(!new_regions.contains(&east));
+    assert!(combined == west || combined == east);
 
     assert_eq!(region_size(&mut app, outside), 3975);
     assert_eq!(region_size(&mut app, combined), 73);
@@ -1405,7 +1415,7 @@ fn region_split_merge() {
     assert!(new_regions.contains(&outside));
     assert!(new_regions.contains(&nw));
     assert!(!new_regions.contains(&ne));
-    assert!(!new_regions.contains(&sw));
+    assert!(new_regions.contains(&sw));
     assert!(!new_regions.contains(&se));
 
     assert_eq!(
@@ -1416,14 +1426,10 @@ fn region_split_merge() {
         tile_region(&mut app, TilePosition::new(layer, 1, 1)).unwrap(),
         ne,
     );
-    assert_ne!(
+    assert_eq!(
         tile_region(&mut app, TilePosition::new(layer, -1, -1)).unwrap(),
         sw,
     );
-    assert_ne!(
-        tile_region(&mut app, TilePosition::new(layer, 1, -1)).unwrap(),
-        se,
-    );
     assert_eq!(
         tile_region(&mut app, TilePosition::new(layer, -1, -1)).unwrap(),
         tile_region(&mut app, TilePosition::new(layer, 1, -1)).unwrap(),
@@ -2730,23 +2736,19 @@ fn flow_update() {
 
     let new_regions = get_regions(&mut app);
     assert_eq!(new_regions.len(), 2);
-    assert!(!new_regions.contains(&inside));
+    assert!(new_regions.contains(&inside));
+    assert_eq!(tile_region(&mut app, center.south()), Some(inside));
 
     let new_flow_fields = get_flow_fields(&mut app);
     assert_eq!(new_flow_fields.len(), 2);
-    assert!(!new_flow_fields.contains(&flow_field_id));
+    assert!(new_flow_fields.contains(&flow_field_id));
+    assert_eq!(region_door_flow_field_id(&app, inside, door), flow_field_id);
 
-    let new_inside = tile_region(&mut app, center.south()).unwrap();
-    assert_ne!(new_inside, inside);
-
-    let new_flow_field_id = region_door_flow_field_id(&app, new_inside, door);
-    assert_ne!(new_flow_field_id, flow_field_id);
-
-    let new_flow_field = app.world().get::<FlowField>(new_flow_field_id).unwrap();
+    let new_flow_field = app.world().get::<FlowField>(flow_field_id).unwrap();
     assert_eq!(new_flow_field.len(), 8);
 
     assert_relative_eq!(
-        get_flow(&app, new_inside, door, center.south()),
+        get_flow(&app, inside, door, center.south()),
         flow_entry(1.0, 0.0, 25),
         epsilon = 0.01
     );
@@ -2785,28 +2787,202 @@ fn flow_speed_update() {
 
     let new_regions = get_regions(&mut app);
     assert_eq!(new_regions.len(), 2);
-    assert!(!new_regions.contains(&inside));
+    assert!(new_regions.contains(&inside));
+    assert_eq!(tile_region(&mut app, center.south()), Some(inside));
 
     let new_flow_fields = get_flow_fields(&mut app);
     assert_eq!(new_flow_fields.len(), 2);
-    assert!(!new_flow_fields.contains(&flow_field_id));
-
-    let new_inside = tile_region(&mut app, center.south()).unwrap();
-    assert_ne!(new_inside, inside);
-
-    let new_flow_field_id = region_door_flow_field_id(&app, new_inside, door);
-    assert_ne!(new_flow_field_id, flow_field_id);
+    assert!(new_flow_fields.contains(&flow_field_id));
+    assert_eq!(region_door_flow_field_id(&app, inside, door), flow_field_id);
 
-    let new_flow_field = app.world().get::<FlowField>(new_flow_field_id).unwrap();
+    let new_flow_field = app.world().get::<FlowField>(flow_field_id).unwrap();
     assert_eq!(new_flow_field.len(), 9);
 
     assert_relative_eq!(
-        get_flow(&app, new_inside, door, center.south()),
+        get_flow(&app, inside, door, center.south()),
         flow_entry(0.0, 1.0, 13),
         epsilon = 0.01
     );
 }
 
+#[test]
+fn flow_repair_wall() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 6, 4);
+    let door_position = center.with_offset(0, 4);
+    let door = set_door_tile(&mut app, door_position);
+
+    update_regions(&mut app);
+
+    for i in -2..=2 {
+        set_wall_tile(&mut app, center.with_offset(i, 1));
+    }
+    update_regions(&mut app);
+
+    assert_eq!(get_regions(&mut app).len(), 2);
+    assert_eq!(get_flow_fields(&mut app).len(), 2);
+
+    let inside = tile_region(&mut app, center).unwrap();
+    assert_flow_field_regenerated(&app, inside, door, door_position);
+
+    assert_eq!(get_flow(&app, inside, door, center).cost(), 47);
+}
+
+#[test]
+fn flow_repair_clear_wall() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 6, 4);
+    let door_position = center.with_offset(0, 4);
+    let door = set_door_tile(&mut app, door_position);
+    for i in -4..=4 {
+        set_wall_tile(&mut app, center.with_offset(i, 1));
+    }
+
+    update_regions(&mut app);
+
+    for i in -1..=1 {
+        clear_tile(&mut app, center.with_offset(i, 1));
+    }
+    update_regions(&mut app);
+
+    assert_eq!(get_regions(&mut app).len(), 2);
+    assert_eq!(get_flow_fields(&mut app).len(), 2);
+
+    let inside = tile_region(&mut app, center).unwrap();
+    assert_flow_field_regenerated(&app, inside, door, door_position);
+
+    assert_eq!(get_flow(&app, inside, door, center).cost(), 20);
+}
+
+#[test]
+fn flow_repair_speed() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 6, 4);
+    let door_position = center.with_offset(0, 4);
+    let door = set_door_tile(&mut app, door_position);
+
+    update_regions(&mut app);
+
+    for i in -1..=1 {
+        set_slow_tile(&mut app, center.with_offset(i, 2));
+        set_fast_tile(&mut app, center.with_offset(i, -2));
+    }
+    update_regions(&mut app);
+
+    let inside = tile_region(&mut app, center).unwrap();
+    assert_flow_field_regenerated(&app, inside, door, door_position);
+}
+
+#[test]
+fn flow_repair_split() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 6, 4);
+    let door_position = center.with_offset(0, 4);
+    let door = set_door_tile(&mut app, door_position);
+
+    update_regions(&mut app);
+
+    let inside = tile_region(&mut app, center).unwrap();
+
+    for i in -5..=5 {
+        set_wall_tile(&mut app, center.with_offset(i, 0));
+    }
+    update_regions(&mut app);
+
+    assert_eq!(get_regions(&mut app).len(), 3);
+    assert_eq!(get_flow_fields(&mut app).len(), 2);
+
+    let north = tile_region(&mut app, center.with_offset(0, 2)).unwrap();
+    let south = tile_region(&mut app, center.with_offset(0, -2)).unwrap();
+    assert_ne!(north, inside);
+    assert_ne!(north, south);
+    assert!(region_doors(&mut app, south).is_empty());
+
+    assert_flow_field_regenerated(&app, north, door, door_position);
+}
+
+#[test]
+fn flow_repair_keeps_flow_field() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 6, 4);
+    let door_position = center.with_offset(0, 4);
+    let door = set_door_tile(&mut app, door_position);
+
+    update_regions(&mut app);
+
+    let inside = tile_region(&mut app, center).unwrap();
+    let flow_field_id = region_door_flow_field_id(&app, inside, door);
+
+    for (position, wall) in [
+        (center.with_offset(-2, 1), true),
+        (center.with_offset(3, -2), true),
+        (center.with_offset(-2, 1), false),
+        (center.with_offset(0, 2), true),
+        (center.with_offset(-5, -3), true),
+        (center.with_offset(3, -2), false),
+    ] {
+        if wall {
+            set_wall_tile(&mut app, position);
+        } else {
+            clear_tile(&mut app, position);
+        }
+        update_regions(&mut app);
+
+        assert_eq!(
+            tile_region(&mut app, center.with_offset(1, 1)),
+            Some(inside)
+        );
+        assert_eq!(region_door_flow_field_id(&app, inside, door), flow_field_id);
+        assert_flow_field_regenerated(&app, inside, door, door_position);
+    }
+}
+
+#[test]
+fn region_split_merge_keeps_region() {
+    let (mut app, layer) = make_app();
+    let center = TilePosition::new(layer, 0, 0);
+
+    set_rect(&mut app, center, 20, 20);
+
+    update_regions(&mut app);
+
+    let inside = tile_region(&mut app, center).unwrap();
+    assert_eq!(region_size(&mut app, inside), 39 * 39);
+
+    for i in -19..=19 {
+        set_wall_tile(&mut app, center.with_offset(15, i));
+    }
+
+    update_regions(&mut app);
+
+    let east = tile_region(&mut app, center.with_offset(17, 0)).unwrap();
+    assert_ne!(east, inside);
+    assert_eq!(tile_region(&mut app, center), Some(inside));
+    assert_eq!(region_size(&mut app, inside), 34 * 39);
+    assert_eq!(region_size(&mut app, east), 4 * 39);
+
+    clear_tile(&mut app, center.with_offset(15, 0));
+
+    update_regions(&mut app);
+
+    assert!(!get_regions(&mut app).contains(&east));
+    assert_eq!(
+        tile_region(&mut app, center.with_offset(17, 0)),
+        Some(inside)
+    );
+    assert_eq!(region_size(&mut app, inside), 39 * 39 - 38);
+}
+
 #[test]
 fn path_different_layer() {
     let (mut app, layer1) = make_app();
@@ -2894,6 +3070,7 @@ fn path_single_region() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, regions[0]);
             assert!(cost_field.contains(start_index));
@@ -2958,6 +3135,7 @@ fn path_cross_region1() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, inside);
             assert!(cost_field.contains(door_index));
@@ -3052,6 +3230,7 @@ fn path_cross_region2() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, goal_region);
             assert!(cost_field.contains(goal_door_index));
@@ -3114,6 +3293,7 @@ fn path_single_region_cross_door() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, regions[0]);
             assert!(cost_field.contains(door_index));
@@ -3205,6 +3385,7 @@ fn path_single_region_cross_region() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, outside);
             assert!(cost_field.contains(door2_index));
@@ -3243,6 +3424,7 @@ fn path_start_door() {
         PathStep::RegionCostField {
             region,
             ref cost_field,
+            ..
         } => {
             assert_eq!(region, regions[0]);
             assert!(cost_field.contains(start_index));
@@ -3299,6 +3481,629 @@ fn path_goal_door() {
     }
 }
 
+#[test]
+fn path_stairs() {
+    let (mut app, _) = make_app();
+    let (lower, upper) = spawn_layer_stack(&mut app);
+
+    let start = TilePosition::new(lower, 5, 5);
+    let lower_stairs = TilePosition::new(lower, 8, 5);
+    let upper_stairs = TilePosition::new(upper, 8, 5);
+    let goal = TilePosition::new(upper, 10, 5);
+
+    set_stairs_tile(&mut app, lower_stairs);
+    set_stairs_tile(&mut app, upper_stairs);
+
+    update_regions(&mut app);
+
+    let lower_region = tile_region(&mut app, start).unwrap();
+    let upper_region = tile_region(&mut app, goal).unwrap();
+    assert_ne!(lower_region, upper_region);
+    assert_eq!(tile_region(&mut app, lower_stairs), Some(lower_region));
+    assert_eq!(tile_region(&mut app, upper_stairs), Some(upper_region));
+
+    let path = find_path(&mut app, start, goal).unwrap();
+    assert_eq!(path.cost(), 35);
+    assert_eq!(path.steps().len(), 3);
+
+    match path.steps()[2] {
+        PathStep::StairsFlowField { region, goal, .. } => {
+            assert_eq!(region, lower_region);
+            assert_eq!(goal, lower_stairs);
+        }
+        _ => panic!("expected StairsFlowField"),
+    }
+
+    match path.steps()[1] {
+        PathStep::Stairs { from, to } => {
+            assert_eq!(from, lower_stairs);
+            assert_eq!(to, upper_stairs);
+        }
+        _ => panic!("expected Stairs"),
+    }
+
+    match path.steps()[0] {
+        PathStep::RegionCostField { region, .. } => {
+            assert_eq!(region, upper_region);
+        }
+        _ => panic!("expected RegionCostField"),
+    }
+
+    app.world_mut()
+        .run_system_once(move |param: PathParam| {
+            let mut path = param
+                .find_path(start, goal, &any_agent(), CostModifiers::default())
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(
+                param.next_move(&mut path, start, &any_agent()).unwrap(),
+                Some(PathMove::Walk(Dir2::EAST))
+            );
+            assert_eq!(
+                param
+                    .next_move(&mut path, lower_stairs, &any_agent())
+                    .unwrap(),
+                Some(PathMove::Climb(upper_stairs))
+            );
+            assert_eq!(
+                param
+                    .next_move(&mut path, upper_stairs, &any_agent())
+                    .unwrap(),
+                Some(PathMove::Walk(Dir2::EAST))
+            );
+            assert_eq!(path.steps().len(), 1);
+        })
+        .unwrap();
+}
+
+#[test]
+fn path_stairs_unlinked() {
+    let (mut app, _) = make_app();
+    let (lower, upper) = spawn_layer_stack(&mut app);
+
+    let start = TilePosition::new(lower, 5, 5);
+    let goal = TilePosition::new(upper, 10, 5);
+
+    set_stairs_tile(&mut app, TilePosition::new(lower, 8, 5));
+    set_stairs_tile(&mut app, TilePosition::new(upper, 9, 5));
+
+    update_regions(&mut app);
+
+    let path = find_path(&mut app, start, goal);
+    assert!(path.is_none());
+}
+
+#[test]
+fn path_door_locked() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    let door = set_door_tile(&mut app, goal.with_offset(0, 2));
+    app.world_mut()
+        .entity_mut(door)
+        .insert(DoorAccess::locked());
+
+    update_regions(&mut app);
+
+    let staff = PathAgent::new(Entity::PLACEHOLDER, Duration::ZERO).with_staff(true);
+    assert!(find_path(&mut app, start, goal).is_none());
+    assert!(find_path_as(&mut app, start, goal, staff).is_none());
+    assert!(find_path_as(&mut app, start, goal.with_offset(0, 2), staff).is_none());
+
+    app.world_mut()
+        .get_mut::<DoorAccess>(door)
+        .unwrap()
+        .set_locked(false);
+
+    assert!(find_path(&mut app, start, goal).is_some());
+}
+
+#[test]
+fn path_door_staff_only() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    let door = set_door_tile(&mut app, goal.with_offset(0, 2));
+    app.world_mut()
+        .entity_mut(door)
+        .insert(DoorAccess::staff_only());
+
+    update_regions(&mut app);
+
+    let prisoner = PathAgent::new(Entity::PLACEHOLDER, Duration::ZERO);
+    let staff = PathAgent::new(Entity::PLACEHOLDER, Duration::ZERO).with_staff(true);
+
+    assert!(find_path_as(&mut app, start, goal, prisoner).is_none());
+    let path = find_path_as(&mut app, start, goal, staff).unwrap();
+    assert_eq!(path.cost(), 49);
+}
+
+#[test]
+fn path_door_owner() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    let owner = app.world_mut().spawn_empty().id();
+    let other = app.world_mut().spawn_empty().id();
+
+    set_square(&mut app, goal, 2);
+    let door = set_door_tile(&mut app, goal.with_offset(0, 2));
+    app.world_mut()
+        .entity_mut(door)
+        .insert(DoorAccess::owned([owner]));
+
+    update_regions(&mut app);
+
+    let owner = PathAgent::new(owner, Duration::ZERO);
+    let other = PathAgent::new(other, Duration::ZERO);
+
+    assert!(find_path_as(&mut app, start, goal, owner).is_some());
+    assert!(find_path_as(&mut app, start, goal, other).is_none());
+}
+
+#[test]
+fn path_door_schedule() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    let door = set_door_tile(&mut app, goal.with_offset(0, 2));
+    app.world_mut()
+        .entity_mut(door)
+        .insert(DoorAccess::default().with_schedule(DoorSchedule::new(
+            Duration::from_secs(100),
+            Duration::from_secs(20),
+            Duration::from_secs(40),
+        )));
+
+    update_regions(&mut app);
+
+    let agent = |secs| PathAgent::new(Entity::PLACEHOLDER, Duration::from_secs(secs));
+
+    assert!(find_path_as(&mut app, start, goal, agent(10)).is_none());
+    assert!(find_path_as(&mut app, start, goal, agent(30)).is_some());
+    assert!(find_path_as(&mut app, start, goal, agent(50)).is_none());
+    assert!(find_path_as(&mut app, start, goal, agent(130)).is_some());
+}
+
+#[test]
+fn path_door_blocked_detour() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    let near = set_door_tile(&mut app, goal.with_offset(-2, 0));
+    set_door_tile(&mut app, goal.with_offset(2, 0));
+
+    update_regions(&mut app);
+
+    let path = find_path(&mut app, start, goal).unwrap();
+    match path.steps()[1] {
+        PathStep::DoorFlowField { goal: door, .. } => assert_eq!(door, goal.with_offset(-2, 0)),
+        _ => panic!("expected DoorFlowField"),
+    }
+
+    app.world_mut()
+        .entity_mut(near)
+        .insert(DoorAccess::locked());
+
+    let path = find_path(&mut app, start, goal).unwrap();
+    match path.steps()[1] {
+        PathStep::DoorFlowField { goal: door, .. } => assert_eq!(door, goal.with_offset(2, 0)),
+        _ => panic!("expected DoorFlowField"),
+    }
+}
+
+#[test]
+fn path_avoid_zone() {
+    let (mut app, layer) = make_app();
+    let start = TilePosition::new(layer, 0, 0);
+    let goal = start.with_offset(6, 0);
+
+    clear_tile(&mut app, start);
+    update_regions(&mut app);
+
+    let path = find_path(&mut app, start, goal).unwrap();
+    assert_eq!(path.cost(), 30);
+
+    let zone = AvoidZone::new(
+        (-1..=1).map(|y| start.with_offset(3, y)),
+        CollisionGroups::all(),
+        100,
+    );
+    let path = find_path_avoiding(&mut app, start, goal, vec![zone]).unwrap();
+    assert!(path.cost() > 30);
+    assert!(path.cost() < 130);
+    assert!(matches!(path.steps(), [PathStep::RegionCostField { .. }]));
+}
+
+#[test]
+fn path_avoid_zone_door() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+    let near_door = goal.with_offset(-2, 0);
+    let far_door = goal.with_offset(2, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, near_door);
+    set_door_tile(&mut app, far_door);
+
+    update_regions(&mut app);
+
+    let other_layer = app.world_mut().spawn(Layer::default()).id();
+    let zone = AvoidZone::new(
+        [TilePosition::new(other_layer, 0, 0)],
+        CollisionGroups::all(),
+        100,
+    );
+    let path = find_path_avoiding(&mut app, start, goal, vec![zone]).unwrap();
+    match path.steps()[1] {
+        PathStep::DoorFlowField { goal: door, .. } => assert_eq!(door, near_door),
+        _ => panic!("expected DoorFlowField"),
+    }
+
+    let zone = AvoidZone::new([near_door], CollisionGroups::all(), 100);
+    let mut path = find_path_avoiding(&mut app, start, goal, vec![zone]).unwrap();
+    assert_eq!(path.steps().len(), 2);
+    match path.steps()[1] {
+        PathStep::WaypointCostField { goal: door, .. } => assert_eq!(door, far_door),
+        _ => panic!("expected WaypointCostField"),
+    }
+    assert!(matches!(path.steps()[0], PathStep::RegionCostField { .. }));
+
+    let mut state = SystemState::<PathParam>::new(app.world_mut());
+    let param = state.get(app.world()).unwrap();
+    assert!(matches!(
+        param.next_move(&mut path, start, &any_agent()).unwrap(),
+        Some(PathMove::Walk(_))
+    ));
+    assert_eq!(path.steps().len(), 2);
+    assert!(matches!(
+        param.next_move(&mut path, far_door, &any_agent()).unwrap(),
+        Some(PathMove::Walk(_))
+    ));
+    assert_eq!(path.steps().len(), 1);
+}
+
+#[test]
+fn danger_zones() {
+    let layer = Entity::from_raw_u32(1).unwrap();
+    let position = TilePosition::new(layer, 0, 0);
+
+    let mut zones = DangerZones::default();
+    zones.insert(position);
+
+    assert_eq!(zones.cost(position), DangerZones::COST);
+    assert_eq!(zones.cost(position.with_offset(2, -2)), DangerZones::COST);
+    assert_eq!(zones.cost(position.with_offset(3, 0)), 0);
+
+    zones.insert(position.with_offset(1, 0));
+    assert_eq!(zones.cost(position.with_offset(3, 0)), DangerZones::COST);
+    assert_eq!(zones.cost(position), 2 * DangerZones::COST);
+
+    zones.tick(DangerZones::DURATION / 2);
+    zones.insert(position);
+    zones.tick(DangerZones::DURATION / 2);
+    assert_eq!(zones.iter().count(), 1);
+    assert_eq!(zones.cost(position.with_offset(3, 0)), 0);
+
+    zones.tick(DangerZones::DURATION);
+    assert!(zones.is_empty());
+}
+
+#[test]
+fn congestion_tiles() {
+    let layer = Entity::from_raw_u32(1).unwrap();
+    let origin = TilePosition::new(layer, 0, 0);
+    let near = origin.with_offset(Congestion::RADIUS, -2);
+    let far = origin.with_offset(Congestion::RADIUS + 1, 0);
+
+    let alone = Congestion::new(Congestion::occupancy([origin, far]), origin);
+    assert!(alone.is_empty());
+    assert_eq!(alone.cost(origin), 0);
+
+    let crowded = Congestion::new(Congestion::occupancy([origin, near, near, far]), origin);
+    assert!(!crowded.is_empty());
+    assert_eq!(crowded.tiles().collect::<Vec<_>>(), vec![near]);
+    assert_eq!(crowded.cost(near), 2 * Congestion::COST);
+
+    let mut zones = DangerZones::default();
+    zones.insert(origin);
+    let tiles: HashSet<_> = zones.tiles().collect();
+    assert_eq!(tiles.len(), 25);
+    assert!(tiles.iter().all(|&position| zones.cost(position) > 0));
+}
+
+#[test]
+fn path_snapshot_cross_region() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    let expected = find_path(&mut app, start, goal).unwrap();
+    let path = find_snapshot_path(&mut app, start, goal).unwrap();
+
+    let outside = tile_region(&mut app, start).unwrap();
+
+    assert_eq!(path.cost(), expected.cost());
+    assert_eq!(path.steps().len(), 2);
+
+    match path.steps()[1] {
+        PathStep::DoorFlowField {
+            region,
+            goal: door_goal,
+            ..
+        } => {
+            assert_eq!(region, outside);
+            assert_eq!(door_goal, goal.with_offset(0, 2));
+        }
+        _ => panic!("expected DoorFlowField"),
+    }
+
+    assert!(matches!(path.steps()[0], PathStep::RegionCostField { .. }));
+}
+
+#[test]
+fn path_snapshot_stairs() {
+    let (mut app, _) = make_app();
+    let (lower, upper) = spawn_layer_stack(&mut app);
+
+    let start = TilePosition::new(lower, 5, 5);
+    let goal = TilePosition::new(upper, 10, 5);
+
+    set_stairs_tile(&mut app, TilePosition::new(lower, 8, 5));
+    set_stairs_tile(&mut app, TilePosition::new(upper, 8, 5));
+
+    update_regions(&mut app);
+
+    let path = find_snapshot_path(&mut app, start, goal).unwrap();
+    assert_eq!(path.cost(), 35);
+    assert_eq!(path.steps().len(), 3);
+
+    set_wall_tile(&mut app, TilePosition::new(upper, 8, 5));
+
+    update_regions(&mut app);
+
+    assert!(find_snapshot_path(&mut app, start, goal).is_none());
+}
+
+#[test]
+fn path_snapshot_rebuild() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    let generation = app.world().resource::<PathSnapshot>().generation();
+    let path = find_snapshot_path(&mut app, start, goal).unwrap();
+    assert!(is_valid_path(&mut app, &path));
+
+    clear_tile(&mut app, goal.with_offset(-2, 0));
+
+    update_regions(&mut app);
+
+    assert!(app.world().resource::<PathSnapshot>().generation() > generation);
+    assert!(!is_valid_path(&mut app, &path));
+
+    let expected = find_path(&mut app, start, goal).unwrap();
+    let path = find_snapshot_path(&mut app, start, goal).unwrap();
+    assert_eq!(path.cost(), expected.cost());
+    assert_eq!(path.steps().len(), 1);
+    assert!(is_valid_path(&mut app, &path));
+}
+
+#[test]
+fn path_request() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    let zone = AvoidZone::new(
+        [goal.with_offset(-3, 3), goal.with_offset(-2, 3)],
+        CollisionGroups::all(),
+        100,
+    );
+    let expected = find_path_avoiding(&mut app, start, goal, vec![zone.clone()]).unwrap();
+
+    let snapshot = app.world().resource::<PathSnapshot>().clone();
+    let request = PathRequest::new(start, goal, any_agent()).with_modifier(Arc::new(zone));
+
+    let response = request.find(&snapshot);
+    assert_eq!(response.generation(), snapshot.generation());
+    let path = response.into_path().unwrap().unwrap();
+    assert_eq!(path.cost(), expected.cost());
+
+    let mut task = request.spawn(&snapshot);
+    let response = loop {
+        if let Some(response) = task.poll() {
+            break response;
+        }
+        app.update();
+    };
+    let path = response.into_path().unwrap().unwrap();
+    assert_eq!(path.cost(), expected.cost());
+}
+
+#[test]
+fn path_cache_goal_field() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    let expected = find_path(&mut app, start, goal).unwrap();
+
+    let goal_field = cache_goal_field(&mut app, goal).unwrap();
+    assert_eq!(goal_field.region(), tile_region(&mut app, goal).unwrap());
+    assert_eq!(app.world().resource::<PathCache>().len(), 1);
+
+    let snapshot = app.world().resource::<PathSnapshot>();
+    let path = snapshot
+        .search()
+        .with_goal_field(&goal_field)
+        .find_path(start, goal, &any_agent(), CostModifiers::default())
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(path.cost(), expected.cost());
+    assert_eq!(path.steps().len(), 2);
+    match path.steps()[0] {
+        PathStep::RegionCostField { ref cost_field, .. } => {
+            assert!(Arc::ptr_eq(cost_field, goal_field.cost_field()));
+        }
+        _ => panic!("expected RegionCostField"),
+    }
+
+    let inside = goal.with_offset(1, 1);
+    let path = snapshot
+        .search()
+        .with_goal_field(&goal_field)
+        .find_path(inside, goal, &any_agent(), CostModifiers::default())
+        .unwrap()
+        .unwrap();
+    assert_eq!(path.cost(), MEDIUM_DIAGONAL_COST);
+
+    let again = cache_goal_field(&mut app, goal).unwrap();
+    assert!(Arc::ptr_eq(again.cost_field(), goal_field.cost_field()));
+}
+
+#[test]
+fn path_request_generated_goal_field() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    let expected = find_path(&mut app, start, goal).unwrap();
+
+    let snapshot = app.world().resource::<PathSnapshot>();
+    let mut response = PathRequest::new(start, goal, any_agent())
+        .with_generated_goal_field()
+        .find(snapshot);
+    let goal_field = response.take_goal_field().unwrap();
+    assert_eq!(goal_field.region(), tile_region(&mut app, goal).unwrap());
+    assert!(app.world().resource::<PathCache>().is_empty());
+
+    let path = response.into_path().unwrap().unwrap();
+    assert_eq!(path.cost(), expected.cost());
+    match path.steps()[0] {
+        PathStep::RegionCostField { ref cost_field, .. } => {
+            assert!(Arc::ptr_eq(cost_field, goal_field.cost_field()));
+        }
+        _ => panic!("expected RegionCostField"),
+    }
+
+    let snapshot = app.world().resource::<PathSnapshot>();
+    let mut response = PathRequest::new(start, goal, any_agent())
+        .with_goal_field(goal_field)
+        .with_generated_goal_field()
+        .find(snapshot);
+    assert!(response.take_goal_field().is_none());
+}
+
+#[test]
+fn path_cache_invalidate() {
+    let (mut app, layer) = make_app();
+    let goal = TilePosition::new(layer, 0, 0);
+    let start = goal.with_offset(-5, 0);
+
+    set_square(&mut app, goal, 2);
+    set_door_tile(&mut app, goal.with_offset(0, 2));
+
+    update_regions(&mut app);
+
+    cache_goal_field(&mut app, goal).unwrap();
+    cache_goal_field(&mut app, start).unwrap();
+    assert_eq!(app.world().resource::<PathCache>().len(), 2);
+
+    clear_tile(&mut app, goal.with_offset(-2, 0));
+
+    update_regions(&mut app);
+
+    assert!(app.world().resource::<PathCache>().is_empty());
+
+    let rebuilt = cache_goal_field(&mut app, goal).unwrap();
+    assert_eq!(rebuilt.region(), tile_region(&mut app, start).unwrap());
+}
+
+#[test]
+fn path_cache_capacity() {
+    let (mut app, layer) = make_app();
+    let start = TilePosition::new(layer, 0, 0);
+
+    clear_tile(&mut app, start);
+    update_regions(&mut app);
+
+    app.insert_resource(PathCache::with_capacity(2));
+
+    for x in 1..=3 {
+        cache_goal_field(&mut app, start.with_offset(x, 0)).unwrap();
+    }
+
+    let cache = app.world().resource::<PathCache>();
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(start.with_offset(1, 0)).is_none());
+    assert!(cache.get(start.with_offset(2, 0)).is_some());
+    assert!(cache.get(start.with_offset(3, 0)).is_some());
+}
+
+#[test]
+fn block_vec_resize_and_clone() {
+    let mut vec = BlockVec::from_elem(0u32, 2500);
+    assert_eq!(vec.len(), 2500);
+    assert!(vec.iter().all(|&value| value == 0));
+
+    let snapshot = vec.clone();
+    vec[2000] = 1;
+    assert_eq!(vec[2000], 1);
+    assert_eq!(snapshot[2000], 0);
+
+    vec.resize(1024, 0);
+    assert_eq!(vec.len(), 1024);
+    assert_eq!(vec.iter().count(), 1024);
+    assert_eq!(vec.get(1024), None);
+
+    vec.resize(1500, 2);
+    assert_eq!(vec[1023], 0);
+    assert_eq!(vec[1024], 2);
+    assert_eq!(vec.iter().count(), 1500);
+
+    vec.resize(0, 0);
+    assert!(vec.is_empty());
+    assert_eq!(snapshot.len(), 2500);
+}
+
 fn make_app() -> (App, Entity) {
     let mut app = App::new();
     app.add_plugins((TaskPoolPlugin::default(), TilePlugin, PathPlugin));
@@ -3306,6 +4111,13 @@ fn make_app() -> (App, Entity) {
     (app, layer)
 }
 
+fn spawn_layer_stack(app: &mut App) -> (Entity, Entity) {
+    let stack = app.world_mut().spawn(LayerStack::default()).id();
+    let lower = app.world_mut().spawn((Layer::new(0), ChildOf(stack))).id();
+    let upper = app.world_mut().spawn((Layer::new(1), ChildOf(stack))).id();
+    (lower, upper)
+}
+
 fn set_wall_tile(app: &mut App, position: TilePosition) {
     app.world_mut()
         .run_system_once(move |mut storage: TileStorageMut| {
@@ -3325,6 +4137,14 @@ fn set_door_tile(app: &mut App, position: TilePosition) -> Entity {
         .id()
 }
 
+fn set_stairs_tile(app: &mut App, position: TilePosition) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, TileMaterial::STAIRS);
+        })
+        .unwrap();
+}
+
 fn set_slow_tile(app: &mut App, position: TilePosition) {
     app.world_mut()
         .run_system_once(move |mut storage: TileStorageMut| {
@@ -3406,7 +4226,7 @@ fn region_outside(app: &mut App, region: Entity) -> bool {
 
 fn region_size(app: &mut App, region: Entity) -> usize {
     let region_tiles = app.world().get::<RegionTiles>(region).unwrap();
-    region_tiles.size() - region_tiles.door_count()
+    region_tiles.len() - region_tiles.door_count()
 }
 
 fn tile_region(app: &mut App, position: TilePosition) -> Option<Entity> {
@@ -3440,6 +4260,38 @@ fn door_regions(app: &App, door: Entity) -> &DoorRegions {
     app.world().get::<DoorRegions>(door).unwrap()
 }
 
+fn assert_flow_field_regenerated(
+    app: &App,
+    region: Entity,
+    door: Entity,
+    door_position: TilePosition,
+) {
+    let region_tiles = app.world().get::<RegionTiles>(region).unwrap();
+    let door_index = region_tiles
+        .get_tile_index(door_position.layer_offset())
+        .unwrap();
+
+    let mut expected = FlowField::new(
+        door_position,
+        door_index,
+        region_tiles[door_index].adjacency(),
+        region_tiles.size(),
+    );
+    expected.populate_flow(region_tiles);
+
+    let flow_field = region_door_flow_field(app, region, door);
+    for (index, tile) in region_tiles.tiles() {
+        assert_eq!(
+            flow_field.get(index),
+            expected.get(index),
+            "{:?}",
+            tile.position()
+        );
+    }
+    assert_eq!(flow_field.len(), expected.len());
+    assert_eq!(flow_field.len(), flow_field.iter().count());
+}
+
 fn flow_entry(dir_x: f32, dir_y: f32, cost: u32) -> FlowFieldEntry {
     FlowFieldEntry::new(Dir2::from_xy(dir_x, dir_y).unwrap(), cost)
 }
@@ -3469,11 +4321,65 @@ fn get_flow(app: &App, region: Entity, door: Entity, position: TilePosition) ->
 }
 
 fn find_path(app: &mut App, start: TilePosition, goal: TilePosition) -> Option<Path> {
+    find_path_as(app, start, goal, any_agent())
+}
+
+fn find_path_as(
+    app: &mut App,
+    start: TilePosition,
+    goal: TilePosition,
+    agent: PathAgent,
+) -> Option<Path> {
+    app.world_mut()
+        .run_system_once(move |param: PathParam| {
+            param
+                .find_path(start, goal, &agent, CostModifiers::default())
+                .unwrap()
+        })
+        .unwrap()
+}
+
+fn find_path_avoiding(
+    app: &mut App,
+    start: TilePosition,
+    goal: TilePosition,
+    zones: Vec<AvoidZone>,
+) -> Option<Path> {
     app.world_mut()
-        .run_system_once(move |param: PathParam| param.find_path(start, goal).unwrap())
+        .run_system_once(move |param: PathParam| {
+            let modifiers: Vec<&dyn CostModifier> =
+                zones.iter().map(|zone| zone as &dyn CostModifier).collect();
+            param
+                .find_path(start, goal, &any_agent(), CostModifiers::new(&modifiers))
+                .unwrap()
+        })
+        .unwrap()
+}
+
+fn find_snapshot_path(app: &mut App, start: TilePosition, goal: TilePosition) -> Option<Path> {
+    app.world()
+        .resource::<PathSnapshot>()
+        .find_path(start, goal, &any_agent(), CostModifiers::default())
         .unwrap()
 }
 
+fn is_valid_path(app: &mut App, path: &Path) -> bool {
+    let mut state = SystemState::<PathParam>::new(app.world_mut());
+    state.get(app.world()).unwrap().is_valid(path)
+}
+
+fn cache_goal_field(app: &mut App, goal: TilePosition) -> Option<GoalField> {
+    app.world_mut()
+        .run_system_once(move |mut cache: ResMut<PathCache>, param: PathParam| {
+            cache.get_or_generate(goal, &param)
+        })
+        .unwrap()
+}
+
+fn any_agent() -> PathAgent {
+    PathAgent::new(Entity::PLACEHOLDER, Duration::ZERO)
+}
+
 fn validate_regions(
     storage: TileStorage,
     index: Res<TileIndex>,
@@ -3488,13 +4394,13 @@ fn validate_regions(
             .map(|chunk| {
                 chunk
                     .tiles()
-                    .filter(|tile| tile.1.kind() == TileKind::Empty)
+                    .filter(|tile| matches!(tile.1.kind(), TileKind::Empty | TileKind::Stairs))
                     .count()
             })
             .sum::<usize>(),
         regions
             .iter()
-            .map(|(_, _, tiles)| tiles.size() - tiles.door_count())
+            .map(|(_, _, tiles)| tiles.len() - tiles.door_count())
             .sum::<usize>()
     );
 
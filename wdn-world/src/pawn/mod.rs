@@ -1,15 +1,20 @@
 pub mod action;
 pub mod path;
+#[cfg(test)]
+mod tests;
 
 use std::{f32::consts::TAU, time::Duration};
 
 use bevy_app::prelude::*;
-use bevy_ecs::prelude::*;
+use bevy_ecs::{lifecycle::HookContext, prelude::*, world::DeferredWorld};
 use bevy_math::{Rot2, Vec2};
 use bevy_transform::prelude::*;
 use wdn_physics::{
     PhysicsSystems,
-    collision::Collider,
+    collision::{
+        Collider,
+        filter::{CollisionFilter, CollisionGroups},
+    },
     kinematics::{Position, Velocity},
     tile::material::TileMaterial,
 };
@@ -19,7 +24,10 @@ use crate::{
     combat::{Health, Projectile},
     pawn::{
         action::{PawnAction, apply_pawn_actions},
-        path::{PawnPath, follow_pawn_paths, open_doors_on_collision},
+        path::{
+            PawnPath, PawnPathQueue, follow_pawn_paths, open_doors_on_collision,
+            receive_pawn_paths, request_pawn_paths,
+        },
     },
 };
 
@@ -28,6 +36,7 @@ pub struct PawnPlugin;
 #[derive(Copy, Clone, Component, Debug, Default)]
 #[require(
     Collider::new(Pawn::RADIUS, true),
+    CollisionFilter = Pawn::FILTER,
     Transform,
     Velocity,
     Health::new(Pawn::MAX_HEALTH),
@@ -43,6 +52,12 @@ pub struct Pawn {
     right_attack_cooldown: Duration,
 }
 
+// Pawns are prisoners unless they're staff, who swap the prisoner filter for their own.
+#[derive(Copy, Clone, Component, Debug, Default)]
+#[component(on_add = Staff::on_add, on_remove = Staff::on_remove)]
+#[require(Pawn)]
+pub struct Staff;
+
 #[derive(Copy, Clone, Component, Debug)]
 #[require(
     Collider::new(PawnProjectile::RADIUS, false),
@@ -53,6 +68,8 @@ pub struct PawnProjectile;
 
 impl Plugin for PawnPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<PawnPathQueue>();
+
         app.configure_sets(
             FixedUpdate,
             WorldSystems::ApplyPawnActions
@@ -64,7 +81,12 @@ impl Plugin for PawnPlugin {
         app.add_systems(
             FixedUpdate,
             (
-                (follow_pawn_paths, apply_pawn_actions)
+                (
+                    receive_pawn_paths,
+                    request_pawn_paths,
+                    follow_pawn_paths,
+                    apply_pawn_actions,
+                )
                     .chain()
                     .in_set(WorldSystems::ApplyPawnActions),
                 (open_doors_on_collision.after(PhysicsSystems::Collisions)),
@@ -75,10 +97,33 @@ impl Plugin for PawnPlugin {
 
 impl Pawn {
     pub const RADIUS: f32 = 0.2;
+    // Pawns have no mass unless given one, and only pawns with mass shove each other aside.
+    pub const MASS: f32 = 1.0;
     pub const MAX_HEALTH: u32 = 5;
     pub const WALK_SPEED: f32 = 1.5;
     pub const TURN_SPEED: f32 = TAU;
     pub const ACCELERATION: f32 = 6.0;
+    pub const FILTER: CollisionFilter =
+        CollisionFilter::new(CollisionGroups::PRISONER, CollisionGroups::all());
+}
+
+impl Staff {
+    pub const FILTER: CollisionFilter = CollisionFilter::new(
+        CollisionGroups::STAFF,
+        CollisionGroups::all().difference(CollisionGroups::STAFF),
+    );
+
+    fn on_add(mut world: DeferredWorld, context: HookContext) {
+        if let Some(mut filter) = world.get_mut::<CollisionFilter>(context.entity) {
+            *filter = Staff::FILTER;
+        }
+    }
+
+    fn on_remove(mut world: DeferredWorld, context: HookContext) {
+        if let Some(mut filter) = world.get_mut::<CollisionFilter>(context.entity) {
+            *filter = Pawn::FILTER;
+        }
+    }
 }
 
 impl PawnProjectile {
@@ -88,13 +133,27 @@ impl PawnProjectile {
     pub const DURATION: Duration = Duration::from_millis(500);
     pub const SPEED: f32 = 0.86;
 
-    pub fn bundle(pawn: Entity, position: Vec2, velocity: Vec2) -> impl Bundle {
+    pub fn bundle(
+        pawn: Entity,
+        filter: &CollisionFilter,
+        position: Vec2,
+        velocity: Vec2,
+    ) -> impl Bundle {
         (
             PawnProjectile,
             Projectile::new(pawn, PawnProjectile::DAMAGE, PawnProjectile::DURATION),
+            PawnProjectile::filter(filter),
             ChildOf(pawn),
             Position::new(position, Rot2::IDENTITY),
             Velocity::new(velocity),
         )
     }
+
+    pub fn filter(source: &CollisionFilter) -> CollisionFilter {
+        let allies = source.memberships & (CollisionGroups::PRISONER | CollisionGroups::STAFF);
+        CollisionFilter::new(
+            CollisionGroups::PROJECTILE,
+            !(CollisionGroups::PROJECTILE | allies),
+        )
+    }
 }
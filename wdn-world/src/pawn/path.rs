@@ -1,15 +1,25 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use bevy_ecs::{batching::BatchingStrategy, prelude::*};
 use bevy_log::warn;
+use bevy_time::prelude::*;
 use wdn_physics::{
-    collision::{CollisionTarget, Collisions},
-    kinematics::GlobalPosition,
+    Deterministic,
+    collision::{CollisionTarget, Collisions, filter::CollisionFilter},
+    kinematics::{GlobalPosition, Position},
     tile::position::TilePosition,
 };
 
 use crate::{
-    door::Door,
-    path::find::{Path, PathParam},
-    pawn::{Pawn, action::PawnAction},
+    door::{Door, DoorAccess},
+    path::{
+        cache::PathCache,
+        cost::{AvoidZone, Congestion, DangerZones},
+        find::{Path, PathAgent, PathMove, PathParam},
+        request::{PathBudget, PathRequest, PathResponse, PathTask},
+        snapshot::PathSnapshot,
+    },
+    pawn::{Pawn, Staff, action::PawnAction},
 };
 
 #[derive(Component, Default, Debug)]
@@ -18,104 +28,290 @@ pub struct PawnPath {
     state: PathState,
 }
 
+#[derive(Resource, Default, Debug)]
+pub struct PawnPathQueue {
+    searching: VecDeque<Entity>,
+}
+
 #[derive(Debug, Default)]
-enum PathState {
+pub enum PathState {
     #[default]
     Pending,
+    Searching(PathTask),
     Active(Path),
     Finished,
     Failed,
 }
 
-pub fn follow_pawn_paths(
+pub fn receive_pawn_paths(
+    mut pawns: Query<(&mut PawnPath, &TilePosition)>,
+    paths: PathParam,
+    snapshot: Res<PathSnapshot>,
+    budget: Res<PathBudget>,
+    mut cache: ResMut<PathCache>,
+    mut queue: ResMut<PawnPathQueue>,
+) {
+    let mut remaining = budget.results;
+    queue.searching.retain(|&id| {
+        if remaining == 0 {
+            return true;
+        }
+
+        let Ok((mut pawn_path, &tile_position)) = pawns.get_mut(id) else {
+            return false;
+        };
+        let PathState::Searching(task) = &mut pawn_path.bypass_change_detection().state else {
+            return false;
+        };
+        let Some(response) = task.poll() else {
+            return true;
+        };
+
+        remaining -= 1;
+        pawn_path.state = resolve_path(
+            response,
+            tile_position,
+            pawn_path.target,
+            &paths,
+            &snapshot,
+            &mut cache,
+        );
+        false
+    });
+}
+
+#[expect(clippy::too_many_arguments)]
+pub fn request_pawn_paths(
     mut pawns: Query<(
-        &mut PawnAction,
+        Entity,
         &mut PawnPath,
         &TilePosition,
-        &GlobalPosition,
+        &CollisionFilter,
+        Has<Staff>,
     )>,
     paths: PathParam,
+    snapshot: Res<PathSnapshot>,
+    budget: Res<PathBudget>,
+    mut cache: ResMut<PathCache>,
+    mut queue: ResMut<PawnPathQueue>,
+    avoid_zones: Query<&AvoidZone>,
+    danger_zones: Res<DangerZones>,
+    time: Res<Time>,
+    deterministic: Option<Res<Deterministic>>,
 ) {
-    pawns
-        .par_iter_mut()
-        .batching_strategy(BatchingStrategy::new().min_batch_size(16))
-        .for_each(
-            |(mut action, mut pawn_path, &tile_position, &global_position)| {
-                let Some(target) = pawn_path.target else {
-                    return;
-                };
+    let pending: Vec<Entity> = pawns
+        .iter()
+        .filter(|(_, pawn_path, tile_position, ..)| {
+            matches!(pawn_path.state, PathState::Pending)
+                && pawn_path
+                    .target
+                    .is_some_and(|target| target != **tile_position)
+        })
+        .map(|(id, ..)| id)
+        .take(budget.requests)
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+
+    let zones: Vec<_> = avoid_zones
+        .iter()
+        .map(|zone| Arc::new(zone.clone()))
+        .collect();
+    let danger = (!danger_zones.is_empty()).then(|| Arc::new(danger_zones.clone()));
+    let occupancy =
+        Congestion::occupancy(pawns.iter().map(|(_, _, &tile_position, ..)| tile_position));
+
+    for id in pending {
+        let Ok((_, mut pawn_path, &tile_position, filter, staff)) = pawns.get_mut(id) else {
+            continue;
+        };
+        let Some(target) = pawn_path.target else {
+            continue;
+        };
+
+        let mut request = PathRequest::new(
+            tile_position,
+            target,
+            PathAgent::new(id, time.elapsed()).with_staff(staff),
+        );
+        for zone in zones
+            .iter()
+            .filter(|zone| zone.applies_to(filter.memberships))
+        {
+            request = request.with_modifier(zone.clone());
+        }
+        if let Some(danger) = &danger {
+            request = request.with_modifier(danger.clone());
+        }
+        let congestion = Congestion::new(occupancy.clone(), tile_position);
+        if !congestion.is_empty() {
+            request = request.with_modifier(Arc::new(congestion));
+        }
+        request = match cache.get(target) {
+            Some(goal_field) => request.with_goal_field(goal_field.clone()),
+            None => request.with_generated_goal_field(),
+        };
+
+        pawn_path.state = if deterministic.is_some() {
+            let response = request.find(&snapshot);
+            resolve_path(
+                response,
+                tile_position,
+                Some(target),
+                &paths,
+                &snapshot,
+                &mut cache,
+            )
+        } else {
+            queue.searching.push_back(id);
+            PathState::Searching(request.spawn(&snapshot))
+        };
+    }
+}
+
+type FollowPawnData = (
+    Entity,
+    &'static mut PawnAction,
+    &'static mut PawnPath,
+    &'static TilePosition,
+    &'static GlobalPosition,
+    Has<Staff>,
+);
+
+pub fn follow_pawn_paths(
+    commands: ParallelCommands,
+    mut pawns: Query<FollowPawnData>,
+    paths: PathParam,
+    time: Res<Time>,
+    deterministic: Option<Res<Deterministic>>,
+) {
+    let follow = |(id, mut action, mut pawn_path, &tile_position, &global_position, staff): (
+        Entity,
+        Mut<PawnAction>,
+        Mut<PawnPath>,
+        &TilePosition,
+        &GlobalPosition,
+        bool,
+    )| {
+        let Some(target) = pawn_path.target else {
+            return;
+        };
+
+        if tile_position == target {
+            pawn_path.state = PathState::Finished;
+            *action = PawnAction::Stand;
+            return;
+        }
 
-                if tile_position == target {
-                    pawn_path.state = PathState::Finished;
+        let agent = PathAgent::new(id, time.elapsed()).with_staff(staff);
+        let desired_dir = loop {
+            match &mut pawn_path.state {
+                PathState::Active(path) => match paths.next_move(path, tile_position, &agent) {
+                    Err(err) => {
+                        warn!("path invalidated, recalculating: {}", err);
+                        pawn_path.state = PathState::Pending;
+                    }
+                    Ok(Some(PathMove::Walk(dir))) => {
+                        break dir;
+                    }
+                    Ok(Some(PathMove::Climb(to))) => {
+                        commands.command_scope(|mut commands| {
+                            commands.entity(id).try_insert((
+                                ChildOf(to.layer()),
+                                Position::new(to.center_position(), global_position.rotation()),
+                            ));
+                        });
+                        *action = PawnAction::Stand;
+                        return;
+                    }
+                    Ok(None) => {
+                        warn!(
+                            "Failed to get path direction at position {:?}",
+                            tile_position
+                        );
+                        pawn_path.state = PathState::Pending;
+                    }
+                },
+                PathState::Finished | PathState::Failed => {
+                    return;
+                }
+                PathState::Pending | PathState::Searching(_) => {
                     *action = PawnAction::Stand;
                     return;
                 }
+            };
+        };
 
-                let desired_dir = loop {
-                    match &mut pawn_path.state {
-                        PathState::Active(path) => match paths.path_dir(path, tile_position) {
-                            Err(err) => {
-                                warn!("path invalidated, recalculating: {}", err);
-                                pawn_path.state = PathState::Pending;
-                            }
-                            Ok(Some(dir)) => {
-                                break dir;
-                            }
-                            Ok(None) => {
-                                warn!(
-                                    "Failed to get path direction at position {:?}",
-                                    tile_position
-                                );
-                                pawn_path.state = PathState::Pending;
-                            }
-                        },
-                        PathState::Finished | PathState::Failed => {
-                            return;
-                        }
-                        PathState::Pending => {
-                            match paths.find_path(tile_position, target).unwrap() {
-                                Some(new_path) => {
-                                    pawn_path.state = PathState::Active(new_path);
-                                }
-                                None => {
-                                    warn!(
-                                        "Failed to find path from {:?} to {:?}",
-                                        tile_position, target
-                                    );
-                                    pawn_path.state = PathState::Failed;
-                                }
-                            }
-                        }
-                    };
-                };
-
-                let actual_dir = global_position.rotation();
-                let delta = actual_dir.angle_to(desired_dir.rotation_from_x());
-
-                if delta.abs() > 1.0 {
-                    *action = if delta > 0.0 {
-                        PawnAction::TurnLeft
-                    } else {
-                        PawnAction::TurnRight
-                    };
-                } else if delta.abs() > 0.1 {
-                    *action = if delta > 0.0 {
-                        PawnAction::SteerLeft
-                    } else {
-                        PawnAction::SteerRight
-                    };
-                } else {
-                    *action = PawnAction::Walk;
-                }
-            },
-        );
+        let actual_dir = global_position.rotation();
+        let delta = actual_dir.angle_to(desired_dir.rotation_from_x());
+
+        if delta.abs() > 1.0 {
+            *action = if delta > 0.0 {
+                PawnAction::TurnLeft
+            } else {
+                PawnAction::TurnRight
+            };
+        } else if delta.abs() > 0.1 {
+            *action = if delta > 0.0 {
+                PawnAction::SteerLeft
+            } else {
+                PawnAction::SteerRight
+            };
+        } else {
+            *action = PawnAction::Walk;
+        }
+    };
+
+    if deterministic.is_some() {
+        pawns.iter_mut().for_each(follow);
+    } else {
+        pawns
+            .par_iter_mut()
+            .batching_strategy(BatchingStrategy::new().min_batch_size(16))
+            .for_each(follow);
+    }
+}
+
+fn resolve_path(
+    mut response: PathResponse,
+    start: TilePosition,
+    target: Option<TilePosition>,
+    paths: &PathParam,
+    snapshot: &PathSnapshot,
+    cache: &mut PathCache,
+) -> PathState {
+    let generation = response.generation();
+    if generation == snapshot.generation()
+        && let Some(goal_field) = response.take_goal_field()
+    {
+        cache.insert(goal_field);
+    }
+
+    match response.into_path() {
+        Ok(Some(path)) if paths.is_valid(&path) => PathState::Active(path),
+        Ok(Some(_)) => PathState::Pending,
+        _ if generation != snapshot.generation() => PathState::Pending,
+        Ok(None) => {
+            warn!("Failed to find path from {:?} to {:?}", start, target);
+            PathState::Failed
+        }
+        Err(err) => {
+            warn!(
+                "Failed to find path from {:?} to {:?}: {}",
+                start, target, err
+            );
+            PathState::Failed
+        }
+    }
 }
 
 pub fn open_doors_on_collision(
-    collisions: Query<&Collisions, With<Pawn>>,
-    mut doors: Query<&mut Door>,
+    collisions: Query<(Entity, &Collisions, Has<Staff>), With<Pawn>>,
+    mut doors: Query<(&mut Door, Option<&DoorAccess>)>,
+    time: Res<Time>,
 ) {
-    collisions.iter().for_each(|collisions| {
+    collisions.iter().for_each(|(id, collisions, staff)| {
         for collision in collisions.iter() {
             if !collision.solid {
                 continue;
@@ -125,7 +321,9 @@ pub fn open_doors_on_collision(
                 CollisionTarget::Tile {
                     id: Some(tile_id), ..
                 } => {
-                    if let Ok(mut door) = doors.get_mut(tile_id) {
+                    if let Ok((mut door, access)) = doors.get_mut(tile_id)
+                        && access.is_none_or(|access| access.allows(id, staff, time.elapsed()))
+                    {
                         door.open();
                     }
                 }
@@ -141,10 +339,27 @@ impl PawnPath {
         self.state = PathState::Pending;
     }
 
+    pub fn clear_target(&mut self) {
+        self.target = None;
+        self.state = PathState::Pending;
+    }
+
     pub fn target(&self) -> Option<TilePosition> {
         self.target
     }
 
+    pub fn state(&self) -> &PathState {
+        &self.state
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, PathState::Finished)
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self.state, PathState::Failed)
+    }
+
     pub fn path(&self) -> Option<&Path> {
         match &self.state {
             PathState::Active(path) => Some(path),
@@ -0,0 +1,675 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use bevy_app::{TaskPoolPlugin, prelude::*};
+use bevy_ecs::{prelude::*, system::RunSystemOnce};
+use bevy_math::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
+use wdn_physics::{
+    PhysicsPlugin,
+    collision::{CollisionTarget, Collisions},
+    kinematics::Position,
+    layer::{Layer, LayerStack},
+    tile::{material::TileMaterial, position::TilePosition, storage::TileStorageMut},
+};
+
+use crate::{
+    WorldPlugin,
+    combat::Health,
+    door::{Door, DoorAccess},
+    path::{
+        cache::PathCache, find::PathStep, flow::CostField, request::PathBudget,
+        snapshot::PathSnapshot,
+    },
+    pawn::{
+        Pawn, Staff,
+        action::PawnAction,
+        path::{PathState, PawnPath},
+    },
+};
+
+#[test]
+fn pawn_climbs_stairs() {
+    let mut app = make_app();
+    let stack = app.world_mut().spawn(LayerStack::default()).id();
+    let lower = app.world_mut().spawn((Layer::new(0), ChildOf(stack))).id();
+    let upper = app.world_mut().spawn((Layer::new(1), ChildOf(stack))).id();
+    fill_empty(&mut app, lower);
+    fill_empty(&mut app, upper);
+    set_material(
+        &mut app,
+        TilePosition::new(lower, 4, 0),
+        TileMaterial::STAIRS,
+    );
+    set_material(
+        &mut app,
+        TilePosition::new(upper, 4, 0),
+        TileMaterial::STAIRS,
+    );
+
+    let goal = TilePosition::new(upper, 7, 0);
+    let pawn = spawn_pawn(&mut app, lower, Vec2::new(0.5, 0.5));
+    app.world_mut()
+        .get_mut::<PawnPath>(pawn)
+        .unwrap()
+        .set_target(goal);
+
+    run_until(&mut app, |world| {
+        world.get::<ChildOf>(pawn).unwrap().parent() == upper
+    });
+
+    let path = app.world().get::<PawnPath>(pawn).unwrap();
+    assert_eq!(path.target(), Some(goal));
+    assert!(!path.is_failed());
+
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(pawn).unwrap().is_finished()
+    });
+
+    assert_eq!(app.world().get::<ChildOf>(pawn).unwrap().parent(), upper);
+    assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), goal);
+    assert!(matches!(
+        app.world().get::<PawnPath>(pawn).unwrap().state(),
+        PathState::Finished
+    ));
+}
+
+#[test]
+fn pawn_crowd_passes_in_corridor() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+    for x in 0..=10 {
+        set_material(&mut app, TilePosition::new(layer, x, 0), TileMaterial::WALL);
+        set_material(&mut app, TilePosition::new(layer, x, 3), TileMaterial::WALL);
+    }
+
+    let mut pawns = Vec::new();
+    for (index, y) in [1, 2].into_iter().enumerate() {
+        for offset in 0..2 {
+            let x = -2 - offset;
+            let row = if (index + offset as usize).is_multiple_of(2) {
+                -1
+            } else {
+                4
+            };
+            let east = spawn_pawn(&mut app, layer, Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+            let west = spawn_pawn(
+                &mut app,
+                layer,
+                Vec2::new((12 - x) as f32 + 0.5, y as f32 + 0.5),
+            );
+            pawns.push((east, TilePosition::new(layer, 15 + 3 * offset, row)));
+            pawns.push((west, TilePosition::new(layer, -5 - 3 * offset, row)));
+        }
+    }
+    for &(pawn, target) in &pawns {
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(target);
+    }
+
+    run_until(&mut app, |world| {
+        pawns
+            .iter()
+            .all(|&(pawn, _)| world.get::<PawnPath>(pawn).unwrap().is_finished())
+    });
+
+    for &(pawn, target) in &pawns {
+        assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), target);
+    }
+}
+
+#[test]
+fn pawn_crowd_passes_through_door() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+    for y in -4..=4 {
+        set_material(&mut app, TilePosition::new(layer, 4, y), TileMaterial::WALL);
+    }
+    let door_position = TilePosition::new(layer, 4, 0);
+    set_material(&mut app, door_position, TileMaterial::DOOR);
+    let door = app
+        .world_mut()
+        .spawn((Door::default(), door_position, ChildOf(layer)))
+        .id();
+
+    let pawns: Vec<(Entity, TilePosition)> = (-1..=1)
+        .map(|y| {
+            let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, y as f32 + 0.5));
+            let target = TilePosition::new(layer, 8, y);
+            app.world_mut()
+                .get_mut::<PawnPath>(pawn)
+                .unwrap()
+                .set_target(target);
+            (pawn, target)
+        })
+        .collect();
+
+    run_until(&mut app, |world| {
+        pawns
+            .iter()
+            .all(|&(pawn, _)| world.get::<PawnPath>(pawn).unwrap().is_finished())
+    });
+
+    for &(pawn, target) in &pawns {
+        assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), target);
+    }
+    assert!(app.world().get::<Door>(door).is_some());
+}
+
+#[test]
+fn pawn_reroutes_around_door_locked_mid_route() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+    for y in -32..32 {
+        set_material(&mut app, TilePosition::new(layer, 4, y), TileMaterial::WALL);
+    }
+    let near_position = TilePosition::new(layer, 4, 0);
+    let far_position = TilePosition::new(layer, 4, 6);
+    let doors = [near_position, far_position].map(|position| {
+        set_material(&mut app, position, TileMaterial::DOOR);
+        app.world_mut()
+            .spawn((Door::default(), position, ChildOf(layer)))
+            .id()
+    });
+
+    let target = TilePosition::new(layer, 8, 0);
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    app.world_mut()
+        .get_mut::<PawnPath>(pawn)
+        .unwrap()
+        .set_target(target);
+
+    run_until(&mut app, |world| {
+        world.get::<TilePosition>(pawn).unwrap().x() == 2
+    });
+    assert!(matches!(
+        app.world().get::<PawnPath>(pawn).unwrap().state(),
+        PathState::Active(_)
+    ));
+
+    app.world_mut()
+        .entity_mut(doors[0])
+        .insert(DoorAccess::locked());
+
+    let mut visited_far_door = false;
+    run_until(&mut app, |world| {
+        let position = *world.get::<TilePosition>(pawn).unwrap();
+        assert_ne!(position, near_position);
+        visited_far_door |= position == far_position;
+        world.get::<PawnPath>(pawn).unwrap().is_finished()
+    });
+
+    assert!(visited_far_door);
+    assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), target);
+}
+
+#[test]
+fn staff_pawn_passes_staff_only_door() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+    for offset in -4..=4 {
+        for position in [
+            TilePosition::new(layer, 4, offset),
+            TilePosition::new(layer, 12, offset),
+            TilePosition::new(layer, 8 + offset, -4),
+            TilePosition::new(layer, 8 + offset, 4),
+        ] {
+            set_material(&mut app, position, TileMaterial::WALL);
+        }
+    }
+    let door_position = TilePosition::new(layer, 4, 0);
+    set_material(&mut app, door_position, TileMaterial::DOOR);
+    app.world_mut().spawn((
+        Door::default(),
+        DoorAccess::staff_only(),
+        door_position,
+        ChildOf(layer),
+    ));
+
+    let staff = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    app.world_mut().entity_mut(staff).insert(Staff);
+    let prisoner = spawn_pawn(&mut app, layer, Vec2::new(0.5, -2.5));
+    let target = TilePosition::new(layer, 8, 0);
+    for pawn in [staff, prisoner] {
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(target);
+    }
+
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(staff).unwrap().is_finished()
+    });
+
+    assert_eq!(*app.world().get::<TilePosition>(staff).unwrap(), target);
+    assert!(app.world().get::<PawnPath>(prisoner).unwrap().is_failed());
+    assert!(app.world().get::<TilePosition>(prisoner).unwrap().x() < 4);
+}
+
+#[test]
+fn staff_pawns_pass_through_each_other() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let first = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    let second = spawn_pawn(&mut app, layer, Vec2::new(6.5, 0.5));
+    for (pawn, target) in [(first, 6), (second, 0)] {
+        app.world_mut().entity_mut(pawn).insert(Staff);
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(TilePosition::new(layer, target, 0));
+    }
+
+    let touching = |world: &World, pawn: Entity, other: Entity| {
+        world.get::<Collisions>(pawn).unwrap().iter().any(|collision| {
+            matches!(collision.target, CollisionTarget::Collider { id, .. } if id == other)
+        })
+    };
+    run_until(&mut app, |world| {
+        assert!(!touching(world, first, second));
+        assert!(!touching(world, second, first));
+        world.get::<PawnPath>(first).unwrap().is_finished()
+            && world.get::<PawnPath>(second).unwrap().is_finished()
+    });
+
+    assert_eq!(
+        *app.world().get::<TilePosition>(first).unwrap(),
+        TilePosition::new(layer, 6, 0)
+    );
+    assert_eq!(
+        *app.world().get::<TilePosition>(second).unwrap(),
+        TilePosition::new(layer, 0, 0)
+    );
+}
+
+#[test]
+fn staff_projectiles_pass_through_staff() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let mut spawn_pair = |x: f32| {
+        let attacker = spawn_pawn(&mut app, layer, Vec2::new(x, 0.5));
+        app.world_mut()
+            .entity_mut(attacker)
+            .insert((Staff, PawnAction::AttackLeft));
+        let target = spawn_pawn(&mut app, layer, Vec2::new(x - 0.12, 0.85));
+        (attacker, target)
+    };
+    let (_, staff) = spawn_pair(0.5);
+    let (_, prisoner) = spawn_pair(8.5);
+    app.world_mut().entity_mut(staff).insert(Staff);
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    let health = |pawn: Entity| {
+        app.world()
+            .get::<Health>(pawn)
+            .map_or(0, |health| health.current)
+    };
+    assert_eq!(health(staff), Pawn::MAX_HEALTH);
+    assert!(health(prisoner) < Pawn::MAX_HEALTH);
+}
+
+#[test]
+fn pawn_paths_respect_budget() {
+    let mut app = make_app();
+    app.insert_resource(PathBudget {
+        requests: 2,
+        results: 1,
+    });
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let pawns: Vec<Entity> = (0..5)
+        .map(|y| {
+            let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, y as f32 * 2.0 + 0.5));
+            app.world_mut()
+                .get_mut::<PawnPath>(pawn)
+                .unwrap()
+                .set_target(TilePosition::new(layer, 12, y * 2));
+            pawn
+        })
+        .collect();
+
+    let state = |world: &World, pawn: Entity| match world.get::<PawnPath>(pawn).unwrap().state() {
+        PathState::Pending => 0,
+        PathState::Searching(_) => 1,
+        _ => 2,
+    };
+    let mut previous = vec![0; pawns.len()];
+    let mut max_requested = 0;
+    let mut max_received = 0;
+    run_until(&mut app, |world| {
+        let current: Vec<_> = pawns.iter().map(|&pawn| state(world, pawn)).collect();
+        let requested = previous
+            .iter()
+            .zip(&current)
+            .filter(|&(&previous, &current)| previous == 0 && current != 0)
+            .count();
+        let received = previous
+            .iter()
+            .zip(&current)
+            .filter(|&(&previous, &current)| previous == 1 && current != 1)
+            .count();
+        assert!(requested <= 2);
+        assert!(received <= 1);
+        max_requested = max_requested.max(requested);
+        max_received = max_received.max(received);
+        previous = current;
+        previous.iter().all(|&state| state == 2)
+    });
+
+    assert_eq!(max_requested, 2);
+    assert_eq!(max_received, 1);
+}
+
+#[test]
+fn pawn_paths_received_in_request_order() {
+    let mut app = make_app();
+    app.insert_resource(PathBudget {
+        requests: 1,
+        results: 0,
+    });
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let first = spawn_pawn(&mut app, layer, Vec2::new(0.5, 4.5));
+    let second = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    app.world_mut().entity_mut(first).insert(Staff);
+
+    let finished = |world: &World, pawn: Entity| match world.get::<PawnPath>(pawn).unwrap().state()
+    {
+        PathState::Searching(task) => task.is_finished(),
+        _ => false,
+    };
+    for pawn in [first, second] {
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(TilePosition::new(layer, 8, 2));
+        run_until(&mut app, |world| {
+            matches!(
+                world.get::<PawnPath>(pawn).unwrap().state(),
+                PathState::Searching(_)
+            )
+        });
+        while !finished(app.world(), pawn) {
+            thread::yield_now();
+        }
+    }
+
+    app.world_mut().resource_mut::<PathBudget>().results = 1;
+    app.update();
+
+    assert!(app.world().get::<PawnPath>(first).unwrap().path().is_some());
+    assert!(finished(app.world(), second));
+
+    app.update();
+
+    assert!(
+        app.world()
+            .get::<PawnPath>(second)
+            .unwrap()
+            .path()
+            .is_some()
+    );
+}
+
+#[test]
+fn pawn_discards_path_from_stale_snapshot() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let goal = TilePosition::new(layer, 8, 0);
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    app.world_mut()
+        .get_mut::<PawnPath>(pawn)
+        .unwrap()
+        .set_target(goal);
+
+    let searching = |world: &World| match world.get::<PawnPath>(pawn).unwrap().state() {
+        PathState::Searching(task) => Some(task.is_finished()),
+        _ => None,
+    };
+    run_until(&mut app, |world| searching(world).is_some());
+    while searching(app.world()) == Some(false) {
+        thread::yield_now();
+    }
+
+    let generation = app.world().resource::<PathSnapshot>().generation();
+    set_material(
+        &mut app,
+        TilePosition::new(layer, 10, 10),
+        TileMaterial::WALL,
+    );
+    app.update();
+
+    assert!(app.world().resource::<PathSnapshot>().generation() > generation);
+    assert!(searching(app.world()).is_some());
+    assert!(app.world().resource::<PathCache>().get(goal).is_none());
+
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(pawn).unwrap().path().is_some()
+    });
+    assert!(app.world().resource::<PathCache>().get(goal).is_some());
+
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(pawn).unwrap().is_finished()
+    });
+    assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), goal);
+}
+
+#[test]
+fn pawn_avoids_crowded_corridor() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+    for x in 0..=14 {
+        set_material(&mut app, TilePosition::new(layer, x, 0), TileMaterial::WALL);
+        set_material(&mut app, TilePosition::new(layer, x, 4), TileMaterial::WALL);
+    }
+    for y in 1..4 {
+        set_material(&mut app, TilePosition::new(layer, 0, y), TileMaterial::WALL);
+        set_material(
+            &mut app,
+            TilePosition::new(layer, 14, y),
+            TileMaterial::WALL,
+        );
+    }
+    for x in 3..=11 {
+        set_material(&mut app, TilePosition::new(layer, x, 2), TileMaterial::WALL);
+    }
+
+    let west = TilePosition::new(layer, 1, 1);
+    let east = TilePosition::new(layer, 13, 1);
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(1.5, 1.5));
+    let walk_to = |app: &mut App, target: TilePosition| {
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(target);
+        let mut took_upper = false;
+        run_until(app, |world| {
+            took_upper |= world.get::<TilePosition>(pawn).unwrap().y() == 3;
+            world.get::<PawnPath>(pawn).unwrap().is_finished()
+        });
+        assert_eq!(*app.world().get::<TilePosition>(pawn).unwrap(), target);
+        took_upper
+    };
+
+    assert!(!walk_to(&mut app, east));
+
+    for x in 4..=10 {
+        spawn_pawn(&mut app, layer, Vec2::new(x as f32 + 0.5, 1.5));
+    }
+    assert!(walk_to(&mut app, west));
+}
+
+#[test]
+fn pawns_share_goal_field() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    let goal = TilePosition::new(layer, 8, 0);
+    let first = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+    app.update();
+    assert!(app.world().resource::<PathCache>().get(goal).is_none());
+
+    app.world_mut()
+        .get_mut::<PawnPath>(first)
+        .unwrap()
+        .set_target(goal);
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(first).unwrap().path().is_some()
+    });
+
+    let first_field = goal_cost_field(&app, first);
+    let cached = app.world().resource::<PathCache>().get(goal).unwrap();
+    assert!(Arc::ptr_eq(&first_field, cached.cost_field()));
+
+    app.world_mut().entity_mut(first).despawn();
+    let second = spawn_pawn(&mut app, layer, Vec2::new(0.5, 3.5));
+
+    app.world_mut()
+        .get_mut::<PawnPath>(second)
+        .unwrap()
+        .set_target(goal);
+    run_until(&mut app, |world| {
+        world.get::<PawnPath>(second).unwrap().path().is_some()
+    });
+
+    let second_field = goal_cost_field(&app, second);
+    assert!(Arc::ptr_eq(&first_field, &second_field));
+    assert_eq!(app.world().resource::<PathCache>().len(), 1);
+}
+
+#[test]
+fn pawns_share_goal_field_in_crowd() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+    fill_empty(&mut app, layer);
+
+    for i in -4..=4 {
+        for position in [
+            TilePosition::new(layer, 4, i),
+            TilePosition::new(layer, 12, i),
+            TilePosition::new(layer, 8 + i, -4),
+            TilePosition::new(layer, 8 + i, 4),
+        ] {
+            set_material(&mut app, position, TileMaterial::WALL);
+        }
+    }
+    let door_position = TilePosition::new(layer, 4, 0);
+    set_material(&mut app, door_position, TileMaterial::DOOR);
+    app.world_mut()
+        .spawn((Door::default(), door_position, ChildOf(layer)));
+
+    let goal = TilePosition::new(layer, 8, 0);
+    for x in 6..=10 {
+        spawn_pawn(&mut app, layer, Vec2::new(x as f32 + 0.5, 1.5));
+    }
+    let first = spawn_pawn(&mut app, layer, Vec2::new(1.5, 0.5));
+    let second = spawn_pawn(&mut app, layer, Vec2::new(1.5, 3.5));
+    app.update();
+
+    let walk_to_goal = |app: &mut App, pawn: Entity| {
+        app.world_mut()
+            .get_mut::<PawnPath>(pawn)
+            .unwrap()
+            .set_target(goal);
+        run_until(app, |world| {
+            world.get::<PawnPath>(pawn).unwrap().path().is_some()
+        });
+        goal_cost_field(app, pawn)
+    };
+
+    let first_field = walk_to_goal(&mut app, first);
+    let cached = app.world().resource::<PathCache>().get(goal).unwrap();
+    assert!(Arc::ptr_eq(&first_field, cached.cost_field()));
+
+    let second_field = walk_to_goal(&mut app, second);
+    assert!(Arc::ptr_eq(&first_field, &second_field));
+    assert_eq!(app.world().resource::<PathCache>().len(), 1);
+}
+
+fn make_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        PhysicsPlugin,
+        WorldPlugin,
+    ));
+
+    let timestep = Duration::from_millis(50);
+    app.insert_resource(Time::<Fixed>::from_duration(timestep));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    app
+}
+
+fn fill_empty(app: &mut App, layer: Entity) {
+    for x in [-1, 0] {
+        for y in [-1, 0] {
+            set_material(
+                app,
+                TilePosition::new(layer, x * 32, y * 32),
+                TileMaterial::EMPTY,
+            );
+        }
+    }
+}
+
+fn spawn_pawn(app: &mut App, layer: Entity, position: Vec2) -> Entity {
+    app.world_mut()
+        .spawn((
+            Pawn::default(),
+            ChildOf(layer),
+            Position::new(position, Rot2::IDENTITY),
+        ))
+        .id()
+}
+
+fn goal_cost_field(app: &App, pawn: Entity) -> Arc<CostField> {
+    let path = app.world().get::<PawnPath>(pawn).unwrap().path().unwrap();
+    match path.steps().first() {
+        Some(PathStep::RegionCostField { cost_field, .. }) => cost_field.clone(),
+        step => panic!("expected RegionCostField, got {step:?}"),
+    }
+}
+
+fn set_material(app: &mut App, position: TilePosition, material: TileMaterial) {
+    app.world_mut()
+        .run_system_once(move |mut storage: TileStorageMut| {
+            storage.set_material(position, material);
+        })
+        .unwrap();
+}
+
+fn run_until(app: &mut App, mut condition: impl FnMut(&World) -> bool) {
+    for _ in 0..1000 {
+        app.update();
+        if condition(app.world()) {
+            return;
+        }
+    }
+
+    panic!("condition not reached");
+}
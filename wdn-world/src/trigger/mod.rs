@@ -0,0 +1,173 @@
+#[cfg(test)]
+mod tests;
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use bevy_platform::collections::HashSet;
+
+use wdn_physics::{
+    PhysicsSystems,
+    collision::{
+        Collider, CollisionTarget, Collisions,
+        filter::{CollisionFilter, CollisionGroups},
+        shape::ColliderShape,
+    },
+    kinematics::Position,
+    tile::{index::TileIndex, position::TilePosition},
+};
+
+use crate::{WorldSystems, pawn::Pawn};
+
+pub struct TriggerPlugin;
+
+#[derive(Component, Clone, Debug, Default)]
+pub struct Trigger {
+    occupants: Vec<Entity>,
+}
+
+#[derive(Component, Clone, Debug, Default)]
+#[require(Trigger)]
+pub struct TileTrigger {
+    tiles: HashSet<TilePosition>,
+}
+
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub struct TriggerEntered {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+#[derive(Message, Clone, Copy, Debug, PartialEq)]
+pub struct TriggerExited {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+pub fn update_triggers(
+    mut triggers: Query<(
+        Entity,
+        &mut Trigger,
+        Option<&TileTrigger>,
+        Option<&Collisions>,
+    )>,
+    pawns: Query<(), With<Pawn>>,
+    index: Res<TileIndex>,
+    mut entered_writer: MessageWriter<TriggerEntered>,
+    mut exited_writer: MessageWriter<TriggerExited>,
+) {
+    for (id, mut trigger, tiles, collisions) in &mut triggers {
+        let mut occupants = Vec::new();
+        if let Some(tiles) = tiles {
+            occupants.extend(
+                tiles
+                    .tiles
+                    .iter()
+                    .flat_map(|&tile| index.get_objects(tile).iter().copied())
+                    .filter(|&entity| pawns.contains(entity)),
+            );
+        }
+        if let Some(collisions) = collisions {
+            occupants.extend(
+                collisions
+                    .iter()
+                    .filter_map(|collision| match collision.target {
+                        CollisionTarget::Collider { id, .. } => Some(id),
+                        CollisionTarget::Tile { .. } => None,
+                    })
+                    .filter(|&entity| pawns.contains(entity)),
+            );
+        }
+        occupants.sort_unstable();
+        occupants.dedup();
+
+        if occupants == trigger.occupants {
+            continue;
+        }
+
+        entered_writer.write_batch(
+            occupants
+                .iter()
+                .filter(|entity| trigger.occupants.binary_search(entity).is_err())
+                .map(|&entity| TriggerEntered {
+                    trigger: id,
+                    entity,
+                }),
+        );
+        exited_writer.write_batch(
+            trigger
+                .occupants
+                .iter()
+                .filter(|entity| occupants.binary_search(entity).is_err())
+                .map(|&entity| TriggerExited {
+                    trigger: id,
+                    entity,
+                }),
+        );
+
+        trigger.occupants = occupants;
+    }
+}
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<TriggerEntered>()
+            .add_message::<TriggerExited>();
+
+        app.configure_sets(
+            FixedUpdate,
+            WorldSystems::UpdateTriggers.after(PhysicsSystems::Kinematics),
+        );
+
+        app.add_systems(
+            FixedUpdate,
+            update_triggers.in_set(WorldSystems::UpdateTriggers),
+        );
+    }
+}
+
+impl Trigger {
+    pub const FILTER: CollisionFilter = CollisionFilter::new(
+        CollisionGroups::SENSOR,
+        CollisionGroups::DEFAULT
+            .union(CollisionGroups::PRISONER)
+            .union(CollisionGroups::STAFF),
+    );
+
+    pub fn bundle(shape: ColliderShape, position: Vec2, rotation: Rot2) -> impl Bundle {
+        (
+            Trigger::default(),
+            Collider::from_shape(shape, false),
+            Trigger::FILTER,
+            Position::new(position, rotation),
+        )
+    }
+
+    pub fn occupants(&self) -> &[Entity] {
+        &self.occupants
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.occupants.binary_search(&entity).is_ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.occupants.is_empty()
+    }
+}
+
+impl TileTrigger {
+    pub fn new(tiles: impl IntoIterator<Item = TilePosition>) -> Self {
+        TileTrigger {
+            tiles: tiles.into_iter().collect(),
+        }
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = TilePosition> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    pub fn contains(&self, tile: TilePosition) -> bool {
+        self.tiles.contains(&tile)
+    }
+}
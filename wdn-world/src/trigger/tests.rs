@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use bevy_app::prelude::*;
+use bevy_ecs::{message::MessageCursor, prelude::*};
+use bevy_math::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy, prelude::*};
+
+use wdn_physics::{
+    PhysicsPlugin,
+    collision::{Collider, shape::ColliderShape},
+    kinematics::Position,
+    layer::Layer,
+    tile::position::TilePosition,
+};
+
+use crate::{
+    pawn::Pawn,
+    trigger::{TileTrigger, Trigger, TriggerEntered, TriggerExited, TriggerPlugin},
+};
+
+#[test]
+fn tile_trigger() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+
+    let trigger = app
+        .world_mut()
+        .spawn(TileTrigger::new([
+            TilePosition::new(layer, 2, 0),
+            TilePosition::new(layer, 3, 0),
+        ]))
+        .id();
+    let pawn = spawn_pawn(&mut app, layer, Vec2::new(0.5, 0.5));
+
+    let mut entered_cursor = MessageCursor::<TriggerEntered>::default();
+    let mut exited_cursor = MessageCursor::<TriggerExited>::default();
+
+    app.update();
+
+    assert!(app.world().get::<Trigger>(trigger).unwrap().is_empty());
+    assert_eq!(read(&app, &mut entered_cursor).len(), 0);
+
+    move_pawn(&mut app, pawn, Vec2::new(2.5, 0.5));
+    app.update();
+
+    assert!(app.world().get::<Trigger>(trigger).unwrap().contains(pawn));
+    assert_eq!(
+        read(&app, &mut entered_cursor),
+        vec![TriggerEntered {
+            trigger,
+            entity: pawn
+        }]
+    );
+
+    move_pawn(&mut app, pawn, Vec2::new(3.5, 0.5));
+    app.update();
+
+    assert!(app.world().get::<Trigger>(trigger).unwrap().contains(pawn));
+    assert_eq!(read(&app, &mut entered_cursor).len(), 0);
+    assert_eq!(read(&app, &mut exited_cursor).len(), 0);
+
+    move_pawn(&mut app, pawn, Vec2::new(4.5, 0.5));
+    app.update();
+
+    assert!(app.world().get::<Trigger>(trigger).unwrap().is_empty());
+    assert_eq!(
+        read(&app, &mut exited_cursor),
+        vec![TriggerExited {
+            trigger,
+            entity: pawn
+        }]
+    );
+}
+
+#[test]
+fn shape_trigger() {
+    let mut app = make_app();
+    let layer = app.world_mut().spawn(Layer::default()).id();
+
+    let trigger = app
+        .world_mut()
+        .spawn((
+            Trigger::bundle(
+                ColliderShape::Rect {
+                    half_size: Vec2::new(1.0, 0.5),
+                },
+                Vec2::new(5.0, 5.0),
+                Rot2::IDENTITY,
+            ),
+            ChildOf(layer),
+        ))
+        .id();
+    let inside = spawn_pawn(&mut app, layer, Vec2::new(5.8, 5.2));
+    spawn_pawn(&mut app, layer, Vec2::new(5.0, 6.5));
+    let bystander = app
+        .world_mut()
+        .spawn((
+            Collider::new(0.2, true),
+            Position::new(Vec2::new(4.5, 5.0), Rot2::IDENTITY),
+            ChildOf(layer),
+        ))
+        .id();
+
+    let mut entered_cursor = MessageCursor::<TriggerEntered>::default();
+
+    app.update();
+
+    let occupants = app.world().get::<Trigger>(trigger).unwrap();
+    assert_eq!(occupants.occupants(), &[inside]);
+    assert!(!occupants.contains(bystander));
+    assert_eq!(
+        read(&app, &mut entered_cursor),
+        vec![TriggerEntered {
+            trigger,
+            entity: inside
+        }]
+    );
+}
+
+fn make_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TimePlugin,
+        PhysicsPlugin,
+        TriggerPlugin,
+    ));
+
+    app.insert_resource(Time::<Fixed>::from_duration(Duration::from_secs(1)));
+    app.insert_resource(Time::<Virtual>::from_max_delta(Duration::MAX));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)));
+
+    app.world_mut()
+        .resource_mut::<Time<Real>>()
+        .update_with_duration(Duration::ZERO);
+
+    app
+}
+
+fn spawn_pawn(app: &mut App, layer: Entity, position: Vec2) -> Entity {
+    app.world_mut()
+        .spawn((
+            Pawn::default(),
+            Position::new(position, Rot2::IDENTITY),
+            ChildOf(layer),
+        ))
+        .id()
+}
+
+fn move_pawn(app: &mut App, pawn: Entity, position: Vec2) {
+    app.world_mut()
+        .entity_mut(pawn)
+        .insert(Position::new(position, Rot2::IDENTITY));
+}
+
+fn read<M: Message + Clone>(app: &App, cursor: &mut MessageCursor<M>) -> Vec<M> {
+    cursor
+        .read(app.world().resource::<Messages<M>>())
+        .cloned()
+        .collect()
+}